@@ -1,8 +0,0 @@
-export var Direction;
-(function (Direction) {
-    Direction["UP"] = "UP";
-    Direction["DOWN"] = "DOWN";
-    Direction["LEFT"] = "LEFT";
-    Direction["RIGHT"] = "RIGHT";
-})(Direction || (Direction = {}));
-//# sourceMappingURL=types.js.map
\ No newline at end of file
@@ -0,0 +1,271 @@
+import {
+  BodySegment,
+  BotDifficulty,
+  Direction,
+  EdgeBehaviour,
+  Food,
+  MapLayout,
+  PositionCoords,
+  Zone,
+} from "./types.js";
+import { BOUNDARY_THICKNESS, isBoundary, isWall } from "./grid.js";
+import { isInsideZone } from "./zone.js";
+
+export const MAX_BOTS = 8;
+export const BOT_NAMES = [
+  "Noodle", "Slinky", "Ziggy", "Pretzel", "Wiggles", "Sir Hiss", "Kaa", "Monty",
+];
+// The same colours players pick from in the client
+export const BOT_COLOURS = [
+  "#24b058", "#f19f4e", "#3f99ff", "#e05fd0", "#f5d547", "#4fd8e8", "#b48cff", "#f95c8a",
+];
+
+interface BotSkill {
+  floodFill: boolean; // checks that a move leaves enough room to turn around in
+  avoidHeads: boolean; // keeps out of the cells other heads can reach next
+  mistakeChance: number; // chance of taking any open move instead of thinking
+  weighsFood: boolean; // goes for the best food nearby instead of the closest
+}
+
+const BOT_SKILLS: Record<BotDifficulty, BotSkill> = {
+  [BotDifficulty.EASY]: { floodFill: false, avoidHeads: false, mistakeChance: 0.1, weighsFood: false },
+  [BotDifficulty.NORMAL]: { floodFill: true, avoidHeads: false, mistakeChance: 0.02, weighsFood: false },
+  [BotDifficulty.HARD]: { floodFill: true, avoidHeads: true, mistakeChance: 0, weighsFood: true },
+};
+
+const DIRECTIONS = [Direction.UP, Direction.DOWN, Direction.LEFT, Direction.RIGHT];
+const OPPOSITE: Record<Direction, Direction> = {
+  [Direction.UP]: Direction.DOWN,
+  [Direction.DOWN]: Direction.UP,
+  [Direction.LEFT]: Direction.RIGHT,
+  [Direction.RIGHT]: Direction.LEFT,
+};
+const DELTAS: Record<Direction, PositionCoords> = {
+  [Direction.UP]: { x: 0, y: -1 },
+  [Direction.DOWN]: { x: 0, y: 1 },
+  [Direction.LEFT]: { x: -1, y: 0 },
+  [Direction.RIGHT]: { x: 1, y: 0 },
+};
+
+// Blocked cells of the whole grid, boundary included, one byte per cell
+export interface Occupancy {
+  width: number;
+  height: number;
+  blocked: Uint8Array;
+}
+
+// Everything a bot looks at to pick its next move
+export interface BotView {
+  occupancy: Occupancy;
+  body: BodySegment[]; // the bot's own body, head last
+  food: Food[];
+  otherHeads: PositionCoords[];
+  difficulty: BotDifficulty;
+}
+
+function cellIndex(occupancy: Occupancy, position: PositionCoords): number {
+  return position.y * occupancy.width + position.x;
+}
+
+function isBlocked(occupancy: Occupancy, position: PositionCoords): boolean {
+  return occupancy.blocked[cellIndex(occupancy, position)] === 1;
+}
+
+// One cell on in the given direction, snakes wrap around the grid like on the client
+export function stepPosition(
+  occupancy: Occupancy,
+  position: PositionCoords,
+  direction: Direction
+): PositionCoords {
+  const delta = DELTAS[direction];
+  return {
+    x: (position.x + delta.x + occupancy.width) % occupancy.width,
+    y: (position.y + delta.y + occupancy.height) % occupancy.height,
+  };
+}
+
+// Walls, solid edges, cells outside the zone and every snake body are blocked.
+// Tails are left open since they move away on the snake's next step.
+export function buildOccupancy(
+  layout: MapLayout,
+  zone: Zone | null,
+  bodies: BodySegment[][]
+): Occupancy {
+  const width = layout.width + BOUNDARY_THICKNESS * 2;
+  const height = layout.height + BOUNDARY_THICKNESS * 2;
+  const occupancy: Occupancy = { width, height, blocked: new Uint8Array(width * height) };
+  for (let y = 0; y < height; y++) {
+    for (let x = 0; x < width; x++) {
+      const solidEdge = layout.edge === EdgeBehaviour.SOLID && isBoundary(layout, x, y);
+      const outsideZone = zone !== null && !isInsideZone(zone, x, y);
+      if (solidEdge || outsideZone || isWall(layout, x, y)) {
+        occupancy.blocked[y * width + x] = 1;
+      }
+    }
+  }
+  bodies.forEach((body) => {
+    // On the wire the tail is the second to last segment, right before the head
+    const tail = body.length > 1 ? body[body.length - 2] : null;
+    body
+      .filter((segment) => segment !== tail)
+      .forEach((segment) => {
+        if (segment.x >= 0 && segment.x < width && segment.y >= 0 && segment.y < height) {
+          occupancy.blocked[cellIndex(occupancy, segment)] = 1;
+        }
+      });
+  });
+  return occupancy;
+}
+
+// Free cells reachable from the start, counting stops once the limit is reached
+function reachableCells(occupancy: Occupancy, start: PositionCoords, limit: number): number {
+  const seen = new Uint8Array(occupancy.blocked.length);
+  const queue = [start];
+  seen[cellIndex(occupancy, start)] = 1;
+  let count = 0;
+  while (queue.length > 0 && count < limit) {
+    const position = queue.shift();
+    count += 1;
+    DIRECTIONS.forEach((direction) => {
+      const next = stepPosition(occupancy, position, direction);
+      const index = cellIndex(occupancy, next);
+      if (!seen[index] && !isBlocked(occupancy, next)) {
+        seen[index] = 1;
+        queue.push(next);
+      }
+    });
+  }
+  return count;
+}
+
+// Breadth first search from the head. Returns the first move towards the
+// food worth the most for the distance, or the closest food for bots that
+// don't weigh it. Poison is never a target.
+function directionToFood(
+  view: BotView,
+  head: PositionCoords,
+  moves: Direction[],
+  skill: BotSkill
+): Direction | null {
+  const { occupancy } = view;
+  const firstMove = new Array<Direction | null>(occupancy.blocked.length).fill(null);
+  const distance = new Int32Array(occupancy.blocked.length).fill(-1);
+  const queue: PositionCoords[] = [];
+  moves.forEach((direction) => {
+    const next = stepPosition(occupancy, head, direction);
+    const index = cellIndex(occupancy, next);
+    if (distance[index] === -1) {
+      distance[index] = 1;
+      firstMove[index] = direction;
+      queue.push(next);
+    }
+  });
+  while (queue.length > 0) {
+    const position = queue.shift();
+    const index = cellIndex(occupancy, position);
+    DIRECTIONS.forEach((direction) => {
+      const next = stepPosition(occupancy, position, direction);
+      const nextIndex = cellIndex(occupancy, next);
+      if (distance[nextIndex] === -1 && !isBlocked(occupancy, next)) {
+        distance[nextIndex] = distance[index] + 1;
+        firstMove[nextIndex] = firstMove[index];
+        queue.push(next);
+      }
+    });
+  }
+
+  let best: Direction | null = null;
+  let bestValue = -Infinity;
+  view.food
+    .filter((food) => food.growth > 0)
+    .forEach((food) => {
+      const index = cellIndex(occupancy, food);
+      if (distance[index] === -1) {
+        return;
+      }
+      const value = skill.weighsFood
+        ? Math.max(food.score, 1) / distance[index]
+        : -distance[index];
+      if (value > bestValue) {
+        bestValue = value;
+        best = firstMove[index];
+      }
+    });
+  return best;
+}
+
+// Pick the bot's next move: head for food while that leaves enough room to
+// move around in, otherwise go wherever there is the most room
+export function chooseDirection(view: BotView): Direction {
+  const { occupancy, body } = view;
+  const skill = BOT_SKILLS[view.difficulty];
+  const head = body[body.length - 1];
+  const moves = DIRECTIONS.filter(
+    (direction) =>
+      (body.length === 1 || direction !== OPPOSITE[head.direction]) &&
+      !isBlocked(occupancy, stepPosition(occupancy, head, direction))
+  );
+  if (moves.length === 0) {
+    return head.direction;
+  }
+  if (Math.random() < skill.mistakeChance) {
+    return moves[Math.floor(Math.random() * moves.length)];
+  }
+
+  const nearOtherHead = (direction: Direction) => {
+    const next = stepPosition(occupancy, head, direction);
+    return view.otherHeads.some((other) =>
+      DIRECTIONS.some((otherDirection) => {
+        const reach = stepPosition(occupancy, other, otherDirection);
+        return reach.x === next.x && reach.y === next.y;
+      })
+    );
+  };
+  // Enough room means space for the whole body and then some
+  const roomNeeded = body.length + 2;
+  const room = new Map<Direction, number>(
+    moves.map((direction) => [
+      direction,
+      skill.floodFill
+        ? reachableCells(occupancy, stepPosition(occupancy, head, direction), roomNeeded)
+        : roomNeeded,
+    ])
+  );
+  const safeMoves = moves.filter(
+    (direction) =>
+      room.get(direction) >= roomNeeded && !(skill.avoidHeads && nearOtherHead(direction))
+  );
+
+  const towardsFood = directionToFood(view, head, safeMoves, skill);
+  if (towardsFood !== null) {
+    return towardsFood;
+  }
+  // Nothing to eat safely, so wander, or squeeze into the roomiest gap when
+  // every move is tight. Going straight is preferred over turning.
+  const mostRoom = Math.max(...moves.map((direction) => room.get(direction)));
+  const candidates =
+    safeMoves.length > 0
+      ? safeMoves
+      : moves.filter((direction) => room.get(direction) === mostRoom);
+  return candidates.includes(head.direction)
+    ? head.direction
+    : candidates[Math.floor(Math.random() * candidates.length)];
+}
+
+// Move a snake one cell in the same body layout the clients send: the
+// body from neck to tail followed by the head
+export function moveSnake(
+  occupancy: Occupancy,
+  segments: BodySegment[],
+  direction: Direction,
+  grow: boolean
+): BodySegment[] {
+  const head = segments[segments.length - 1];
+  const body = segments.slice(0, -1);
+  const newHead = { ...stepPosition(occupancy, head, direction), direction };
+  const newBody = [{ x: head.x, y: head.y, direction }, ...body];
+  if (!grow) {
+    newBody.pop();
+  }
+  return [...newBody, newHead];
+}
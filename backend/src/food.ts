@@ -0,0 +1,68 @@
+import { Food, FoodKind, PositionCoords } from "./types.js";
+
+interface FoodKindInfo {
+  growth: number; // segments gained, negative for segments lost
+  score: number;
+  lifetimeMs: number | null; // null for food that stays until eaten
+  weight: number; // how often the kind spawns relative to the others
+}
+
+const FOOD_KINDS: Record<FoodKind, FoodKindInfo> = {
+  [FoodKind.NORMAL]: { growth: 1, score: 1, lifetimeMs: null, weight: 10 },
+  [FoodKind.GOLDEN]: { growth: 3, score: 5, lifetimeMs: 8000, weight: 2 },
+  [FoodKind.POISON]: { growth: -2, score: -2, lifetimeMs: 15000, weight: 2 },
+  // Worth the most when fresh, the score drops as it ages
+  [FoodKind.DECAYING]: { growth: 2, score: 4, lifetimeMs: 8000, weight: 3 },
+  // Only dropped by boosting snakes, never spawned
+  [FoodKind.PELLET]: { growth: 1, score: 0, lifetimeMs: 20000, weight: 0 },
+};
+
+export function createFood(kind: FoodKind, position: PositionCoords): Food {
+  const info = FOOD_KINDS[kind];
+  return {
+    x: position.x,
+    y: position.y,
+    kind: kind,
+    growth: info.growth,
+    score: info.score,
+    expires_at: info.lifetimeMs === null ? null : Date.now() + info.lifetimeMs,
+  };
+}
+
+export function randomFoodKind(): FoodKind {
+  const kinds = Object.values(FoodKind);
+  const totalWeight = kinds.reduce((total, kind) => total + FOOD_KINDS[kind].weight, 0);
+  let roll = Math.random() * totalWeight;
+  for (const kind of kinds) {
+    roll -= FOOD_KINDS[kind].weight;
+    if (roll < 0) {
+      return kind;
+    }
+  }
+  return FoodKind.NORMAL;
+}
+
+// Drop expired food and decay the rest, returns whether anything changed
+export function updateFood(foods: Food[], now: number): boolean {
+  let changed = false;
+  for (let i = foods.length - 1; i >= 0; i--) {
+    const food = foods[i];
+    if (food.expires_at === null) {
+      continue;
+    }
+    if (now >= food.expires_at) {
+      foods.splice(i, 1);
+      changed = true;
+      continue;
+    }
+    if (food.kind === FoodKind.DECAYING) {
+      const info = FOOD_KINDS[food.kind];
+      const score = Math.max(1, Math.ceil((info.score * (food.expires_at - now)) / info.lifetimeMs));
+      if (score !== food.score) {
+        food.score = score;
+        changed = true;
+      }
+    }
+  }
+  return changed;
+}
@@ -0,0 +1,58 @@
+import { EdgeBehaviour, MapLayout, PositionCoords, Rect } from "./types.js";
+
+// Cells drawn around the map, grid coordinates start at 0 on the boundary
+// while map coordinates start at 0 inside it
+export const BOUNDARY_THICKNESS = 1;
+
+export function containsCell(rect: Rect, x: number, y: number): boolean {
+  return x >= rect.x && x < rect.x + rect.width && y >= rect.y && y < rect.y + rect.height;
+}
+
+export function isBoundary(layout: MapLayout, x: number, y: number): boolean {
+  return (
+    x < BOUNDARY_THICKNESS ||
+    x >= layout.width + BOUNDARY_THICKNESS ||
+    y < BOUNDARY_THICKNESS ||
+    y >= layout.height + BOUNDARY_THICKNESS
+  );
+}
+
+export function isWall(layout: MapLayout, x: number, y: number): boolean {
+  return layout.walls.some((wall) =>
+    containsCell(wall, x - BOUNDARY_THICKNESS, y - BOUNDARY_THICKNESS)
+  );
+}
+
+// Cells a snake dies on
+export function isDeadly(layout: MapLayout, x: number, y: number): boolean {
+  return isWall(layout, x, y) || (layout.edge === EdgeBehaviour.SOLID && isBoundary(layout, x, y));
+}
+
+// Grid cells in the map's food zones, or anywhere if it has none, that are not walls
+export function foodCells(layout: MapLayout): PositionCoords[] {
+  const zones: Rect[] =
+    layout.food_zones.length > 0
+      ? layout.food_zones
+      : [{ x: 0, y: 0, width: layout.width, height: layout.height }];
+  const cells: PositionCoords[] = [];
+  zones.forEach((rect) => {
+    for (let y = rect.y; y < rect.y + rect.height; y++) {
+      for (let x = rect.x; x < rect.x + rect.width; x++) {
+        const cell = { x: x + BOUNDARY_THICKNESS, y: y + BOUNDARY_THICKNESS };
+        if (!isWall(layout, cell.x, cell.y)) {
+          cells.push(cell);
+        }
+      }
+    }
+  });
+  return cells;
+}
+
+export function randomCell(cells: PositionCoords[]): PositionCoords {
+  return cells[Math.floor(Math.random() * cells.length)];
+}
+
+// A random cell for food or a power-up, maps always have at least one
+export function getRandomPosition(layout: MapLayout): PositionCoords {
+  return randomCell(foodCells(layout));
+}
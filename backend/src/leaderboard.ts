@@ -0,0 +1,91 @@
+import fs from "node:fs";
+import {
+  Leaderboard,
+  LeaderboardEntry,
+  LeaderboardPeriod,
+} from "./types.js";
+
+const LEADERBOARD_FILE = process.env.LEADERBOARD_FILE ?? "leaderboard.json";
+const LEADERBOARD_SIZE = 10;
+// Entries kept on disk besides today's, enough to answer the all-time view
+const KEPT_ENTRIES = 100;
+
+let entries: LeaderboardEntry[] = loadEntries();
+
+function loadEntries(): LeaderboardEntry[] {
+  try {
+    const data = JSON.parse(fs.readFileSync(LEADERBOARD_FILE, "utf8"));
+    return Array.isArray(data) ? data : [];
+  } catch (error) {
+    if (error.code !== "ENOENT") {
+      console.error("Error reading leaderboard file:", error);
+    }
+    return [];
+  }
+}
+
+// One write at a time, to a temporary file that then replaces the old one,
+// so the file is never left half written. Changes made during a write are
+// saved once it is done.
+let writing = false;
+let changedWhileWriting = false;
+
+function saveEntries() {
+  if (writing) {
+    changedWhileWriting = true;
+    return;
+  }
+  writing = true;
+  const temporaryFile = `${LEADERBOARD_FILE}.tmp`;
+  fs.writeFile(temporaryFile, JSON.stringify(entries), (writeError) => {
+    const done = (error: NodeJS.ErrnoException | null) => {
+      if (error) {
+        console.error("Error writing leaderboard file:", error);
+      }
+      writing = false;
+      if (changedWhileWriting) {
+        changedWhileWriting = false;
+        saveEntries();
+      }
+    };
+    if (writeError) {
+      done(writeError);
+      return;
+    }
+    fs.rename(temporaryFile, LEADERBOARD_FILE, done);
+  });
+}
+
+function startOfToday(): number {
+  const now = new Date();
+  return Date.UTC(now.getUTCFullYear(), now.getUTCMonth(), now.getUTCDate());
+}
+
+function byScore(a: LeaderboardEntry, b: LeaderboardEntry): number {
+  return b.score - a.score || a.achieved_at - b.achieved_at;
+}
+
+export function recordScore(entry: LeaderboardEntry) {
+  if (entry.score <= 0) {
+    return;
+  }
+  entries.push(entry);
+  // Keep every entry from today for the daily view and the best of the rest
+  const today = startOfToday();
+  const best = entries.filter((e) => e.achieved_at < today).sort(byScore);
+  entries = entries
+    .filter((e) => e.achieved_at >= today)
+    .concat(best.slice(0, KEPT_ENTRIES));
+  saveEntries();
+}
+
+export function getLeaderboard(period: LeaderboardPeriod): Leaderboard {
+  const since = period === LeaderboardPeriod.DAILY ? startOfToday() : 0;
+  return {
+    period,
+    entries: entries
+      .filter((entry) => entry.achieved_at >= since)
+      .sort(byScore)
+      .slice(0, LEADERBOARD_SIZE),
+  };
+}
@@ -0,0 +1,57 @@
+import { test } from "node:test";
+import assert from "node:assert/strict";
+import { MapParseError, parseMap } from "./mapfile.js";
+import { Direction, MapLayout } from "./types.js";
+
+// Same cases as the tests of map_file.rs in the shared crate, both have to agree
+const ROW = "..........";
+
+// An ASCII map with the headers on lines 2 and 3, its rows start on line 5
+function asciiMap(rows: string[]): string {
+  return `snake-map 1\nname: Test\nedge: wrap\n\n${rows.join("\n")}\n`;
+}
+
+function errorAt(text: string): [number, number] {
+  const result = parseMap(text);
+  assert.ok(result instanceof MapParseError, "expected the map to be rejected");
+  return [result.line, result.column];
+}
+
+test("ASCII rows become rects", () => {
+  const map = parseMap(asciiMap(["##..ff..>.", ...Array(9).fill(ROW)])) as MapLayout;
+  assert.equal(map.width, 10);
+  assert.equal(map.height, 10);
+  assert.deepEqual(map.walls, [{ x: 0, y: 0, width: 2, height: 1 }]);
+  assert.deepEqual(map.food_zones, [{ x: 4, y: 0, width: 2, height: 1 }]);
+  assert.deepEqual(map.spawn_points, [{ x: 8, y: 0, direction: Direction.RIGHT }]);
+});
+
+test("unknown cells are reported in characters", () => {
+  assert.deepEqual(errorAt(asciiMap([ROW, ROW, "....x....."])), [7, 5]);
+  // Ten characters wide, but not ten UTF-16 code units
+  assert.deepEqual(errorAt(asciiMap([ROW, "..🐍......."])), [6, 3]);
+});
+
+test("ragged rows are reported where they end", () => {
+  assert.deepEqual(errorAt(asciiMap([ROW, ROW, "........."])), [7, 10]);
+  assert.deepEqual(errorAt(asciiMap([ROW, "..........."])), [6, 11]);
+});
+
+test("unknown headers and values are reported", () => {
+  assert.deepEqual(errorAt("snake-map 1\nname: Test\nsize: 10\n\n..........\n"), [3, 1]);
+  assert.deepEqual(errorAt("snake-map 1\nname: Tëst\nedge:  bouncy\n\n..........\n"), [3, 8]);
+  assert.deepEqual(errorAt("; a comment\nsnake-map one\n"), [2, 11]);
+  assert.deepEqual(errorAt("map 1\n"), [1, 1]);
+});
+
+test("bad JSON is reported in characters", () => {
+  assert.deepEqual(errorAt('{\n  "name": "Test",\n  "width": x\n}'), [3, 12]);
+  assert.deepEqual(errorAt('{"name": "Schlänge 🐍", "width": x}'), [1, 33]);
+});
+
+test("name length is counted in characters", () => {
+  const rows = Array(10).fill(ROW).join("\n");
+  const name = "🐍".repeat(24);
+  assert.ok(!(parseMap(`snake-map 1\nname: ${name}\n\n${rows}\n`) instanceof MapParseError));
+  assert.deepEqual(errorAt(`snake-map 1\nname: ${name}🐍\n\n${rows}\n`), [0, 0]);
+});
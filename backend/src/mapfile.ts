@@ -0,0 +1,299 @@
+import fs from "node:fs";
+import path from "node:path";
+import { Direction, EdgeBehaviour, MapLayout, Rect } from "./types.js";
+import { containsCell, foodCells } from "./grid.js";
+
+// Must match MAP_VERSION, MIN_MAP_SIZE and MAX_MAP_SIZE in the client
+export const MAP_VERSION = 1;
+const MIN_MAP_SIZE = 10;
+const MAX_MAP_SIZE = 60;
+const MAX_MAP_NAME_LENGTH = 24;
+const CLASSIC_SIZE = 30;
+// First line of the ASCII variant, followed by the version
+const ASCII_MAGIC = "snake-map";
+const SPAWN_CELLS: Record<string, Direction> = {
+  "^": Direction.UP,
+  v: Direction.DOWN,
+  "<": Direction.LEFT,
+  ">": Direction.RIGHT,
+};
+
+// Lengths and columns are counted in characters rather than UTF-16 code
+// units, like the client does
+function charLength(text: string): number {
+  return Array.from(text).length;
+}
+
+// Maps rooms can be played on, by name
+export const maps = new Map<string, MapLayout>();
+
+// Line and column start at 1 and are 0 when the problem is not at a
+// single place in the text
+export class MapParseError {
+  constructor(public line: number, public column: number, public message: string) {}
+
+  toString(): string {
+    return this.line === 0
+      ? this.message
+      : `line ${this.line}, column ${this.column}: ${this.message}`;
+  }
+}
+
+// The open square the game was first played on
+export function classicMap(): MapLayout {
+  return {
+    name: "Classic",
+    width: CLASSIC_SIZE,
+    height: CLASSIC_SIZE,
+    edge: EdgeBehaviour.WRAP,
+    walls: [],
+    spawn_points: [],
+    food_zones: [],
+  };
+}
+
+export function findMap(name: string): MapLayout {
+  return maps.get(name) ?? classicMap();
+}
+
+// Parse a map in either the JSON or the ASCII format and check that it can be played
+export function parseMap(text: string): MapLayout | MapParseError {
+  const parsed = text.trimStart().startsWith("{") ? parseJsonMap(text) : parseAsciiMap(text);
+  if (parsed instanceof MapParseError) {
+    return parsed;
+  }
+  const error = validateMap(parsed.version, parsed.layout);
+  return error === null ? parsed.layout : new MapParseError(0, 0, error);
+}
+
+interface VersionedLayout {
+  version: number;
+  layout: MapLayout;
+}
+
+function parseJsonMap(text: string): VersionedLayout | MapParseError {
+  let data: any;
+  try {
+    data = JSON.parse(text);
+  } catch (e) {
+    return jsonSyntaxError(text, e instanceof Error ? e.message : String(e));
+  }
+  const isRects = (value: any) => value === undefined || Array.isArray(value);
+  if (
+    typeof data?.name !== "string" ||
+    !Number.isInteger(data?.width) ||
+    !Number.isInteger(data?.height) ||
+    !isRects(data?.walls) ||
+    !isRects(data?.spawn_points) ||
+    !isRects(data?.food_zones)
+  ) {
+    return new MapParseError(0, 0, "Maps need a name, a width and a height");
+  }
+  return {
+    version: data.version,
+    layout: {
+      name: data.name,
+      width: data.width,
+      height: data.height,
+      edge: data.edge ?? EdgeBehaviour.WRAP,
+      walls: data.walls ?? [],
+      spawn_points: data.spawn_points ?? [],
+      food_zones: data.food_zones ?? [],
+    },
+  };
+}
+
+// Messages from JSON.parse differ between versions of node and often do not
+// say where the problem is. It is at the end of the shortest start of the
+// text that fails for a reason other than running out of text.
+function jsonSyntaxError(text: string, message: string): MapParseError {
+  const failsWithin = (end: number) => {
+    try {
+      JSON.parse(text.slice(0, end));
+      return false;
+    } catch (e) {
+      const failure = e instanceof Error ? e.message : String(e);
+      const position = /position (\d+)/.exec(failure);
+      return position ? Number(position[1]) < end : !failure.includes("end of JSON input");
+    }
+  };
+  let low = 0;
+  let high = text.length;
+  while (low < high) {
+    const middle = Math.floor((low + high) / 2);
+    if (failsWithin(middle)) {
+      high = middle;
+    } else {
+      low = middle + 1;
+    }
+  }
+  const before = text.slice(0, Math.max(low - 1, 0)).split("\n");
+  return new MapParseError(before.length, charLength(before[before.length - 1]) + 1, message);
+}
+
+// The ASCII variant: a `snake-map <version>` line, `key: value` headers, a
+// blank line and then one row of the map per line. `.` is floor, `#` is wall,
+// `f` is floor in a food zone and `^ v < >` are spawn points facing that way.
+// Lines starting with `;` are comments.
+function parseAsciiMap(text: string): VersionedLayout | MapParseError {
+  const lines = text
+    .split("\n")
+    .map((line, index) => ({ number: index + 1, text: line.trimEnd() }))
+    .filter((line) => !line.text.startsWith(";"));
+  if (lines.length === 0 || lines[0].text.length === 0) {
+    return new MapParseError(0, 0, "The map is empty");
+  }
+
+  const magic = lines[0];
+  if (!magic.text.startsWith(ASCII_MAGIC)) {
+    return new MapParseError(magic.number, 1, `Expected \`${ASCII_MAGIC} <version>\``);
+  }
+  const version = Number(magic.text.slice(ASCII_MAGIC.length).trim());
+  if (!Number.isInteger(version)) {
+    return new MapParseError(magic.number, ASCII_MAGIC.length + 2, "Expected a version number");
+  }
+
+  const layout: MapLayout = { ...classicMap(), name: "", width: 0, height: 0 };
+  let index = 1;
+  for (; index < lines.length && lines[index].text.length > 0; index++) {
+    const line = lines[index];
+    const separator = line.text.indexOf(":");
+    if (separator === -1) {
+      return new MapParseError(line.number, 1, "Expected `key: value`");
+    }
+    const key = line.text.slice(0, separator).trim();
+    const rawValue = line.text.slice(separator + 1);
+    const value = rawValue.trim();
+    const valueColumn =
+      charLength(line.text.slice(0, separator)) + 2 + (charLength(rawValue) - charLength(rawValue.trimStart()));
+    if (key === "name") {
+      layout.name = value;
+    } else if (key === "edge") {
+      if (!Object.values(EdgeBehaviour).includes(value as EdgeBehaviour)) {
+        return new MapParseError(line.number, valueColumn, `Unknown edge behaviour \`${value}\``);
+      }
+      layout.edge = value as EdgeBehaviour;
+    } else {
+      return new MapParseError(line.number, 1, `Unknown header \`${key}\``);
+    }
+  }
+
+  // Drop the blank lines around the rows
+  const body = lines.slice(index);
+  const first = body.findIndex((line) => line.text.length > 0);
+  const last = body.map((line) => line.text.length > 0).lastIndexOf(true);
+  const rows = first === -1 ? [] : body.slice(first, last + 1);
+  if (rows.length === 0) {
+    return new MapParseError(0, 0, "The map has no rows");
+  }
+  const width = charLength(rows[0].text);
+  if (width > MAX_MAP_SIZE || rows.length > MAX_MAP_SIZE) {
+    return new MapParseError(0, 0, `Maps are at most ${MAX_MAP_SIZE} by ${MAX_MAP_SIZE} cells`);
+  }
+  for (let y = 0; y < rows.length; y++) {
+    const row = rows[y];
+    const cells = Array.from(row.text);
+    if (cells.length !== width) {
+      return new MapParseError(
+        row.number,
+        Math.min(cells.length, width) + 1,
+        `Expected ${width} cells in every row`
+      );
+    }
+    let wallStart = -1;
+    let zoneStart = -1;
+    // A trailing floor cell closes the runs still open at the end of the row
+    cells.push(".");
+    for (let x = 0; x < cells.length; x++) {
+      const cell = cells[x];
+      if (cell in SPAWN_CELLS) {
+        layout.spawn_points.push({ x, y, direction: SPAWN_CELLS[cell] });
+      } else if (!".#f".includes(cell)) {
+        return new MapParseError(row.number, x + 1, `Unknown cell \`${cell}\``);
+      }
+      // Runs of walls and food zone cells become one rect each
+      if (cell === "#" && wallStart === -1) {
+        wallStart = x;
+      } else if (cell !== "#" && wallStart !== -1) {
+        layout.walls.push({ x: wallStart, y, width: x - wallStart, height: 1 });
+        wallStart = -1;
+      }
+      if (cell === "f" && zoneStart === -1) {
+        zoneStart = x;
+      } else if (cell !== "f" && zoneStart !== -1) {
+        layout.food_zones.push({ x: zoneStart, y, width: x - zoneStart, height: 1 });
+        zoneStart = -1;
+      }
+    }
+  }
+  layout.width = width;
+  layout.height = rows.length;
+  return { version, layout };
+}
+
+// Returns the reason the map can't be played, or null if it can
+function validateMap(version: number, layout: MapLayout): string | null {
+  if (version !== MAP_VERSION) {
+    return `Unsupported map version ${version}, expected ${MAP_VERSION}`;
+  }
+  const nameLength = charLength(layout.name.trim());
+  if (nameLength === 0 || nameLength > MAX_MAP_NAME_LENGTH) {
+    return `Map names must be 1 to ${MAX_MAP_NAME_LENGTH} characters long`;
+  }
+  const validSize = (size: number) => size >= MIN_MAP_SIZE && size <= MAX_MAP_SIZE;
+  if (!validSize(layout.width) || !validSize(layout.height)) {
+    return `Maps are ${MIN_MAP_SIZE} to ${MAX_MAP_SIZE} cells wide and high`;
+  }
+  if (!Object.values(EdgeBehaviour).includes(layout.edge)) {
+    return "Unknown edge behaviour";
+  }
+  const inside = (rect: Rect) =>
+    [rect?.x, rect?.y, rect?.width, rect?.height].every(Number.isInteger) &&
+    rect.width > 0 &&
+    rect.height > 0 &&
+    rect.x >= 0 &&
+    rect.y >= 0 &&
+    rect.x + rect.width <= layout.width &&
+    rect.y + rect.height <= layout.height;
+  const badWall = layout.walls.findIndex((wall) => !inside(wall));
+  if (badWall !== -1) {
+    return `Wall ${badWall + 1} is not inside the map`;
+  }
+  const badZone = layout.food_zones.findIndex((zone) => !inside(zone));
+  if (badZone !== -1) {
+    return `Food zone ${badZone + 1} is not inside the map`;
+  }
+  const badSpawn = layout.spawn_points.findIndex(
+    (spawn) =>
+      !inside({ x: spawn?.x, y: spawn?.y, width: 1, height: 1 }) ||
+      !Object.values(Direction).includes(spawn.direction) ||
+      layout.walls.some((wall) => containsCell(wall, spawn.x, spawn.y))
+  );
+  if (badSpawn !== -1) {
+    return `Spawn point ${badSpawn + 1} is not on a free cell`;
+  }
+  if (foodCells(layout).length === 0) {
+    return "There is nowhere for food to spawn";
+  }
+  return null;
+}
+
+// Load every .json and .txt map in the directory, maps that fail to parse
+// are logged and skipped
+export function loadMaps(directory: string) {
+  if (!fs.existsSync(directory)) {
+    return;
+  }
+  fs.readdirSync(directory)
+    .filter((file) => file.endsWith(".json") || file.endsWith(".txt"))
+    .forEach((file) => {
+      const result = parseMap(fs.readFileSync(path.join(directory, file), "utf8"));
+      if (result instanceof MapParseError) {
+        const location = result.line === 0 ? "" : `:${result.line}:${result.column}`;
+        console.error(`Failed to load map ${file}${location}: ${result.message}`);
+        return;
+      }
+      maps.set(result.name, result);
+    });
+  console.log(`Loaded ${maps.size} maps`);
+}
@@ -0,0 +1,137 @@
+import {
+  Direction,
+  MatchPhase,
+  MatchResult,
+  MatchSpawn,
+  MapLayout,
+  MatchState,
+  PositionCoords,
+  TeamResult,
+} from "./types.js";
+import { BOUNDARY_THICKNESS, randomCell } from "./grid.js";
+import { floorCells, isNearWall, SPAWN_CLEARANCE } from "./spawn.js";
+
+export function createMatchState(): MatchState {
+  return {
+    phase: MatchPhase.WAITING,
+    phaseEndsAt: 0,
+    readyIds: new Set<number>(),
+    participantIds: [],
+    killsAtStart: new Map<number, number>(),
+    results: [],
+    teamResults: [],
+    nextZoneShrinkAt: 0,
+    lastSecondsLeft: 0,
+  };
+}
+
+function directionToCentre(layout: MapLayout, position: PositionCoords): Direction {
+  const dx = BOUNDARY_THICKNESS + layout.width / 2 - position.x;
+  const dy = BOUNDARY_THICKNESS + layout.height / 2 - position.y;
+  if (Math.abs(dx) > Math.abs(dy)) {
+    return dx > 0 ? Direction.RIGHT : Direction.LEFT;
+  }
+  return dy > 0 ? Direction.DOWN : Direction.UP;
+}
+
+// Use the map's spawn points in a random order while they last, then spread
+// the other spawns out, facing the middle of the map. Falls back to any cell
+// that is not a wall or another spawn if the snakes don't fit with full clearance.
+export function computeMatchSpawns(playerIds: number[], layout: MapLayout): MatchSpawn[] {
+  const spawns: MatchSpawn[] = [];
+  const spawnPoints = [...layout.spawn_points].sort(() => Math.random() - 0.5);
+  const min = BOUNDARY_THICKNESS + SPAWN_CLEARANCE;
+  playerIds.forEach((playerId) => {
+    const spawnPoint = spawnPoints.pop();
+    if (spawnPoint) {
+      spawns.push({
+        player_id: playerId,
+        x: spawnPoint.x + BOUNDARY_THICKNESS,
+        y: spawnPoint.y + BOUNDARY_THICKNESS,
+        direction: spawnPoint.direction,
+      });
+      return;
+    }
+    let position: PositionCoords | undefined;
+    for (let attempt = 0; attempt < 100 && position === undefined; attempt++) {
+      const candidate = {
+        x: min + Math.floor(Math.random() * (layout.width - SPAWN_CLEARANCE * 2)),
+        y: min + Math.floor(Math.random() * (layout.height - SPAWN_CLEARANCE * 2)),
+      };
+      const crowded = spawns.some(
+        (spawn) =>
+          Math.max(Math.abs(spawn.x - candidate.x), Math.abs(spawn.y - candidate.y)) <=
+          SPAWN_CLEARANCE * 2
+      );
+      if (!crowded && !isNearWall(layout, candidate, SPAWN_CLEARANCE)) {
+        position = candidate;
+      }
+    }
+    if (position === undefined) {
+      const free = floorCells(layout).filter(
+        (cell) => !spawns.some((spawn) => spawn.x === cell.x && spawn.y === cell.y)
+      );
+      position = randomCell(free.length > 0 ? free : floorCells(layout));
+    }
+    spawns.push({
+      player_id: playerId,
+      x: position.x,
+      y: position.y,
+      direction: directionToCentre(layout, position),
+    });
+  });
+  return spawns;
+}
+
+// Survivors come first, longest snake ahead, then the dead in the
+// reverse order they died in
+export function rankMatchResults(
+  entries: {
+    player_id: number;
+    name: string;
+    team: number | null;
+    kills: number;
+    alive: boolean;
+    length: number;
+    diedAt: number;
+  }[]
+): MatchResult[] {
+  return entries
+    .slice()
+    .sort((a, b) => {
+      if (a.alive !== b.alive) {
+        return a.alive ? -1 : 1;
+      }
+      return a.alive ? b.length - a.length : b.diedAt - a.diedAt;
+    })
+    .map((entry, index) => ({
+      player_id: entry.player_id,
+      name: entry.name,
+      team: entry.team,
+      placement: index + 1,
+      kills: entry.kills,
+      survived: entry.alive,
+    }));
+}
+
+// Add up the results of each team's members
+export function rankTeamResults(results: MatchResult[]): TeamResult[] {
+  const teams = new Map<number, TeamResult>();
+  results.forEach((result) => {
+    if (result.team === null) {
+      return;
+    }
+    const teamResult = teams.get(result.team) ?? {
+      team: result.team,
+      placement: 0,
+      kills: 0,
+      survivors: 0,
+    };
+    teamResult.kills += result.kills;
+    teamResult.survivors += result.survived ? 1 : 0;
+    teams.set(result.team, teamResult);
+  });
+  return Array.from(teams.values())
+    .sort((a, b) => b.survivors - a.survivors || b.kills - a.kills)
+    .map((teamResult, index) => ({ ...teamResult, placement: index + 1 }));
+}
@@ -0,0 +1,17 @@
+import { test } from "node:test";
+import assert from "node:assert/strict";
+import { SHRINK_SEGMENTS, shrinkBody } from "./powerups.js";
+import { playerState } from "./testing.js";
+
+const cells = (body: { x: number; y: number }[]) => body.map(({ x, y }) => [x, y]);
+
+test("a shrink takes segments off the tail end and keeps the head", () => {
+  const body = playerState([[5, 5], [4, 5], [3, 5], [2, 5], [1, 5], [6, 5]]).body_segments;
+  assert.deepEqual(cells(shrinkBody(body, SHRINK_SEGMENTS)), [[5, 5], [4, 5], [6, 5]]);
+});
+
+test("a shrink never takes the head", () => {
+  const body = playerState([[5, 5], [6, 5]]).body_segments;
+  assert.deepEqual(cells(shrinkBody(body, SHRINK_SEGMENTS)), [[6, 5]]);
+  assert.deepEqual(shrinkBody([], SHRINK_SEGMENTS), []);
+});
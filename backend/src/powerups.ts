@@ -0,0 +1,62 @@
+import { BodySegment, PowerUpKind } from "./types.js";
+
+export const MAX_POWER_UPS = 2;
+// Segments lost when another snake picks up a shrink,
+// must match SHRINK_SEGMENTS in the client
+export const SHRINK_SEGMENTS = 3;
+
+// How often each kind spawns relative to the others
+const POWER_UP_SPAWN_TABLE: [PowerUpKind, number][] = [
+  [PowerUpKind.SPEED, 3],
+  [PowerUpKind.SLOW, 2],
+  [PowerUpKind.GHOST, 2],
+  [PowerUpKind.SHRINK_OTHERS, 1],
+  [PowerUpKind.MAGNET, 3],
+  [PowerUpKind.SHIELD, 2],
+];
+
+// How long each effect lasts, instant effects last 0 ms
+export const EFFECT_DURATIONS_MS: Record<PowerUpKind, number> = {
+  [PowerUpKind.SPEED]: 5000,
+  [PowerUpKind.SLOW]: 5000,
+  [PowerUpKind.GHOST]: 4000,
+  [PowerUpKind.SHRINK_OTHERS]: 0,
+  [PowerUpKind.MAGNET]: 8000,
+  [PowerUpKind.SHIELD]: 10000,
+};
+
+export function randomPowerUpKind(): PowerUpKind {
+  const totalWeight = POWER_UP_SPAWN_TABLE.reduce((total, [, weight]) => total + weight, 0);
+  let roll = Math.random() * totalWeight;
+  for (const [kind, weight] of POWER_UP_SPAWN_TABLE) {
+    roll -= weight;
+    if (roll < 0) {
+      return kind;
+    }
+  }
+  return POWER_UP_SPAWN_TABLE[0][0];
+}
+
+// Slow and shrink hit every other snake, the rest help the one who picked them up
+export function affectsOthers(kind: PowerUpKind): boolean {
+  return kind === PowerUpKind.SLOW || kind === PowerUpKind.SHRINK_OTHERS;
+}
+
+// Take segments off the tail end of a body, the head is always kept
+export function shrinkBody(body: BodySegment[], segments: number): BodySegment[] {
+  if (body.length === 0) {
+    return body;
+  }
+  const head = body[body.length - 1];
+  const rest = body.slice(0, -1);
+  return [...rest.slice(0, Math.max(rest.length - segments, 0)), head];
+}
+
+// Remove the effects that have run out and return their kinds
+export function expireEffects(effects: Map<PowerUpKind, number>, now: number): PowerUpKind[] {
+  const expired = Array.from(effects)
+    .filter(([, expiresAt]) => now >= expiresAt)
+    .map(([kind]) => kind);
+  expired.forEach((kind) => effects.delete(kind));
+  return expired;
+}
@@ -0,0 +1,140 @@
+import {
+  BotDifficulty,
+  CorpseBehaviour,
+  FoodKind,
+  HeadOnRule,
+  JoinRoomRequest,
+  Room,
+  RoomInfo,
+  RoomMode,
+  RoomSettings,
+} from "./types.js";
+import { getRandomPosition } from "./grid.js";
+import { findMap, maps } from "./mapfile.js";
+import { createMatchState } from "./match.js";
+import { createFood } from "./food.js";
+import { MAX_BOTS } from "./bots.js";
+
+const MAX_ROOM_NAME_LENGTH = 24;
+export const MAX_ROOM_PLAYERS = 16;
+const MIN_MATCH_TIME_LIMIT = 30;
+const MAX_MATCH_TIME_LIMIT = 600;
+export const MAX_TEAMS = 4;
+const JOIN_CODE_LENGTH = 5;
+// No 0/O or 1/I so codes can be read out loud
+const JOIN_CODE_ALPHABET = "ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+export const rooms = new Map<number, Room>();
+let nextRoomId = 1;
+
+function generateJoinCode(): string {
+  let code: string;
+  do {
+    code = "";
+    for (let i = 0; i < JOIN_CODE_LENGTH; i++) {
+      code += JOIN_CODE_ALPHABET[Math.floor(Math.random() * JOIN_CODE_ALPHABET.length)];
+    }
+  } while (Array.from(rooms.values()).some((room) => room.code === code));
+  return code;
+}
+
+export function createRoom(settings: RoomSettings): Room {
+  const layout = findMap(settings.map);
+  const room: Room = {
+    id: nextRoomId++,
+    code: generateJoinCode(),
+    settings: { ...settings, name: settings.name.trim() },
+    mapState: {
+      food_positions: [createFood(FoodKind.NORMAL, getRandomPosition(layout))],
+      power_ups: [],
+      zone: null,
+      next_zone: null,
+      layout,
+    },
+    playerIds: new Set<number>(),
+    // Battle royale rooms play match rounds too
+    match: settings.mode !== RoomMode.ENDLESS ? createMatchState() : null,
+  };
+  rooms.set(room.id, room);
+  return room;
+}
+
+export function findRoom(request: JoinRoomRequest): Room | undefined {
+  if (typeof request.code === "string") {
+    const code = request.code.trim().toUpperCase();
+    return Array.from(rooms.values()).find((room) => room.code === code);
+  }
+  // Private rooms are only joined by their code, their ids are easy to guess
+  const room = rooms.get(request.room_id);
+  return room && !room.settings.private ? room : undefined;
+}
+
+export function roomInfo(room: Room): RoomInfo {
+  return {
+    room_id: room.id,
+    code: room.code,
+    settings: room.settings,
+    player_count: room.playerIds.size,
+  };
+}
+
+// Returns the reason the settings are rejected, or null if they are valid
+export function validateRoomSettings(settings: any): string | null {
+  if (typeof settings?.name !== "string" || typeof settings?.private !== "boolean") {
+    return "Invalid room settings";
+  }
+  const name = settings.name.trim();
+  if (name.length === 0 || name.length > MAX_ROOM_NAME_LENGTH) {
+    return `Room name must be 1 to ${MAX_ROOM_NAME_LENGTH} characters long`;
+  }
+  if (
+    !Number.isInteger(settings.max_players) ||
+    settings.max_players < 2 ||
+    settings.max_players > MAX_ROOM_PLAYERS
+  ) {
+    return `Rooms hold 2 to ${MAX_ROOM_PLAYERS} players`;
+  }
+  if (
+    !Object.values(HeadOnRule).includes(settings.head_on_rule) ||
+    !Object.values(CorpseBehaviour).includes(settings.corpse_behaviour)
+  ) {
+    return "Invalid room rules";
+  }
+  if (!Object.values(RoomMode).includes(settings.mode)) {
+    return "Invalid room mode";
+  }
+  if (
+    !Number.isInteger(settings.match_time_limit) ||
+    settings.match_time_limit < MIN_MATCH_TIME_LIMIT ||
+    settings.match_time_limit > MAX_MATCH_TIME_LIMIT
+  ) {
+    return `Match time limit must be ${MIN_MATCH_TIME_LIMIT} to ${MAX_MATCH_TIME_LIMIT} seconds`;
+  }
+  const validTeamCount =
+    Number.isInteger(settings.team_count) &&
+    (settings.team_count === 0 ||
+      (settings.team_count >= 2 &&
+        settings.team_count <= MAX_TEAMS &&
+        settings.team_count <= settings.max_players));
+  if (!validTeamCount || typeof settings.friendly_fire !== "boolean") {
+    return `Rooms have no teams or 2 to ${MAX_TEAMS} teams`;
+  }
+  if (typeof settings.power_ups !== "boolean") {
+    return "Invalid room rules";
+  }
+  if (typeof settings.map !== "string" || (settings.map !== "" && !maps.has(settings.map))) {
+    return "Unknown map";
+  }
+  if (
+    !Number.isInteger(settings.bot_count) ||
+    settings.bot_count < 0 ||
+    settings.bot_count > MAX_BOTS ||
+    settings.bot_count >= settings.max_players
+  ) {
+    return `Rooms have 0 to ${MAX_BOTS} bots and room for at least one player`;
+  }
+  if (!Object.values(BotDifficulty).includes(settings.bot_difficulty)) {
+    return "Invalid bot difficulty";
+  }
+  return null;
+}
@@ -1,16 +1,96 @@
 import WebSocket, { WebSocketServer } from "ws";
 import {
+  BotDifficulty,
   Direction,
   PlayerStateData,
   PlayerNetworkData,
   ServerMessage,
-  MapState,
   PositionCoords,
+  CorpseBehaviour,
+  HeadOnRule,
+  Kill,
+  KillReport,
+  PlayerProfile,
+  Pong,
+  SnakeMove,
+  LeaderboardPeriod,
+  Room,
+  RoomSettings,
+  JoinRoomRequest,
+  RoomMode,
+  MatchPhase,
+  MatchInfo,
+  MatchState,
+  PowerUpKind,
+  PowerUpPickup,
+  EffectChange,
+  FoodKind,
 } from "./types.js";
+import { resolveCollisions } from "./simulation.js";
+import { getLeaderboard, recordScore } from "./leaderboard.js";
+import { getRandomPosition, isBoundary, isDeadly, isWall } from "./grid.js";
+import { computeMatchSpawns, rankMatchResults, rankTeamResults } from "./match.js";
+import { findSafeSpawn, isFreeSpawnCell } from "./spawn.js";
+import {
+  rooms,
+  createRoom,
+  findRoom,
+  roomInfo,
+  validateRoomSettings,
+  MAX_ROOM_PLAYERS,
+} from "./rooms.js";
+import {
+  EFFECT_DURATIONS_MS,
+  MAX_POWER_UPS,
+  SHRINK_SEGMENTS,
+  affectsOthers,
+  expireEffects,
+  randomPowerUpKind,
+  shrinkBody,
+} from "./powerups.js";
+import { createFood, randomFoodKind, updateFood } from "./food.js";
+import { BOOST_MIN_LENGTH, NORMAL_TICKS_PER_MOVE, ticksPerMove } from "./speed.js";
+import { fullZone, getRandomZonePosition, isInsideZone, shrinkZone } from "./zone.js";
+import { loadMaps, maps } from "./mapfile.js";
+import { needsResync, resetMoves, takeMove, validateBoostDrop, validateFoodClaim } from "./validation.js";
+import {
+  BOT_COLOURS,
+  BOT_NAMES,
+  MAX_BOTS,
+  buildOccupancy,
+  chooseDirection,
+  moveSnake,
+} from "./bots.js";
 
-const BOUNDARY_THICKNESS = 1;
-const GRID_HEIGHT = 30;
-const GRID_WIDTH = 30;
+// Collisions, effects and matches are updated every simulation tick,
+// must match TICK_TIME in the client
+const SIMULATION_TICK_TIME = 25;
+const NETWORK_TICK_TIME = 50;
+// Must match RESPAWN_TICKS * TICK_TIME and SPAWN_PROTECTION_TICKS * TICK_TIME in the client
+const RESPAWN_DELAY_MS = 3000;
+const SPAWN_PROTECTION_MS = 2000;
+const CORPSE_BEHAVIOUR: CorpseBehaviour =
+  process.env.CORPSE_BEHAVIOUR === CorpseBehaviour.FOOD
+    ? CorpseBehaviour.FOOD
+    : CorpseBehaviour.VANISH;
+const MIN_MATCH_PLAYERS = 2;
+const MATCH_COUNTDOWN_MS = 3000;
+// The battle royale zone holds still for a while before it starts closing in
+const ZONE_SHRINK_DELAY_MS = 10000;
+const ZONE_SHRINK_INTERVAL_MS = 3000;
+const POWER_UP_SPAWN_MS = 15000;
+// Time a snake gets to move clear after its shield takes a hit
+const SHIELD_GRACE_MS = 1000;
+const MAX_NAME_LENGTH = 16;
+const COLOUR_PATTERN = /^#[0-9a-fA-F]{6}$/;
+// Map files rooms can be created with, relative to where the server is started
+const MAPS_DIRECTORY = process.env.MAPS_DIRECTORY ?? "maps";
+// Bots keep the public room busy when few people are online
+const DEFAULT_ROOM_BOTS = Math.min(Number(process.env.DEFAULT_ROOM_BOTS ?? 3) || 0, MAX_BOTS);
+const HEAD_ON_RULE: HeadOnRule =
+  process.env.HEAD_ON_RULE === HeadOnRule.LONGER_WINS
+    ? HeadOnRule.LONGER_WINS
+    : HeadOnRule.BOTH_DIE;
 
 // Initialize WebSocket server
 const wss = new WebSocketServer({ port: 8080 });
@@ -18,30 +98,62 @@ const wss = new WebSocketServer({ port: 8080 });
 // Store connected players and their ids
 let nextplayer_id = 1; // This is the next ID to assign
 const players = new Map<number, PlayerNetworkData>(); // Map of player IDs to their WebSocket connections
-const mapState: MapState = {
-  food_positions: [getRandomPosition()],
-};
 
-function getRandomPosition(): PositionCoords {
-  return {
-    x: BOUNDARY_THICKNESS + Math.floor(Math.random() * GRID_WIDTH),
-    y: BOUNDARY_THICKNESS + Math.floor(Math.random() * GRID_HEIGHT),
-  };
+// Every message sent is stamped with the simulation tick, which clients
+// pace their own ticks by
+let serverTick = 0;
+let serverTickTime = Date.now();
+
+// The public room everyone can drop into, it is never removed
+loadMaps(MAPS_DIRECTORY);
+
+const defaultRoom = createRoom({
+  name: "Arena",
+  private: false,
+  max_players: MAX_ROOM_PLAYERS,
+  head_on_rule: HEAD_ON_RULE,
+  corpse_behaviour: CORPSE_BEHAVIOUR,
+  mode: RoomMode.ENDLESS,
+  match_time_limit: 120,
+  team_count: 0,
+  friendly_fire: true,
+  power_ups: true,
+  map: "",
+  bot_count: DEFAULT_ROOM_BOTS,
+  bot_difficulty: BotDifficulty.NORMAL,
+});
+addBots(defaultRoom);
+
+function isFoodPosition(room: Room, x: number, y: number): boolean {
+  return room.mapState.food_positions.some(
+    (position) => position.x === x && position.y === y
+  );
 }
 
-// Helper function to broadcast a message to all connected players
-function broadcast(data: any) {
-  // Send to all connected players
-  wss.clients.forEach((client) => {
-    if (client.readyState === WebSocket.OPEN) {
-      client.send(JSON.stringify(data));
-    }
-  });
+function isPowerUpPosition(room: Room, x: number, y: number): boolean {
+  return room.mapState.power_ups.some((powerUp) => powerUp.x === x && powerUp.y === y);
+}
+
+function roomOf(playerId: number): Room | undefined {
+  return rooms.get(players.get(playerId)?.roomId);
+}
+
+function sendToPlayer(playerId: number, data: ServerMessage) {
+  const playerNetworkData = players.get(playerId);
+  if (playerNetworkData?.ws?.readyState === WebSocket.OPEN) {
+    playerNetworkData.ws.send(JSON.stringify({ ...data, tick: serverTick }));
+  }
+}
+
+// Helper function to broadcast a message to all players in a room
+function broadcast(room: Room, data: ServerMessage) {
+  room.playerIds.forEach((playerId) => sendToPlayer(playerId, data));
 }
 
 function validatePlayerData(data: any): data is PlayerStateData {
   return (
     typeof data.player_id === "number" &&
+    typeof data.score === "number" &&
     Array.isArray(data.body_segments) &&
     data.body_segments.every(
       (segment: any) =>
@@ -52,6 +164,13 @@ function validatePlayerData(data: any): data is PlayerStateData {
   );
 }
 
+// Holding the boost key is up to the player, having the length for it goes
+// by the body the server accepted last. It includes the head, which
+// BOOST_MIN_LENGTH does not count.
+function canBoost(playerNetworkData: PlayerNetworkData, playerData: PlayerStateData): boolean {
+  return playerData.boosting === true && playerNetworkData.stateData.body_segments.length > BOOST_MIN_LENGTH;
+}
+
 function savePlayerData(playerData: PlayerStateData) {
   const playerId = parseInt(playerData.player_id as any);
   // Check if the player exists in the map
@@ -59,6 +178,16 @@ function savePlayerData(playerData: PlayerStateData) {
     // Retrieve the current PlayerNetworkData for the player
     const playerNetworkData = players.get(playerId);
     if (playerData) {
+      // Spawn protection is decided by the server, not by the client
+      playerData.invulnerable = Date.now() < playerNetworkData.protectedUntil;
+      playerData.kills = playerNetworkData.stateData.kills;
+      playerData.score = playerNetworkData.stateData.score;
+      playerData.name = playerNetworkData.profile.name;
+      playerData.colour = playerNetworkData.profile.colour;
+      playerData.team = playerNetworkData.team;
+      playerData.effects = Array.from(playerNetworkData.effects.keys());
+      playerData.boosting = canBoost(playerNetworkData, playerData);
+      playerData.ticks_per_move = ticksPerMove(playerNetworkData.effects, playerData.boosting);
       // Update only the stateData field
       playerNetworkData.stateData = playerData;
       // Re-insert the modified object into the map if necessary
@@ -69,8 +198,30 @@ function savePlayerData(playerData: PlayerStateData) {
   }
 }
 
-function handlePlayerState(playerData: PlayerStateData) {
+function handlePlayerState(playerId: number, playerData: PlayerStateData, tick: number) {
   if (validatePlayerData(playerData)) {
+    if (playerData.player_id !== playerId) {
+      console.error(`Player ${playerId} sent a player_state for player ${playerData.player_id}`);
+      return;
+    }
+    const playerNetworkData = players.get(playerId);
+    const room = roomOf(playerId);
+    // Late updates from a dead snake would otherwise leave a frozen corpse
+    if (!playerNetworkData?.alive || !room) {
+      return;
+    }
+    // An update overtaken by a newer one would move the snake backwards
+    if (tick !== 0 && tick < playerNetworkData.lastInputTick) {
+      return;
+    }
+    const moveInterval =
+      ticksPerMove(playerNetworkData.effects, canBoost(playerNetworkData, playerData)) * SIMULATION_TICK_TIME;
+    const violation = takeMove(room.mapState.layout, playerNetworkData, playerData, tick, moveInterval, Date.now());
+    if (violation !== null) {
+      console.error(`Rejected player_state from player ${playerId}: ${violation}`);
+      sendResync(playerId);
+      return;
+    }
     // Process and save player data as needed
     savePlayerData(playerData);
   } else {
@@ -79,35 +230,1026 @@ function handlePlayerState(playerData: PlayerStateData) {
   }
 }
 
-function handleAddFood() {
-  mapState.food_positions.push(getRandomPosition());
+// Send a player the state the server kept after turning down their update,
+// so the client carries on from there instead of every later update being
+// turned down too
+function sendResync(playerId: number) {
+  const playerNetworkData = players.get(playerId);
+  const now = Date.now();
+  if (playerNetworkData.stateData.body_segments.length === 0 || !needsResync(playerNetworkData, now)) {
+    return;
+  }
+  playerNetworkData.resyncSentAt = now;
+  const serverMessage: ServerMessage = {
+    message_type: "player_state_rejected",
+    player_id: playerId,
+    message: JSON.stringify(playerNetworkData.stateData),
+  };
+  sendToPlayer(playerId, serverMessage);
+}
+
+// The client snapped to the correction it was sent, its updates follow on
+// from the state the server kept again
+function handlePlayerResynced(playerId: number) {
+  const playerNetworkData = players.get(playerId);
+  if (playerNetworkData) {
+    playerNetworkData.resyncSentAt = null;
+  }
+}
+
+// Turn a dead snake's body into food pellets if the room is set up to do so
+function dropCorpse(room: Room, playerData: PlayerStateData) {
+  if (room.settings.corpse_behaviour !== CorpseBehaviour.FOOD) {
+    return;
+  }
+  playerData.body_segments.forEach((segment) => {
+    const layout = room.mapState.layout;
+    const blocked = isBoundary(layout, segment.x, segment.y) || isWall(layout, segment.x, segment.y);
+    if (!blocked && !isFoodPosition(room, segment.x, segment.y)) {
+      room.mapState.food_positions.push(createFood(FoodKind.NORMAL, segment));
+    }
+  });
+  broadcastMapState(room);
+}
+
+function playerName(playerId: number): string {
+  return players.get(playerId)?.profile?.name ?? `Player ${playerId}`;
+}
+
+function areTeammates(playerId: number, otherId: number): boolean {
+  const team = players.get(playerId)?.team ?? null;
+  return team !== null && team === players.get(otherId)?.team;
+}
+
+// The score is the server's own count from accepted food claims, only
+// people make it onto the leaderboard
+function recordLeaderboardScore(playerId: number, achievedAt: number) {
+  const playerNetworkData = players.get(playerId);
+  if (playerNetworkData.bot) {
+    return;
+  }
+  recordScore({
+    name: playerName(playerId),
+    score: playerNetworkData.stateData.score,
+    kills: playerNetworkData.stateData.kills,
+    length: playerNetworkData.stateData.body_segments.length,
+    achieved_at: achievedAt,
+  });
+}
+
+function killPlayer(kill: Kill) {
+  const playerNetworkData = players.get(kill.victim_id);
+  const room = roomOf(kill.victim_id);
+  if (!playerNetworkData || !playerNetworkData.alive || !room) {
+    return;
+  }
+  playerNetworkData.alive = false;
+  playerNetworkData.diedAt = Date.now();
+  playerNetworkData.previousHead = null;
+  resetMoves(playerNetworkData);
+  playerNetworkData.effects.clear();
+  dropCorpse(room, playerNetworkData.stateData);
+  recordLeaderboardScore(kill.victim_id, playerNetworkData.diedAt);
+  playerNetworkData.stateData = {
+    ...playerNetworkData.stateData,
+    body_segments: [],
+    invulnerable: false,
+    score: 0,
+    effects: [],
+  };
+
+  // Credit the snake whose body was hit, unless it was a teammate
+  const attackerNetworkData = players.get(kill.attacker_id);
+  if (
+    kill.attacker_id !== kill.victim_id &&
+    !areTeammates(kill.attacker_id, kill.victim_id) &&
+    attackerNetworkData
+  ) {
+    attackerNetworkData.stateData.kills += 1;
+  }
+
+  const killReport: KillReport = {
+    ...kill,
+    attacker_name: playerName(kill.attacker_id),
+    victim_name: playerName(kill.victim_id),
+  };
+  const serverMessage: ServerMessage = {
+    message_type: "player_died",
+    player_id: kill.victim_id,
+    message: JSON.stringify(killReport),
+  };
+  broadcast(room, serverMessage);
+}
+
+function handlePlayerRespawn(playerId: number, playerData: PlayerStateData) {
+  if (!validatePlayerData(playerData)) {
+    console.error("Invalid player data format");
+    return;
+  }
+  if (playerData.player_id !== playerId) {
+    console.error(`Player ${playerId} tried to respawn player ${playerData.player_id}`);
+    return;
+  }
+  const playerNetworkData = players.get(playerId);
+  const room = roomOf(playerId);
+  if (!playerNetworkData || !room || playerNetworkData.alive) {
+    return;
+  }
+  // Snakes knocked out of a match sit out until the next round
+  if (room.match) {
+    console.error(`Player ${playerData.player_id} tried to respawn during a match`);
+    return;
+  }
+  // A fresh snake is only a head, anything longer was never eaten for
+  if (playerData.body_segments.length !== 1) {
+    console.error(`Player ${playerData.player_id} tried to respawn with ${playerData.body_segments.length} segments`);
+    return;
+  }
+  // Allow one network tick of slack for timer drift between client and server
+  if (Date.now() - playerNetworkData.diedAt < RESPAWN_DELAY_MS - NETWORK_TICK_TIME) {
+    console.error(`Player ${playerData.player_id} tried to respawn too early`);
+    // Put the client back into its dead state
+    const kill: KillReport = {
+      attacker_id: playerData.player_id,
+      victim_id: playerData.player_id,
+      attacker_name: playerNetworkData.profile.name,
+      victim_name: playerNetworkData.profile.name,
+    };
+    const serverMessage: ServerMessage = {
+      message_type: "player_died",
+      player_id: playerData.player_id,
+      message: JSON.stringify(kill),
+    };
+    sendToPlayer(playerId, serverMessage);
+    return;
+  }
+  // A cell the client should not have picked is swapped for one the server
+  // finds the same way it does for bots, and the client is told where it is
+  const others = otherSnakes(room, playerId);
+  const food = room.mapState.food_positions;
+  const moved = !isFreeSpawnCell(room.mapState.layout, playerData.body_segments[0], others, food);
+  if (moved) {
+    console.error(`Player ${playerData.player_id} tried to respawn on a cell that is not free`);
+    playerData.body_segments = [findSafeSpawn(room.mapState.layout, others, food)];
+  }
+  playerNetworkData.alive = true;
+  playerNetworkData.protectedUntil = Date.now() + SPAWN_PROTECTION_MS;
+  savePlayerData(playerData);
+  if (moved) {
+    sendResync(playerId);
+  }
+
+  const serverMessage: ServerMessage = {
+    message_type: "player_respawned",
+    player_id: playerData.player_id,
+    message: JSON.stringify(playerNetworkData.stateData),
+  };
+  broadcast(room, serverMessage);
+}
+
+// Returns the reason a profile is rejected, or null if it is valid
+function validateProfile(playerId: number, profile: any): string | null {
+  if (typeof profile?.name !== "string" || typeof profile?.colour !== "string") {
+    return "Invalid profile";
+  }
+  const name = profile.name.trim();
+  if (name.length === 0 || name.length > MAX_NAME_LENGTH) {
+    return `Name must be 1 to ${MAX_NAME_LENGTH} characters long`;
+  }
+  const nameTaken = Array.from(players.entries()).some(
+    ([id, data]) =>
+      id !== playerId &&
+      data.profile?.name.toLowerCase() === name.toLowerCase()
+  );
+  if (nameTaken) {
+    return "Name is already taken";
+  }
+  if (!COLOUR_PATTERN.test(profile.colour)) {
+    return "Invalid colour";
+  }
+  return null;
+}
+
+function handleJoin(playerId: number, profile: PlayerProfile) {
+  const playerNetworkData = players.get(playerId);
+  if (!playerNetworkData) {
+    console.error("Player not found in the map");
+    return;
+  }
+  const error = validateProfile(playerId, profile);
+  if (error !== null) {
+    const serverMessage: ServerMessage = {
+      message_type: "join_rejected",
+      player_id: playerId,
+      message: error,
+    };
+    sendToPlayer(playerId, serverMessage);
+    return;
+  }
+
+  playerNetworkData.profile = {
+    name: profile.name.trim(),
+    colour: profile.colour,
+  };
+  playerNetworkData.stateData.name = playerNetworkData.profile.name;
+  playerNetworkData.stateData.colour = playerNetworkData.profile.colour;
+
+  const serverMessage: ServerMessage = {
+    message_type: "join_accepted",
+    player_id: playerId,
+    message: JSON.stringify(playerNetworkData.profile),
+  };
+  sendToPlayer(playerId, serverMessage);
+}
+
+function handleGetLeaderboard(playerId: number, period: LeaderboardPeriod) {
+  const playerNetworkData = players.get(playerId);
+  if (!playerNetworkData) {
+    console.error("Player not found in the map");
+    return;
+  }
+  if (!Object.values(LeaderboardPeriod).includes(period)) {
+    console.error("Invalid leaderboard period");
+    return;
+  }
+  const serverMessage: ServerMessage = {
+    message_type: "leaderboard",
+    player_id: playerId,
+    message: JSON.stringify(getLeaderboard(period)),
+  };
+  sendToPlayer(playerId, serverMessage);
+}
+
+function sendRoomError(playerId: number, error: string) {
+  const serverMessage: ServerMessage = {
+    message_type: "room_error",
+    player_id: playerId,
+    message: error,
+  };
+  sendToPlayer(playerId, serverMessage);
+}
+
+// Answer straight away with the server's clock and tick so the client can
+// measure the round trip and estimate how far apart the clocks are
+function handlePing(playerId: number, message: string) {
+  const pong: Pong = {
+    client_time: Number(message),
+    server_time: Date.now(),
+    tick: serverTick,
+    tick_time: serverTickTime,
+  };
+  const serverMessage: ServerMessage = {
+    message_type: "pong",
+    player_id: playerId,
+    message: JSON.stringify(pong),
+  };
+  sendToPlayer(playerId, serverMessage);
+}
+
+function handleListRooms(playerId: number) {
+  const publicRooms = Array.from(rooms.values())
+    .filter((room) => !room.settings.private)
+    .map(roomInfo);
+  const serverMessage: ServerMessage = {
+    message_type: "room_list",
+    player_id: playerId,
+    message: JSON.stringify(publicRooms),
+  };
+  sendToPlayer(playerId, serverMessage);
+  const mapListMessage: ServerMessage = {
+    message_type: "map_list",
+    player_id: playerId,
+    message: JSON.stringify(Array.from(maps.keys())),
+  };
+  sendToPlayer(playerId, mapListMessage);
+}
+
+// New players are put on the team with the fewest players
+function smallestTeam(room: Room): number {
+  const teamSizes: number[] = new Array(room.settings.team_count).fill(0);
+  room.playerIds.forEach((id) => {
+    const team = players.get(id).team;
+    if (team !== null) {
+      teamSizes[team] += 1;
+    }
+  });
+  return teamSizes.indexOf(Math.min(...teamSizes));
+}
+
+function sendTeam(playerId: number) {
+  const serverMessage: ServerMessage = {
+    message_type: "team_assigned",
+    player_id: playerId,
+    message: JSON.stringify(players.get(playerId).team),
+  };
+  sendToPlayer(playerId, serverMessage);
+}
+
+function joinRoom(playerId: number, room: Room) {
+  const playerNetworkData = players.get(playerId);
+  if (!playerNetworkData?.profile) {
+    sendRoomError(playerId, "Join the game before entering a room");
+    return;
+  }
+  if (playerNetworkData.roomId === room.id) {
+    return;
+  }
+  if (room.playerIds.size >= room.settings.max_players) {
+    sendRoomError(playerId, "Room is full");
+    return;
+  }
+  leaveRoom(playerId);
+
+  playerNetworkData.team = room.settings.team_count > 0 ? smallestTeam(room) : null;
+  room.playerIds.add(playerId);
+  playerNetworkData.roomId = room.id;
+  // In match rooms snakes spawn together when the next round starts
+  playerNetworkData.alive = room.match === null;
+  playerNetworkData.protectedUntil = Date.now() + SPAWN_PROTECTION_MS;
+  playerNetworkData.previousHead = null;
+  resetMoves(playerNetworkData);
+  playerNetworkData.stateData = {
+    ...playerNetworkData.stateData,
+    body_segments: [],
+    score: 0,
+    team: playerNetworkData.team,
+  };
+
+  // The map comes first so the snake spawns on the right one
+  broadcastMapState(room);
+  const serverMessage: ServerMessage = {
+    message_type: "room_joined",
+    player_id: playerId,
+    message: JSON.stringify(roomInfo(room)),
+  };
+  sendToPlayer(playerId, serverMessage);
+  sendTeam(playerId);
+  if (room.match) {
+    broadcastMatchState(room);
+  }
+}
+
+function leaveRoom(playerId: number) {
+  const playerNetworkData = players.get(playerId);
+  const room = roomOf(playerId);
+  if (!playerNetworkData || !room) {
+    return;
+  }
+  // Leaving or disconnecting mid-life still counts for the leaderboard
+  if (playerNetworkData.alive) {
+    recordLeaderboardScore(playerId, Date.now());
+  }
+  room.playerIds.delete(playerId);
+  playerNetworkData.roomId = null;
+  playerNetworkData.team = null;
+  playerNetworkData.effects.clear();
+  playerNetworkData.alive = false;
+  if (room.match) {
+    room.match.readyIds.delete(playerId);
+    broadcastMatchState(room);
+  }
+
+  // Let the other players drop the snake right away
+  const serverMessage: ServerMessage = {
+    message_type: "player_despawned",
+    player_id: playerId,
+    message: "",
+  };
+  broadcast(room, serverMessage);
+
+  // Bots don't keep a room open on their own
+  const onlyBotsLeft = Array.from(room.playerIds).every(isBot);
+  if (onlyBotsLeft && room !== defaultRoom) {
+    room.playerIds.forEach((id) => players.delete(id));
+    rooms.delete(room.id);
+  }
+}
+
+function isBot(playerId: number): boolean {
+  return players.get(playerId)?.bot != null;
+}
+
+// Bots join like any other player, only without a connection
+function addBot(room: Room) {
+  const playerId = nextplayer_id++;
+  const takenNames = new Set(Array.from(players.values()).map((data) => data.profile?.name));
+  const name =
+    BOT_NAMES.map((botName) => `Bot ${botName}`).find((botName) => !takenNames.has(botName)) ??
+    `Bot ${playerId}`;
+  const colour = BOT_COLOURS[playerId % BOT_COLOURS.length];
+  players.set(playerId, {
+    stateData: {
+      player_id: playerId,
+      body_segments: [],
+      invulnerable: true,
+      kills: 0,
+      score: 0,
+      name,
+      colour,
+      team: null,
+      effects: [],
+      ticks_per_move: NORMAL_TICKS_PER_MOVE,
+      boosting: false,
+    },
+    ws: null,
+    profile: { name, colour },
+    roomId: null,
+    team: null,
+    effects: new Map(),
+    alive: false,
+    diedAt: 0,
+    protectedUntil: 0,
+    previousHead: null,
+    lastInputTick: 0,
+    growthCredit: 0,
+    resyncSentAt: null,
+    moveBudget: 0,
+    moveBudgetAt: 0,
+    bot: { difficulty: room.settings.bot_difficulty, moveTicks: 0, pendingGrowth: 0 },
+  });
+  joinRoom(playerId, room);
+  if (room.match === null) {
+    spawnBot(room, playerId);
+  }
+}
+
+function addBots(room: Room) {
+  for (let i = 0; i < room.settings.bot_count; i++) {
+    addBot(room);
+  }
+}
+
+// The snakes alive in a room other than the player's own
+function otherSnakes(room: Room, playerId: number): PlayerStateData[] {
+  return Array.from(room.playerIds)
+    .filter((id) => id !== playerId && players.get(id).alive)
+    .map((id) => players.get(id).stateData);
+}
+
+// Put a bot back on the grid, the server does for bots what clients do
+// for their own snake on respawn
+function spawnBot(room: Room, playerId: number) {
+  const playerNetworkData = players.get(playerId);
+  const spawn = findSafeSpawn(room.mapState.layout, otherSnakes(room, playerId), room.mapState.food_positions);
+  playerNetworkData.alive = true;
+  playerNetworkData.protectedUntil = Date.now() + SPAWN_PROTECTION_MS;
+  playerNetworkData.previousHead = null;
+  resetMoves(playerNetworkData);
+  playerNetworkData.bot.moveTicks = 0;
+  playerNetworkData.bot.pendingGrowth = 0;
+  playerNetworkData.stateData = {
+    ...playerNetworkData.stateData,
+    body_segments: [{ x: spawn.x, y: spawn.y, direction: spawn.direction }],
+    invulnerable: true,
+    score: 0,
+    effects: [],
+  };
+  const serverMessage: ServerMessage = {
+    message_type: "player_respawned",
+    player_id: playerId,
+    message: JSON.stringify(playerNetworkData.stateData),
+  };
+  broadcast(room, serverMessage);
+}
+
+// Move every bot that is due a move this tick, eating whatever it lands on
+function updateBots(room: Room) {
+  const now = Date.now();
+  room.playerIds.forEach((playerId) => {
+    const playerNetworkData = players.get(playerId);
+    const bot = playerNetworkData.bot;
+    if (!bot) {
+      return;
+    }
+    if (!playerNetworkData.alive) {
+      // Bots knocked out of a match wait for the next round like everyone else
+      if (room.match === null && now - playerNetworkData.diedAt >= RESPAWN_DELAY_MS) {
+        spawnBot(room, playerId);
+      }
+      return;
+    }
+    const stateData = playerNetworkData.stateData;
+    stateData.ticks_per_move = ticksPerMove(playerNetworkData.effects, false);
+    stateData.invulnerable = now < playerNetworkData.protectedUntil;
+    stateData.effects = Array.from(playerNetworkData.effects.keys());
+    bot.moveTicks += 1;
+    if (bot.moveTicks < stateData.ticks_per_move || stateData.body_segments.length === 0) {
+      return;
+    }
+    bot.moveTicks = 0;
+
+    const bodies = Array.from(room.playerIds)
+      .map((id) => players.get(id))
+      .filter((data) => data.alive)
+      .map((data) => data.stateData.body_segments);
+    const occupancy = buildOccupancy(room.mapState.layout, room.mapState.zone, bodies);
+    const otherHeads = bodies
+      .filter((body) => body !== stateData.body_segments && body.length > 0)
+      .map((body) => body[body.length - 1]);
+    const direction = chooseDirection({
+      occupancy,
+      body: stateData.body_segments,
+      food: room.mapState.food_positions,
+      otherHeads,
+      difficulty: bot.difficulty,
+    });
+    const grow = bot.pendingGrowth > 0;
+    if (grow) {
+      bot.pendingGrowth -= 1;
+    }
+    let segments = moveSnake(occupancy, stateData.body_segments, direction, grow);
+
+    const head = segments[segments.length - 1];
+    const food = room.mapState.food_positions.find(
+      (position) => position.x === head.x && position.y === head.y
+    );
+    if (food) {
+      if (food.growth >= 0) {
+        bot.pendingGrowth += food.growth;
+      } else {
+        // Poison takes segments off the tail end of the body
+        segments = shrinkBody(segments, -food.growth);
+      }
+    }
+    // The food is checked against the head, so the snake moves first
+    stateData.body_segments = segments;
+    if (food) {
+      handleEatFood(playerId, head);
+    }
+  });
+}
+
+function handleCreateRoom(playerId: number, settings: RoomSettings) {
+  const error = validateRoomSettings(settings);
+  if (error !== null) {
+    sendRoomError(playerId, error);
+    return;
+  }
+  if (!players.get(playerId)?.profile) {
+    sendRoomError(playerId, "Join the game before creating a room");
+    return;
+  }
+  const room = createRoom(settings);
+  joinRoom(playerId, room);
+  addBots(room);
+}
+
+function handleJoinRoom(playerId: number, request: JoinRoomRequest) {
+  const room = findRoom(request);
+  if (!room) {
+    sendRoomError(playerId, "Room not found");
+    return;
+  }
+  joinRoom(playerId, room);
+}
+
+function secondsLeft(match: MatchState): number {
+  if (match.phase !== MatchPhase.COUNTDOWN && match.phase !== MatchPhase.PLAYING) {
+    return 0;
+  }
+  return Math.max(0, Math.ceil((match.phaseEndsAt - Date.now()) / 1000));
+}
+
+function broadcastMatchState(room: Room) {
+  const match = room.match;
+  match.lastSecondsLeft = secondsLeft(match);
+  const matchInfo: MatchInfo = {
+    phase: match.phase,
+    seconds_left: match.lastSecondsLeft,
+    // Bots are always ready
+    ready_ids: Array.from(room.playerIds).filter((id) => match.readyIds.has(id) || isBot(id)),
+    player_count: room.playerIds.size,
+    players: Array.from(room.playerIds).map((id) => ({
+      player_id: id,
+      name: playerName(id),
+      team: players.get(id).team,
+    })),
+    results: match.results,
+    team_results: match.teamResults,
+  };
+  const serverMessage: ServerMessage = {
+    message_type: "match_state",
+    player_id: 0,
+    message: JSON.stringify(matchInfo),
+  };
+  broadcast(room, serverMessage);
+}
+
+function handleSetReady(playerId: number, ready: boolean) {
+  const room = roomOf(playerId);
+  const match = room?.match;
+  if (!match) {
+    return;
+  }
+  if (match.phase !== MatchPhase.WAITING && match.phase !== MatchPhase.FINISHED) {
+    return;
+  }
+  if (ready) {
+    match.readyIds.add(playerId);
+  } else {
+    match.readyIds.delete(playerId);
+  }
+  broadcastMatchState(room);
+}
+
+function handleSetTeam(playerId: number, team: number) {
+  const playerNetworkData = players.get(playerId);
+  const room = roomOf(playerId);
+  if (!playerNetworkData || !room) {
+    return;
+  }
+  if (!Number.isInteger(team) || team < 0 || team >= room.settings.team_count) {
+    return;
+  }
+  // Teams are fixed for the length of a round
+  const match = room.match;
+  if (match && match.phase !== MatchPhase.WAITING && match.phase !== MatchPhase.FINISHED) {
+    return;
+  }
+  playerNetworkData.team = team;
+  playerNetworkData.stateData.team = team;
+  sendTeam(playerId);
+  if (match) {
+    broadcastMatchState(room);
+  }
+}
+
+// Players still in the running, teams count as one side
+function sideCount(playerIds: number[]): number {
+  return new Set(playerIds.map((id) => players.get(id).team ?? -id)).size;
+}
+
+function startMatch(room: Room) {
+  const match = room.match;
+  match.phase = MatchPhase.PLAYING;
+  match.phaseEndsAt = Date.now() + room.settings.match_time_limit * 1000;
+  match.participantIds = Array.from(room.playerIds);
+  match.readyIds.clear();
+  match.killsAtStart.clear();
+
+  if (room.settings.mode === RoomMode.BATTLE_ROYALE) {
+    room.mapState.zone = fullZone(room.mapState.layout);
+    room.mapState.next_zone = shrinkZone(room.mapState.zone);
+    match.nextZoneShrinkAt = Date.now() + ZONE_SHRINK_DELAY_MS;
+    broadcastMapState(room);
+  }
+
+  const spawns = computeMatchSpawns(match.participantIds, room.mapState.layout);
+  spawns.forEach((spawn) => {
+    const playerNetworkData = players.get(spawn.player_id);
+    match.killsAtStart.set(spawn.player_id, playerNetworkData.stateData.kills);
+    playerNetworkData.alive = true;
+    playerNetworkData.protectedUntil = 0;
+    playerNetworkData.previousHead = null;
+    resetMoves(playerNetworkData);
+    playerNetworkData.effects.clear();
+    playerNetworkData.stateData = {
+      ...playerNetworkData.stateData,
+      body_segments: [{ x: spawn.x, y: spawn.y, direction: spawn.direction }],
+      invulnerable: false,
+      score: 0,
+    };
+  });
+
+  const serverMessage: ServerMessage = {
+    message_type: "match_started",
+    player_id: 0,
+    message: JSON.stringify(spawns),
+  };
+  broadcast(room, serverMessage);
+  broadcastMatchState(room);
+}
+
+function finishMatch(room: Room) {
+  const match = room.match;
+  const participantIds = match.participantIds.filter((id) => room.playerIds.has(id));
+  match.results = rankMatchResults(
+    participantIds.map((id) => {
+      const playerNetworkData = players.get(id);
+      return {
+        player_id: id,
+        name: playerName(id),
+        team: playerNetworkData.team,
+        kills: playerNetworkData.stateData.kills - (match.killsAtStart.get(id) ?? 0),
+        alive: playerNetworkData.alive,
+        length: playerNetworkData.stateData.body_segments.length,
+        diedAt: playerNetworkData.diedAt,
+      };
+    })
+  );
+  match.teamResults = rankTeamResults(match.results);
+  match.phase = MatchPhase.FINISHED;
+  match.readyIds.clear();
+  if (room.mapState.zone) {
+    room.mapState.zone = null;
+    room.mapState.next_zone = null;
+    broadcastMapState(room);
+  }
+  // Survivors stop where they are until the rematch
+  participantIds.forEach((id) => {
+    players.get(id).alive = false;
+  });
+  broadcastMatchState(room);
+}
+
+function updateMatch(room: Room) {
+  const match = room.match;
+  switch (match.phase) {
+    case MatchPhase.WAITING:
+    case MatchPhase.FINISHED: {
+      const playerIds = Array.from(room.playerIds);
+      const everyoneReady =
+        playerIds.length >= MIN_MATCH_PLAYERS &&
+        sideCount(playerIds) >= 2 &&
+        playerIds.every((id) => match.readyIds.has(id) || isBot(id));
+      if (everyoneReady) {
+        match.phase = MatchPhase.COUNTDOWN;
+        match.phaseEndsAt = Date.now() + MATCH_COUNTDOWN_MS;
+        match.results = [];
+        match.teamResults = [];
+        broadcastMatchState(room);
+      }
+      return;
+    }
+    case MatchPhase.COUNTDOWN:
+      if (Date.now() >= match.phaseEndsAt) {
+        startMatch(room);
+        return;
+      }
+      break;
+    case MatchPhase.PLAYING: {
+      const aliveIds = match.participantIds.filter(
+        (id) => room.playerIds.has(id) && players.get(id).alive
+      );
+      if (sideCount(aliveIds) <= 1 || Date.now() >= match.phaseEndsAt) {
+        finishMatch(room);
+        return;
+      }
+      if (room.mapState.next_zone && Date.now() >= match.nextZoneShrinkAt) {
+        closeZone(room);
+      }
+      break;
+    }
+  }
+  // Keep the clients' countdown and round timer in step
+  if (secondsLeft(match) !== match.lastSecondsLeft) {
+    broadcastMatchState(room);
+  }
 }
 
-function handleEatFood(foodPosition: PositionCoords) {
-  // if the food exists in the map, remove it
-  const foodIndex = mapState.food_positions.findIndex(
+// Move the zone in by one step and drop the food left outside of it
+function closeZone(room: Room) {
+  const zone = room.mapState.next_zone;
+  room.mapState.zone = zone;
+  room.mapState.next_zone = shrinkZone(zone);
+  room.mapState.food_positions = room.mapState.food_positions.filter((position) =>
+    isInsideZone(zone, position.x, position.y)
+  );
+  room.mapState.power_ups = room.mapState.power_ups.filter((powerUp) =>
+    isInsideZone(zone, powerUp.x, powerUp.y)
+  );
+  room.match.nextZoneShrinkAt += ZONE_SHRINK_INTERVAL_MS;
+  broadcastMapState(room);
+}
+
+function handleAddFood(room: Room) {
+  const zone = room.mapState.zone;
+  const layout = room.mapState.layout;
+  const position = zone ? getRandomZonePosition(layout, zone) : getRandomPosition(layout);
+  room.mapState.food_positions.push(createFood(randomFoodKind(), position));
+}
+
+function handleEatFood(playerId: number, foodPosition: PositionCoords) {
+  const room = roomOf(playerId);
+  const playerNetworkData = players.get(playerId);
+  if (!room || !playerNetworkData.alive) {
+    return;
+  }
+  const foodIndex = room.mapState.food_positions.findIndex(
     (position) => position.x === foodPosition.x && position.y === foodPosition.y
   );
-  if (foodIndex !== -1) {
-    mapState.food_positions.splice(foodIndex, 1);
+  // Someone else got to it first
+  if (foodIndex === -1) {
+    return;
+  }
+  const food = room.mapState.food_positions[foodIndex];
+  const violation = validateFoodClaim(
+    playerNetworkData.stateData,
+    food,
+    playerNetworkData.effects.has(PowerUpKind.MAGNET)
+  );
+  if (violation !== null) {
+    console.error(`Rejected eat_food from player ${playerId}: ${violation}`);
+    // The client grows from food it thinks it ate, which would be turned down
+    sendResync(playerId);
+    return;
   }
-  handleAddFood();
-  broadcastMapState();
+  room.mapState.food_positions.splice(foodIndex, 1);
+  const stateData = playerNetworkData.stateData;
+  stateData.score = Math.max(stateData.score + food.score, 0);
+  // Poison takes segments off, and the growth still to come with them
+  playerNetworkData.growthCredit =
+    food.growth >= 0 ? playerNetworkData.growthCredit + food.growth : 0;
+  handleAddFood(room);
+  broadcastMapState(room);
   return;
 }
 
-function handlePlayerMessage(jsonMessage: string) {
+// A boosting snake sheds its tail, which is left behind as a pellet
+function handleBoostDrop(playerId: number, position: PositionCoords) {
+  const room = roomOf(playerId);
+  const playerNetworkData = players.get(playerId);
+  if (!room || !playerNetworkData.alive || !playerNetworkData.stateData.boosting) {
+    return;
+  }
+  const violation = validateBoostDrop(playerNetworkData.stateData, position);
+  if (violation !== null) {
+    console.error(`Rejected boost_drop from player ${playerId}: ${violation}`);
+    return;
+  }
+  // The tail is gone even where no pellet fits, and can't be dropped twice
+  const body = playerNetworkData.stateData.body_segments;
+  body.splice(body.length - 2, 1);
+  if (
+    isBoundary(room.mapState.layout, position.x, position.y) ||
+    isWall(room.mapState.layout, position.x, position.y) ||
+    isFoodPosition(room, position.x, position.y)
+  ) {
+    return;
+  }
+  room.mapState.food_positions.push(createFood(FoodKind.PELLET, position));
+  broadcastMapState(room);
+}
+
+function handleAddPowerUp(room: Room) {
+  const zone = room.mapState.zone;
+  const layout = room.mapState.layout;
+  const position = zone ? getRandomZonePosition(layout, zone) : getRandomPosition(layout);
+  if (isFoodPosition(room, position.x, position.y) || isPowerUpPosition(room, position.x, position.y)) {
+    return;
+  }
+  room.mapState.power_ups.push({ kind: randomPowerUpKind(), ...position });
+}
+
+function startEffect(room: Room, playerId: number, kind: PowerUpKind) {
+  const playerNetworkData = players.get(playerId);
+  const duration = EFFECT_DURATIONS_MS[kind];
+  if (duration > 0) {
+    playerNetworkData.effects.set(kind, Date.now() + duration);
+  }
+  // Clients shrink their own snake when told, bots are shrunk here
+  if (kind === PowerUpKind.SHRINK_OTHERS && playerNetworkData.bot) {
+    playerNetworkData.stateData.body_segments = shrinkBody(
+      playerNetworkData.stateData.body_segments,
+      SHRINK_SEGMENTS
+    );
+  }
+  const effectChange: EffectChange = {
+    player_id: playerId,
+    kind: kind,
+    duration_ms: duration,
+  };
+  const serverMessage: ServerMessage = {
+    message_type: "effect_started",
+    player_id: playerId,
+    message: JSON.stringify(effectChange),
+  };
+  broadcast(room, serverMessage);
+}
+
+function endEffect(room: Room, playerId: number, kind: PowerUpKind) {
+  const effectChange: EffectChange = {
+    player_id: playerId,
+    kind: kind,
+    duration_ms: 0,
+  };
+  const serverMessage: ServerMessage = {
+    message_type: "effect_expired",
+    player_id: playerId,
+    message: JSON.stringify(effectChange),
+  };
+  broadcast(room, serverMessage);
+}
+
+function handlePickUpPowerUp(playerId: number, position: PositionCoords) {
+  const room = roomOf(playerId);
+  if (!room || !players.get(playerId).alive) {
+    return;
+  }
+  const powerUpIndex = room.mapState.power_ups.findIndex(
+    (powerUp) => powerUp.x === position.x && powerUp.y === position.y
+  );
+  if (powerUpIndex === -1) {
+    return;
+  }
+  const [powerUp] = room.mapState.power_ups.splice(powerUpIndex, 1);
+
+  const pickup: PowerUpPickup = { ...powerUp, player_id: playerId };
+  const serverMessage: ServerMessage = {
+    message_type: "power_up_picked",
+    player_id: playerId,
+    message: JSON.stringify(pickup),
+  };
+  broadcast(room, serverMessage);
+
+  if (affectsOthers(powerUp.kind)) {
+    room.playerIds.forEach((id) => {
+      if (id !== playerId && players.get(id).alive && !areTeammates(id, playerId)) {
+        startEffect(room, id, powerUp.kind);
+      }
+    });
+  } else {
+    startEffect(room, playerId, powerUp.kind);
+  }
+  broadcastMapState(room);
+}
+
+function updateEffects(room: Room) {
+  const now = Date.now();
+  room.playerIds.forEach((playerId) => {
+    expireEffects(players.get(playerId).effects, now).forEach((kind) => {
+      endEffect(room, playerId, kind);
+    });
+  });
+}
+
+// A shield takes the hit instead of the snake and is used up
+function absorbWithShield(room: Room, playerId: number): boolean {
+  const playerNetworkData = players.get(playerId);
+  if (!playerNetworkData.effects.delete(PowerUpKind.SHIELD)) {
+    return false;
+  }
+  playerNetworkData.protectedUntil = Date.now() + SHIELD_GRACE_MS;
+  endEffect(room, playerId, PowerUpKind.SHIELD);
+  return true;
+}
+
+// `playerId` is the id of the socket the message came in on, a message can
+// only ever act for the player on the other end of it
+function handlePlayerMessage(playerId: number, jsonMessage: string) {
   try {
     // Parse the JSON message into a JavaScript object
     const serverMessage: ServerMessage = JSON.parse(jsonMessage);
-    // Validate the structure (optional but recommended)
+    if (serverMessage.player_id !== playerId) {
+      console.error(`Player ${playerId} sent a message as player ${serverMessage.player_id}`);
+      return;
+    }
     // switch case match the serverMessage.message_type to the expected values
     switch (serverMessage.message_type) {
+      case "join":
+        handleJoin(playerId, JSON.parse(serverMessage.message));
+        break;
       case "player_state":
-        handlePlayerState(JSON.parse(serverMessage.message));
+        handlePlayerState(playerId, JSON.parse(serverMessage.message), serverMessage.tick ?? 0);
+        break;
+      case "player_state_resynced":
+        handlePlayerResynced(playerId);
         break;
       case "eat_food":
-        handleEatFood(JSON.parse(serverMessage.message));
+        handleEatFood(playerId, JSON.parse(serverMessage.message));
+        break;
+      case "player_died":
+        // Clients only report running into their own body
+        killPlayer({
+          attacker_id: playerId,
+          victim_id: playerId,
+        });
+        break;
+      case "player_respawned":
+        handlePlayerRespawn(playerId, JSON.parse(serverMessage.message));
+        break;
+      case "get_leaderboard":
+        handleGetLeaderboard(
+          playerId,
+          JSON.parse(serverMessage.message)
+        );
+        break;
+      case "list_rooms":
+        handleListRooms(playerId);
+        break;
+      case "create_room":
+        handleCreateRoom(playerId, JSON.parse(serverMessage.message));
+        break;
+      case "join_room":
+        handleJoinRoom(playerId, JSON.parse(serverMessage.message));
+        break;
+      case "leave_room":
+        leaveRoom(playerId);
+        break;
+      case "set_ready":
+        handleSetReady(playerId, JSON.parse(serverMessage.message) === true);
+        break;
+      case "boost_drop":
+        handleBoostDrop(playerId, JSON.parse(serverMessage.message));
+        break;
+      case "pick_up_power_up":
+        handlePickUpPowerUp(playerId, JSON.parse(serverMessage.message));
+        break;
+      case "set_team":
+        handleSetTeam(playerId, JSON.parse(serverMessage.message));
+        break;
+      case "ping":
+        handlePing(playerId, serverMessage.message);
         break;
       default:
         console.error("Invalid message type");
@@ -122,8 +1264,35 @@ wss.on("connection", (ws) => {
   // Assign a unique player ID
   const player_id = nextplayer_id++;
   const stateData: PlayerNetworkData = {
-    stateData: { player_id: player_id, body_segments: [] },
+    stateData: {
+      player_id: player_id,
+      body_segments: [],
+      invulnerable: true,
+      kills: 0,
+      score: 0,
+      name: "",
+      colour: "",
+      team: null,
+      effects: [],
+      ticks_per_move: NORMAL_TICKS_PER_MOVE,
+      boosting: false,
+    },
     ws: ws,
+    profile: null,
+    roomId: null,
+    team: null,
+    effects: new Map(),
+    // Players enter the game once they join a room
+    alive: false,
+    diedAt: 0,
+    protectedUntil: 0,
+    previousHead: null,
+    lastInputTick: 0,
+    growthCredit: 0,
+    resyncSentAt: null,
+    moveBudget: 0,
+    moveBudgetAt: 0,
+    bot: null,
   };
   players.set(player_id, stateData);
 
@@ -134,67 +1303,142 @@ wss.on("connection", (ws) => {
     message: "",
   };
   //console.log(`Player ${player_id} connected. Assigned ID: ${player_id}`);
-  ws.send(JSON.stringify(serverMessage));
-
-  broadcastMapState();
+  sendToPlayer(player_id, serverMessage);
 
   // Listen for messages from this player
   ws.on("message", (message) => {
     // update the state of the player in the server when they send their data
-    handlePlayerMessage(message.toString());
+    handlePlayerMessage(player_id, message.toString());
   });
 
   // Handle when a player disconnects
   ws.on("close", () => {
-    // Remove the player from the list when they disconnect
+    // Remove the player from their room and the list when they disconnect
+    leaveRoom(player_id);
     players.delete(player_id);
   });
 });
 
-function broadcastMapState() {
+function broadcastMapState(room: Room) {
   const serverMessage: ServerMessage = {
     message_type: "map_state",
     player_id: 0,
-    message: JSON.stringify(mapState),
+    message: JSON.stringify(room.mapState),
   };
 
-  broadcast(serverMessage);
+  broadcast(room, serverMessage);
 }
 
-function broadcastPlayerStates() {
-  players.forEach((playerNetworkData, playerId) => {
-    if (playerNetworkData.ws.readyState === WebSocket.OPEN) {
-      // Filter out the current player's data before sending
-      const otherPlayersData = Array.from(players.entries())
-        .filter(([id, _]) => id !== playerId)
-        .map(([_, data]) => data.stateData);
+function broadcastPlayerStates(room: Room) {
+  room.playerIds.forEach((playerId) => {
+    if (isBot(playerId)) {
+      return;
+    }
+    // Filter out the current player's data before sending
+    const otherPlayersData = Array.from(room.playerIds)
+      .filter((id) => id !== playerId && players.get(id).alive)
+      .map((id) => players.get(id).stateData);
 
-      const serverMessage: ServerMessage = {
-        message_type: "player_states",
-        player_id: 0,
-        message: JSON.stringify(otherPlayersData),
-      };
+    const serverMessage: ServerMessage = {
+      message_type: "player_states",
+      player_id: 0,
+      message: JSON.stringify(otherPlayersData),
+    };
+
+    sendToPlayer(playerId, serverMessage);
+  });
+}
 
-      playerNetworkData.ws.send(JSON.stringify(serverMessage));
+// Collisions between snakes are decided here rather than on each client,
+// so every client in the room agrees on who died and who killed them
+function resolveTick(room: Room) {
+  const now = Date.now();
+  const moves: SnakeMove[] = [];
+  room.playerIds.forEach((playerId) => {
+    const playerNetworkData = players.get(playerId);
+    const bodySegments = playerNetworkData.stateData.body_segments;
+    if (!playerNetworkData.alive || bodySegments.length === 0) {
+      return;
+    }
+    moves.push({
+      player_id: playerId,
+      previous_head: playerNetworkData.previousHead,
+      body_segments: bodySegments,
+      invulnerable: now < playerNetworkData.protectedUntil,
+      ghost: playerNetworkData.effects.has(PowerUpKind.GHOST),
+      team: playerNetworkData.team,
+      growing: playerNetworkData.bot
+        ? playerNetworkData.bot.pendingGrowth > 0
+        : playerNetworkData.growthCredit > 0,
+    });
+  });
+
+  const kills = resolveCollisions(
+    moves,
+    room.settings.head_on_rule,
+    room.settings.friendly_fire
+  ).filter((kill) => !absorbWithShield(room, kill.victim_id));
+
+  moves.forEach((move) => {
+    const head = move.body_segments[move.body_segments.length - 1];
+    players.get(move.player_id).previousHead = { x: head.x, y: head.y };
+  });
+  // Snakes caught outside the battle royale zone or on a wall die on their own
+  const zone = room.mapState.zone;
+  moves.forEach((move) => {
+    const head = move.body_segments[move.body_segments.length - 1];
+    const alreadyKilled = kills.some((kill) => kill.victim_id === move.player_id);
+    const outsideZone = zone !== null && !isInsideZone(zone, head.x, head.y);
+    if (!alreadyKilled && (outsideZone || isDeadly(room.mapState.layout, head.x, head.y))) {
+      kills.push({ attacker_id: move.player_id, victim_id: move.player_id });
+    }
+  });
+  kills.forEach(killPlayer);
+}
+
+function simulationTic() {
+  serverTick++;
+  serverTickTime = Date.now();
+  rooms.forEach((room) => {
+    updateEffects(room);
+    if (updateFood(room.mapState.food_positions, Date.now())) {
+      broadcastMapState(room);
+    }
+    updateBots(room);
+    resolveTick(room);
+    if (room.match) {
+      updateMatch(room);
     }
   });
 }
 
-// TODO
 function networkTic() {
-  broadcastPlayerStates();
+  rooms.forEach(broadcastPlayerStates);
 }
 
 function foodSpawn() {
-  if (mapState.food_positions.length < 3) {
-    handleAddFood();
-    broadcastMapState();
-  }
+  rooms.forEach((room) => {
+    if (room.mapState.food_positions.length < 3) {
+      handleAddFood(room);
+      broadcastMapState(room);
+    }
+  });
+}
+
+function powerUpSpawn() {
+  rooms.forEach((room) => {
+    if (room.settings.power_ups && room.mapState.power_ups.length < MAX_POWER_UPS) {
+      handleAddPowerUp(room);
+      broadcastMapState(room);
+    }
+  });
 }
 
 // Periodically broadcast player states every second (1000 ms)
-setInterval(networkTic, 50);
+setInterval(simulationTic, SIMULATION_TICK_TIME);
+setInterval(networkTic, NETWORK_TICK_TIME);
 setInterval(foodSpawn, 10000);
+setInterval(powerUpSpawn, POWER_UP_SPAWN_MS);
 
 console.log("WebSocket server running on ws://localhost:8080");
 
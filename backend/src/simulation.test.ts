@@ -0,0 +1,41 @@
+import { test } from "node:test";
+import assert from "node:assert/strict";
+import { resolveCollisions } from "./simulation.js";
+import { HeadOnRule } from "./types.js";
+import { snakeMove } from "./testing.js";
+
+test("running into the neck behind another head is a kill", () => {
+  const victim = snakeMove(1, [[4, 5]]);
+  const other = snakeMove(2, [[4, 5], [4, 4], [4, 3], [5, 5]]);
+  const kills = resolveCollisions([victim, other], HeadOnRule.BOTH_DIE, true);
+  assert.deepEqual(kills, [{ attacker_id: 2, victim_id: 1 }]);
+});
+
+test("the tail end is free to enter", () => {
+  const mover = snakeMove(1, [[5, 3]]);
+  const other = snakeMove(2, [[5, 5], [5, 4], [5, 3], [5, 6]]);
+  assert.deepEqual(resolveCollisions([mover, other], HeadOnRule.BOTH_DIE, true), []);
+});
+
+test("the tail end of a growing snake stays solid", () => {
+  const mover = snakeMove(1, [[5, 3]]);
+  const other = snakeMove(2, [[5, 5], [5, 4], [5, 3], [5, 6]], true);
+  const kills = resolveCollisions([mover, other], HeadOnRule.BOTH_DIE, true);
+  assert.deepEqual(kills, [{ attacker_id: 2, victim_id: 1 }]);
+});
+
+test("heads meeting follow the head-on rule", () => {
+  const short = snakeMove(1, [[2, 2], [3, 2]]);
+  const long = snakeMove(2, [[4, 2], [5, 2], [3, 2]]);
+  assert.equal(resolveCollisions([short, long], HeadOnRule.BOTH_DIE, true).length, 2);
+  assert.deepEqual(resolveCollisions([short, long], HeadOnRule.LONGER_WINS, true), [
+    { attacker_id: 2, victim_id: 1 },
+  ]);
+});
+
+test("teammates pass through each other without friendly fire", () => {
+  const mover = { ...snakeMove(1, [[4, 4]]), team: 0 };
+  const teammate = { ...snakeMove(2, [[4, 4], [4, 5], [5, 4]]), team: 0 };
+  assert.deepEqual(resolveCollisions([mover, teammate], HeadOnRule.BOTH_DIE, false), []);
+  assert.equal(resolveCollisions([mover, teammate], HeadOnRule.BOTH_DIE, true).length, 1);
+});
@@ -0,0 +1,78 @@
+import { HeadOnRule, Kill, PositionCoords, SnakeMove } from "./types.js";
+
+function samePosition(a: PositionCoords, b: PositionCoords): boolean {
+  return a.x === b.x && a.y === b.y;
+}
+
+function headOf(move: SnakeMove): PositionCoords {
+  return move.body_segments[move.body_segments.length - 1];
+}
+
+function isHeadOn(move: SnakeMove, other: SnakeMove): boolean {
+  if (samePosition(headOf(move), headOf(other))) {
+    return true;
+  }
+  // Two snakes swapping cells pass through each other's heads
+  return (
+    move.previous_head !== null &&
+    other.previous_head !== null &&
+    samePosition(headOf(move), other.previous_head) &&
+    samePosition(headOf(other), move.previous_head)
+  );
+}
+
+function areTeammates(move: SnakeMove, other: SnakeMove): boolean {
+  return (
+    move.player_id !== other.player_id &&
+    move.team !== null &&
+    move.team === other.team
+  );
+}
+
+// Resolve the moves of all snakes in a tick at the same time, so the
+// outcome does not depend on the order the updates arrived in. Without
+// friendly fire, teammates pass through each other.
+export function resolveCollisions(
+  moves: SnakeMove[],
+  headOnRule: HeadOnRule,
+  friendlyFire: boolean
+): Kill[] {
+  const attackers = new Map<number, number>(); // victim id -> attacker id
+  // Spawn protected snakes and ghosts can neither kill nor be killed
+  const solidMoves = moves.filter((move) => !move.invulnerable && !move.ghost);
+
+  solidMoves.forEach((move) => {
+    const head = headOf(move);
+    for (const other of solidMoves) {
+      if (!friendlyFire && areTeammates(move, other)) {
+        continue;
+      }
+      if (other.player_id !== move.player_id && isHeadOn(move, other)) {
+        const loses =
+          headOnRule === HeadOnRule.BOTH_DIE ||
+          move.body_segments.length <= other.body_segments.length;
+        if (loses) {
+          attackers.set(move.player_id, other.player_id);
+          break;
+        }
+        continue;
+      }
+      // The tail end, second to last on the wire, moves away on the snake's
+      // next step unless it is growing, so the cell it occupies is free to
+      // enter. Heads are covered by the head-on check.
+      const tailIndex = other.growing ? -1 : other.body_segments.length - 2;
+      const body = other.body_segments
+        .slice(0, -1)
+        .filter((_, index) => index !== tailIndex);
+      if (body.some((segment) => samePosition(segment, head))) {
+        attackers.set(move.player_id, other.player_id);
+        break;
+      }
+    }
+  });
+
+  return Array.from(attackers, ([victim_id, attacker_id]) => ({
+    attacker_id,
+    victim_id,
+  }));
+}
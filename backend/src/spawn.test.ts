@@ -0,0 +1,88 @@
+import { test } from "node:test";
+import assert from "node:assert/strict";
+import { findSafeSpawn, isFreeSpawnCell } from "./spawn.js";
+import { computeMatchSpawns } from "./match.js";
+import { isWall } from "./grid.js";
+import { Direction, EdgeBehaviour, FoodKind, MapLayout } from "./types.js";
+import { createFood } from "./food.js";
+import { playerState } from "./testing.js";
+
+// A 10 by 10 map walled in everywhere but the two cells at the top left,
+// which are grid cells (1, 1) and (2, 1)
+const POCKET: MapLayout = {
+  name: "Pocket",
+  width: 10,
+  height: 10,
+  edge: EdgeBehaviour.WRAP,
+  walls: [
+    { x: 2, y: 0, width: 8, height: 1 },
+    { x: 0, y: 1, width: 10, height: 9 },
+  ],
+  spawn_points: [],
+  food_zones: [],
+};
+
+const OPEN: MapLayout = { ...POCKET, name: "Open", width: 20, height: 20, walls: [] };
+
+test("spawns are never on a wall, however crowded the map", () => {
+  for (let i = 0; i < 20; i++) {
+    const spawn = findSafeSpawn(POCKET, [], []);
+    assert.ok(!isWall(POCKET, spawn.x, spawn.y), `spawned on a wall at ${spawn.x}, ${spawn.y}`);
+  }
+});
+
+test("spawns are never on another snake", () => {
+  for (let i = 0; i < 20; i++) {
+    const spawn = findSafeSpawn(POCKET, [playerState([[1, 1]], Direction.LEFT)], []);
+    assert.deepEqual([spawn.x, spawn.y], [2, 1]);
+  }
+});
+
+test("spawns keep their distance from other snakes when there is room", () => {
+  const other = playerState([[9, 10], [10, 10], [11, 10]]);
+  for (let i = 0; i < 50; i++) {
+    const spawn = findSafeSpawn(OPEN, [other], []);
+    const distance = Math.min(
+      ...other.body_segments.map((segment) =>
+        Math.max(Math.abs(segment.x - spawn.x), Math.abs(segment.y - spawn.y))
+      )
+    );
+    assert.ok(distance > 3, `spawned ${distance} cells from a snake`);
+  }
+});
+
+test("map spawn points next to a snake are skipped", () => {
+  const layout = {
+    ...OPEN,
+    spawn_points: [
+      { x: 2, y: 2, direction: Direction.UP },
+      { x: 15, y: 15, direction: Direction.DOWN },
+    ],
+  };
+  for (let i = 0; i < 20; i++) {
+    assert.deepEqual(findSafeSpawn(layout, [playerState([[4, 4]])], []), {
+      x: 16,
+      y: 16,
+      direction: Direction.DOWN,
+    });
+  }
+});
+
+test("match spawns fall back to free cells instead of walls", () => {
+  for (let i = 0; i < 20; i++) {
+    const spawns = computeMatchSpawns([1, 2], POCKET);
+    spawns.forEach((spawn) => assert.ok(!isWall(POCKET, spawn.x, spawn.y)));
+    assert.notDeepEqual([spawns[0].x, spawns[0].y], [spawns[1].x, spawns[1].y]);
+  }
+});
+
+test("a respawn cell picked by the client has to be free", () => {
+  const snake = playerState([[4, 5], [5, 5]]);
+  assert.equal(isFreeSpawnCell(OPEN, { x: 10, y: 10 }, [snake], []), true);
+  assert.equal(isFreeSpawnCell(OPEN, { x: 4, y: 5 }, [snake], []), false);
+  // In the path of the head
+  assert.equal(isFreeSpawnCell(OPEN, { x: 7, y: 5 }, [snake], []), false);
+  assert.equal(isFreeSpawnCell(OPEN, { x: 10, y: 10 }, [], [createFood(FoodKind.NORMAL, { x: 10, y: 10 })]), false);
+  assert.equal(isFreeSpawnCell(OPEN, { x: 0, y: 10 }, [], []), false);
+  assert.equal(isFreeSpawnCell(POCKET, { x: 5, y: 5 }, [], []), false);
+});
@@ -0,0 +1,151 @@
+import { BodySegment, Direction, Food, MapLayout, PlayerStateData, PositionCoords } from "./types.js";
+import { BOUNDARY_THICKNESS, isBoundary, isWall, randomCell } from "./grid.js";
+import { NORMAL_TICKS_PER_MOVE } from "./speed.js";
+
+// Cells kept between a spawn and the walls and other snakes,
+// must match SPAWN_CLEARANCE in the client
+export const SPAWN_CLEARANCE = 3;
+
+const DIRECTIONS = [Direction.UP, Direction.DOWN, Direction.LEFT, Direction.RIGHT];
+const DELTAS: Record<Direction, PositionCoords> = {
+  [Direction.UP]: { x: 0, y: -1 },
+  [Direction.DOWN]: { x: 0, y: 1 },
+  [Direction.LEFT]: { x: -1, y: 0 },
+  [Direction.RIGHT]: { x: 1, y: 0 },
+};
+
+export function isNearWall(layout: MapLayout, position: PositionCoords, clearance: number): boolean {
+  for (let dy = -clearance; dy <= clearance; dy++) {
+    for (let dx = -clearance; dx <= clearance; dx++) {
+      if (isWall(layout, position.x + dx, position.y + dy)) {
+        return true;
+      }
+    }
+  }
+  return false;
+}
+
+// Grid cells inside the map that are not walls, maps always have at least one
+export function floorCells(layout: MapLayout): PositionCoords[] {
+  const cells: PositionCoords[] = [];
+  for (let y = BOUNDARY_THICKNESS; y < layout.height + BOUNDARY_THICKNESS; y++) {
+    for (let x = BOUNDARY_THICKNESS; x < layout.width + BOUNDARY_THICKNESS; x++) {
+      if (!isWall(layout, x, y)) {
+        cells.push({ x, y });
+      }
+    }
+  }
+  return cells;
+}
+
+function isOnSnake(snakes: PlayerStateData[], position: PositionCoords): boolean {
+  return snakes.some((snake) =>
+    snake.body_segments.some((segment) => segment.x === position.x && segment.y === position.y)
+  );
+}
+
+function isClearSpawnPosition(
+  position: PositionCoords,
+  snakes: PlayerStateData[],
+  food: Food[],
+  clearance: number
+): boolean {
+  if (food.some((item) => item.x === position.x && item.y === position.y)) {
+    return false;
+  }
+  return snakes.every((snake) => {
+    const nearBody = snake.body_segments.some(
+      (segment) =>
+        Math.max(Math.abs(segment.x - position.x), Math.abs(segment.y - position.y)) <= clearance
+    );
+    // The head is the last segment, look a few cells ahead of it,
+    // further for faster snakes
+    const head = snake.body_segments[snake.body_segments.length - 1];
+    const lookahead =
+      SPAWN_CLEARANCE * 2 * Math.max(Math.floor(NORMAL_TICKS_PER_MOVE / Math.max(snake.ticks_per_move, 1)), 1);
+    let inHeadPath = false;
+    if (head) {
+      const delta = DELTAS[head.direction];
+      for (let step = 1; step <= lookahead && !inHeadPath; step++) {
+        inHeadPath = head.x + delta.x * step === position.x && head.y + delta.y * step === position.y;
+      }
+    }
+    return !nearBody && !inHeadPath;
+  });
+}
+
+function freeRunLength(
+  layout: MapLayout,
+  position: PositionCoords,
+  direction: Direction,
+  snakes: PlayerStateData[]
+): number {
+  const delta = DELTAS[direction];
+  let cell = { x: position.x + delta.x, y: position.y + delta.y };
+  let run = 0;
+  while (!isBoundary(layout, cell.x, cell.y) && !isWall(layout, cell.x, cell.y) && !isOnSnake(snakes, cell)) {
+    run++;
+    cell = { x: cell.x + delta.x, y: cell.y + delta.y };
+  }
+  return run;
+}
+
+// Point a freshly spawned snake toward the longest straight run of free cells
+function openDirection(layout: MapLayout, position: PositionCoords, snakes: PlayerStateData[]): Direction {
+  const runs = DIRECTIONS.map((direction) => freeRunLength(layout, position, direction, snakes));
+  const longest = Math.max(...runs);
+  const open = DIRECTIONS.filter((_, index) => runs[index] === longest);
+  return open[Math.floor(Math.random() * open.length)];
+}
+
+// Whether a snake may respawn on a cell its client picked: inside the map and
+// off the walls, food and other snakes, and out of the path of their heads.
+// How much room to keep beyond that is left to the client's search.
+export function isFreeSpawnCell(
+  layout: MapLayout,
+  position: PositionCoords,
+  snakes: PlayerStateData[],
+  food: Food[]
+): boolean {
+  return (
+    !isBoundary(layout, position.x, position.y) &&
+    !isWall(layout, position.x, position.y) &&
+    isClearSpawnPosition(position, snakes, food, 0)
+  );
+}
+
+// The same search the client makes for a respawn: one of the map's spawn
+// points that is clear of other snakes, or a cell that keeps SPAWN_CLEARANCE
+// cells of space to the boundary, the walls and every other snake, and that
+// is not on food or in the path of another snake's head. The clearance is
+// relaxed one step at a time if the map is too crowded, and as a last resort
+// any cell that is not a wall is taken.
+export function findSafeSpawn(layout: MapLayout, snakes: PlayerStateData[], food: Food[]): BodySegment {
+  const spawnPoints = layout.spawn_points
+    .map((spawn) => ({
+      x: spawn.x + BOUNDARY_THICKNESS,
+      y: spawn.y + BOUNDARY_THICKNESS,
+      direction: spawn.direction,
+    }))
+    .filter((spawn) => isClearSpawnPosition(spawn, snakes, food, SPAWN_CLEARANCE));
+  if (spawnPoints.length > 0) {
+    return spawnPoints[Math.floor(Math.random() * spawnPoints.length)];
+  }
+  for (let clearance = SPAWN_CLEARANCE; clearance >= 0; clearance--) {
+    const candidates: PositionCoords[] = [];
+    for (let y = BOUNDARY_THICKNESS + clearance; y < layout.height + BOUNDARY_THICKNESS - clearance; y++) {
+      for (let x = BOUNDARY_THICKNESS + clearance; x < layout.width + BOUNDARY_THICKNESS - clearance; x++) {
+        const position = { x, y };
+        if (!isNearWall(layout, position, clearance) && isClearSpawnPosition(position, snakes, food, clearance)) {
+          candidates.push(position);
+        }
+      }
+    }
+    if (candidates.length > 0) {
+      const position = randomCell(candidates);
+      return { ...position, direction: openDirection(layout, position, snakes) };
+    }
+  }
+  const position = randomCell(floorCells(layout));
+  return { ...position, direction: openDirection(layout, position, snakes) };
+}
@@ -0,0 +1,22 @@
+import { PowerUpKind } from "./types.js";
+
+// Snake speed as the number of simulation ticks between two moves,
+// must match the values in game_grid.rs and state.rs of the shared crate
+export const NORMAL_TICKS_PER_MOVE = 2;
+export const FAST_TICKS_PER_MOVE = 1;
+export const SLOW_TICKS_PER_MOVE = 4;
+// Must match BOOST_MIN_LENGTH in the client
+export const BOOST_MIN_LENGTH = 4;
+
+// Speed and slow cancel each other out, boosting doubles the speed
+export function ticksPerMove(effects: Map<PowerUpKind, number>, boosting: boolean): number {
+  const fast = effects.has(PowerUpKind.SPEED);
+  const slow = effects.has(PowerUpKind.SLOW);
+  let ticks = NORMAL_TICKS_PER_MOVE;
+  if (fast && !slow) {
+    ticks = FAST_TICKS_PER_MOVE;
+  } else if (slow && !fast) {
+    ticks = SLOW_TICKS_PER_MOVE;
+  }
+  return boosting ? Math.max(1, Math.floor(ticks / 2)) : ticks;
+}
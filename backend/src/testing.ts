@@ -0,0 +1,58 @@
+import { Direction, PlayerNetworkData, PlayerStateData, SnakeMove } from "./types.js";
+
+// Snakes for the tests, built from cells listed neck first and head last,
+// like on the wire
+
+export function playerState(
+  cells: [number, number][],
+  direction = Direction.RIGHT,
+  playerId = 1
+): PlayerStateData {
+  return {
+    player_id: playerId,
+    body_segments: cells.map(([x, y]) => ({ x, y, direction })),
+    invulnerable: false,
+    kills: 0,
+    score: 0,
+    name: "",
+    colour: "",
+    team: null,
+    effects: [],
+    ticks_per_move: 2,
+    boosting: false,
+  };
+}
+
+export function snakeMove(playerId: number, cells: [number, number][], growing = false): SnakeMove {
+  return {
+    player_id: playerId,
+    previous_head: null,
+    body_segments: playerState(cells).body_segments,
+    invulnerable: false,
+    ghost: false,
+    team: null,
+    growing,
+  };
+}
+
+// A live player in a room, as the server keeps them
+export function playerNetworkData(stateData: PlayerStateData): PlayerNetworkData {
+  return {
+    stateData,
+    ws: null,
+    profile: null,
+    roomId: 1,
+    team: null,
+    effects: new Map(),
+    alive: true,
+    diedAt: 0,
+    protectedUntil: 0,
+    previousHead: null,
+    lastInputTick: 0,
+    growthCredit: 0,
+    resyncSentAt: null,
+    moveBudget: 0,
+    moveBudgetAt: 0,
+    bot: null,
+  };
+}
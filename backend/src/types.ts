@@ -16,17 +16,76 @@ export interface BodySegment {
 export interface PlayerStateData {
   player_id: number;
   body_segments: BodySegment[];
+  invulnerable: boolean;
+  kills: number;
+  score: number;
+  name: string;
+  colour: string;
+  team: number | null;
+  effects: PowerUpKind[];
+  ticks_per_move: number; // the snake's speed, lower is faster
+  boosting: boolean;
+}
+
+// Chosen by the player on the join screen
+export interface PlayerProfile {
+  name: string;
+  colour: string;
 }
 
 export interface PlayerNetworkData {
   stateData: PlayerStateData;
-  ws: WebSocket;
+  ws: WebSocket | null; // null for bots
+  profile: PlayerProfile | null; // null until the player has joined
+  roomId: number | null; // null while in the lobby
+  team: number | null; // null in rooms without teams
+  effects: Map<PowerUpKind, number>; // Date.now() each active effect runs out
+  alive: boolean;
+  diedAt: number; // Date.now() of the last death
+  protectedUntil: number; // Date.now() until which spawn protection lasts
+  previousHead: PositionCoords | null; // head at the previous network tick
+  lastInputTick: number; // client tick of the newest player_state taken, 0 after spawning
+  growthCredit: number; // segments the snake may still grow by from food it ate
+  resyncSentAt: number | null; // Date.now() the last correction went out, null once the client took it
+  moveBudget: number; // moves the player may make right away, topped up as time passes
+  moveBudgetAt: number; // Date.now() the move budget was last topped up
+  bot: BotState | null; // null for human players
+}
+
+export enum BotDifficulty {
+  EASY = "easy",
+  NORMAL = "normal",
+  HARD = "hard",
+}
+
+// Bots are moved by the server rather than by a client
+export interface BotState {
+  difficulty: BotDifficulty;
+  moveTicks: number; // simulation ticks since the last move
+  pendingGrowth: number; // segments still to be added from eaten food
+}
+
+// What happens to a snake's body when it dies
+export enum CorpseBehaviour {
+  VANISH = "vanish",
+  FOOD = "food",
 }
 
 export interface ServerMessage {
   message_type: string;
   player_id: number;
   message: string;
+  // The server tick it was sent on, or for inputs the client tick they were
+  // made on, 0 from clients that have not synced their clock yet
+  tick?: number;
+}
+
+// The answer to a ping, for the client to estimate the server's clock
+export interface Pong {
+  client_time: number; // echoed from the ping
+  server_time: number; // Date.now() when the ping was answered
+  tick: number;
+  tick_time: number; // Date.now() when the current tick started
 }
 
 export interface PositionCoords {
@@ -34,6 +93,245 @@ export interface PositionCoords {
   y: number;
 }
 
+// Inclusive cell bounds of the playable area in battle royale rooms
+export interface Zone {
+  left: number;
+  top: number;
+  right: number;
+  bottom: number;
+}
+
+export enum PowerUpKind {
+  SPEED = "speed",
+  SLOW = "slow",
+  GHOST = "ghost", // pass through other snakes
+  SHRINK_OTHERS = "shrink_others",
+  MAGNET = "magnet", // eat food from a distance
+  SHIELD = "shield", // survive one hit
+}
+
+export interface PowerUp {
+  kind: PowerUpKind;
+  x: number;
+  y: number;
+}
+
+// player_id picked up a power-up of kind at x, y
+export interface PowerUpPickup extends PowerUp {
+  player_id: number;
+}
+
+// An effect starting on or running out for player_id
+export interface EffectChange {
+  player_id: number;
+  kind: PowerUpKind;
+  duration_ms: number;
+}
+
+export enum FoodKind {
+  NORMAL = "normal",
+  GOLDEN = "golden",
+  POISON = "poison", // shrinks the snake that eats it
+  DECAYING = "decaying",
+  PELLET = "pellet", // shed by boosting snakes
+}
+
+export interface Food extends PositionCoords {
+  kind: FoodKind;
+  growth: number; // segments gained, negative for segments lost
+  score: number;
+  expires_at: number | null; // Date.now() the server removes it, null if never
+}
+
+// What happens to a snake running off the edge of the map
+export enum EdgeBehaviour {
+  WRAP = "wrap",
+  SOLID = "solid",
+}
+
+// A block of cells, in map coordinates starting at 0 in the top left
+export interface Rect {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
+export interface SpawnPoint {
+  x: number;
+  y: number;
+  direction: Direction;
+}
+
+export interface MapLayout {
+  name: string;
+  width: number;
+  height: number;
+  edge: EdgeBehaviour;
+  walls: Rect[];
+  spawn_points: SpawnPoint[];
+  food_zones: Rect[]; // food only spawns in these, anywhere if there are none
+}
+
 export interface MapState {
-  food_positions: PositionCoords[];
+  food_positions: Food[];
+  power_ups: PowerUp[];
+  zone: Zone | null; // null when the whole grid is playable
+  next_zone: Zone | null; // what the zone shrinks to next
+  layout: MapLayout;
+}
+
+// How two snakes meeting head to head are resolved
+export enum HeadOnRule {
+  BOTH_DIE = "both_die",
+  LONGER_WINS = "longer_wins",
+}
+
+// One snake's move in a network tick, the head is the last body segment
+export interface SnakeMove {
+  player_id: number;
+  previous_head: PositionCoords | null;
+  body_segments: BodySegment[];
+  invulnerable: boolean;
+  ghost: boolean;
+  team: number | null;
+  growing: boolean; // the tail stays put on the next step
+}
+
+// attacker_id equals victim_id for self-inflicted deaths
+export interface Kill {
+  attacker_id: number;
+  victim_id: number;
+}
+
+// A kill as broadcast to the clients, with names so every kill feed agrees
+export interface KillReport extends Kill {
+  attacker_name: string;
+  victim_name: string;
+}
+
+export enum LeaderboardPeriod {
+  DAILY = "daily",
+  ALL_TIME = "all_time",
+}
+
+// The score a snake had when it died
+export interface LeaderboardEntry {
+  name: string;
+  score: number;
+  kills: number;
+  length: number;
+  achieved_at: number; // Date.now() of the death
+}
+
+export interface Leaderboard {
+  period: LeaderboardPeriod;
+  entries: LeaderboardEntry[];
+}
+
+export enum RoomMode {
+  ENDLESS = "endless", // drop in, respawn forever
+  MATCH = "match", // rounds with a ready-check and a winner
+  BATTLE_ROYALE = "battle_royale", // match rounds in a shrinking zone
+}
+
+// Rules chosen when a room is created
+export interface RoomSettings {
+  name: string;
+  private: boolean; // private rooms are only joinable by code
+  max_players: number;
+  head_on_rule: HeadOnRule;
+  corpse_behaviour: CorpseBehaviour;
+  mode: RoomMode;
+  match_time_limit: number; // seconds
+  team_count: number; // 0 for every snake for itself
+  friendly_fire: boolean; // whether teammates' bodies are solid
+  power_ups: boolean;
+  map: string; // name of a loaded map, empty for the classic map
+  bot_count: number;
+  bot_difficulty: BotDifficulty;
+}
+
+export enum MatchPhase {
+  WAITING = "waiting",
+  COUNTDOWN = "countdown",
+  PLAYING = "playing",
+  FINISHED = "finished",
+}
+
+export interface MatchResult {
+  player_id: number;
+  name: string;
+  team: number | null;
+  placement: number;
+  kills: number;
+  survived: boolean;
+}
+
+// A team's combined result, teams are ranked by survivors and then kills
+export interface TeamResult {
+  team: number;
+  placement: number;
+  kills: number;
+  survivors: number;
+}
+
+export interface MatchPlayer {
+  player_id: number;
+  name: string;
+  team: number | null;
+}
+
+export interface MatchState {
+  phase: MatchPhase;
+  phaseEndsAt: number; // Date.now() the countdown or round runs out
+  readyIds: Set<number>;
+  participantIds: number[];
+  killsAtStart: Map<number, number>;
+  results: MatchResult[];
+  teamResults: TeamResult[];
+  nextZoneShrinkAt: number;
+  lastSecondsLeft: number; // the match state is rebroadcast when this changes
+}
+
+// What clients are told about the match in their room
+export interface MatchInfo {
+  phase: MatchPhase;
+  seconds_left: number;
+  ready_ids: number[];
+  player_count: number;
+  players: MatchPlayer[];
+  results: MatchResult[];
+  team_results: TeamResult[];
+}
+
+// Where each snake starts a round, everyone spawns at the same time
+export interface MatchSpawn {
+  player_id: number;
+  x: number;
+  y: number;
+  direction: Direction;
+}
+
+export interface Room {
+  id: number;
+  code: string;
+  settings: RoomSettings;
+  mapState: MapState;
+  playerIds: Set<number>;
+  match: MatchState | null; // only set in match mode rooms
+}
+
+// What the lobby shows about a room
+export interface RoomInfo {
+  room_id: number;
+  code: string;
+  settings: RoomSettings;
+  player_count: number;
+}
+
+// Rooms are joined by id from the room list or by their join code
+export interface JoinRoomRequest {
+  room_id: number | null;
+  code: string | null;
 }
@@ -0,0 +1,188 @@
+import { test } from "node:test";
+import assert from "node:assert/strict";
+import {
+  MOVE_BURST,
+  needsResync,
+  RESYNC_RETRY_MS,
+  takeMove,
+  validateBoostDrop,
+  validateFoodClaim,
+  validateMove,
+} from "./validation.js";
+import { EdgeBehaviour, FoodKind, MapLayout, PlayerStateData } from "./types.js";
+import { createFood } from "./food.js";
+import { playerNetworkData, playerState } from "./testing.js";
+
+const LAYOUT: MapLayout = {
+  name: "Test",
+  width: 10,
+  height: 10,
+  edge: EdgeBehaviour.WRAP,
+  walls: [],
+  spawn_points: [],
+  food_zones: [],
+};
+
+// Heading right along row 5 with the tail at (2, 5)
+const PREVIOUS = playerState([[4, 5], [3, 5], [2, 5], [5, 5]]);
+
+test("one step with the body following is a move", () => {
+  assert.equal(validateMove(LAYOUT, PREVIOUS, playerState([[5, 5], [4, 5], [3, 5], [6, 5]]), 0), null);
+  assert.equal(validateMove(LAYOUT, PREVIOUS, playerState([[5, 5], [4, 5], [3, 5], [5, 4]]), 0), null);
+});
+
+test("the head moves exactly one cell per update", () => {
+  assert.notEqual(validateMove(LAYOUT, PREVIOUS, playerState([[5, 5], [4, 5], [3, 5], [7, 5]]), 0), null);
+  assert.notEqual(validateMove(LAYOUT, PREVIOUS, playerState([[4, 5], [3, 5], [2, 5], [5, 5]]), 0), null);
+});
+
+test("the head wraps around the edge in one step", () => {
+  const atEdge = playerState([[10, 5], [11, 5]]);
+  assert.equal(validateMove(LAYOUT, atEdge, playerState([[11, 5], [0, 5]]), 0), null);
+});
+
+test("the body has to follow the head", () => {
+  // The body left where it was
+  assert.notEqual(validateMove(LAYOUT, PREVIOUS, playerState([[4, 5], [3, 5], [2, 5], [6, 5]]), 0), null);
+  // The body moved somewhere else entirely
+  assert.notEqual(validateMove(LAYOUT, PREVIOUS, playerState([[5, 6], [4, 6], [3, 6], [6, 5]]), 0), null);
+});
+
+test("the tail only stays put while there is growth left", () => {
+  const grown = playerState([[5, 5], [4, 5], [3, 5], [2, 5], [6, 5]]);
+  assert.notEqual(validateMove(LAYOUT, PREVIOUS, grown, 0), null);
+  assert.equal(validateMove(LAYOUT, PREVIOUS, grown, 1), null);
+  const grownTwice = playerState([[5, 5], [4, 5], [3, 5], [2, 5], [1, 5], [6, 5]]);
+  assert.notEqual(validateMove(LAYOUT, PREVIOUS, grownTwice, 5), null);
+});
+
+test("segments may be cut off the tail end", () => {
+  assert.equal(validateMove(LAYOUT, PREVIOUS, playerState([[5, 5], [6, 5]]), 0), null);
+  assert.equal(validateMove(LAYOUT, PREVIOUS, playerState([[6, 5]]), 0), null);
+  assert.notEqual(validateMove(LAYOUT, PREVIOUS, playerState([[4, 5], [3, 5], [6, 5]]), 0), null);
+});
+
+test("the first update after joining can start anywhere but not long", () => {
+  const joined = playerState([]);
+  assert.equal(validateMove(LAYOUT, joined, playerState([[7, 2]]), 0), null);
+  assert.notEqual(validateMove(LAYOUT, joined, playerState([[6, 2], [7, 2]]), 0), null);
+  assert.notEqual(validateMove(LAYOUT, joined, playerState([]), 0), null);
+});
+
+test("a snake turned down for growing carries on from the state sent back", () => {
+  const player = playerNetworkData(PREVIOUS);
+  // The client ate food the server has two cells away, so no growth is allowed
+  const food = createFood(FoodKind.NORMAL, { x: 7, y: 5 });
+  assert.notEqual(validateFoodClaim(PREVIOUS, food, false), null);
+  const grown = playerState([[5, 5], [4, 5], [3, 5], [2, 5], [6, 5]]);
+  assert.notEqual(takeMove(LAYOUT, player, grown, 1, 50, 1000), null);
+  assert.equal(needsResync(player, 1000), true);
+  player.resyncSentAt = 1000;
+  // Moves made before the correction arrived go on being turned down
+  const ahead = playerState([[6, 5], [5, 5], [4, 5], [3, 5], [7, 5]]);
+  assert.notEqual(takeMove(LAYOUT, player, ahead, 2, 50, 1050), null);
+  assert.equal(needsResync(player, 1000 + RESYNC_RETRY_MS - 1), false);
+  // Even one that follows on, it could be behind the correction
+  const next = playerState([[5, 5], [4, 5], [3, 5], [6, 5]]);
+  assert.notEqual(takeMove(LAYOUT, player, next, 3, 50, 1100), null);
+  // The client snaps back to the state it was sent, says so and moves on from there
+  assert.deepEqual(player.stateData, PREVIOUS);
+  player.resyncSentAt = null;
+  assert.equal(takeMove(LAYOUT, player, next, 4, 50, 1150), null);
+});
+
+test("boosting sheds the tail and nothing else", () => {
+  assert.equal(validateBoostDrop(PREVIOUS, { x: 2, y: 5 }), null);
+  assert.notEqual(validateBoostDrop(PREVIOUS, { x: 3, y: 5 }), null);
+  assert.notEqual(validateBoostDrop(PREVIOUS, { x: 5, y: 5 }), null);
+  assert.notEqual(validateBoostDrop(playerState([[5, 5]]), { x: 5, y: 5 }), null);
+});
+
+test("moves are taken no quicker than the snake's speed, give or take a burst", () => {
+  const player = playerNetworkData(playerState([[5, 5]]));
+  let x = 5;
+  const step = (now: number) => {
+    const violation = takeMove(LAYOUT, player, playerState([[x + 1, 5]]), x, 50, now);
+    if (violation === null) {
+      player.stateData = playerState([[++x, 5]]);
+    }
+    return violation;
+  };
+  // Updates held up on the way arrive together
+  for (let i = 0; i < MOVE_BURST; i++) {
+    assert.equal(step(1000), null);
+  }
+  assert.notEqual(step(1000), null);
+  assert.equal(step(1050), null);
+  assert.notEqual(step(1060), null);
+  // Steady updates at the snake's speed are always taken
+  for (let now = 1100; now < 2000; now += 50) {
+    assert.equal(step(now), null);
+  }
+});
+
+// A message on its way between the client and the server
+interface InFlight {
+  at: number;
+  deliver: (now: number) => void;
+}
+
+test("a session over a connection that loses and reorders messages gets back in step", () => {
+  const MOVE_INTERVAL = 50;
+  const LATENCY = 40;
+  const cellsOf = (playerData: PlayerStateData): [number, number][] =>
+    playerData.body_segments.map((segment) => [segment.x, segment.y]);
+  // One move to the right, wrapping round the map and its boundary
+  const step = (cells: [number, number][]): [number, number][] => {
+    const [x, y] = cells[cells.length - 1];
+    return [[x, y], ...cells.slice(0, -2), [(x + 1) % 12, y]];
+  };
+
+  // Both ways, every seventh message is lost and every fifth held back for
+  // the next ones to overtake, like the conditioner does
+  let lossy = true;
+  let sent = 0;
+  const inFlight: InFlight[] = [];
+  const send = (now: number, deliver: (now: number) => void) => {
+    sent++;
+    if (lossy && sent % 7 === 0) {
+      return;
+    }
+    const held = lossy && sent % 5 === 0 ? 100 : 0;
+    inFlight.push({ at: now + LATENCY + held, deliver });
+  };
+
+  let client: [number, number][] = [[4, 5], [3, 5], [2, 5], [5, 5]];
+  const player = playerNetworkData(playerState(client));
+  // What handlePlayerState and sendResync do on the server
+  const receiveUpdate = (tick: number, next: PlayerStateData) => (now: number) => {
+    if (tick < player.lastInputTick) {
+      return;
+    }
+    if (takeMove(LAYOUT, player, next, tick, MOVE_INTERVAL, now) === null) {
+      player.stateData = next;
+    } else if (needsResync(player, now)) {
+      player.resyncSentAt = now;
+      const correction = cellsOf(player.stateData);
+      send(now, (now) => {
+        client = correction;
+        send(now, () => (player.resyncSentAt = null));
+      });
+    }
+  };
+
+  for (let now = 0, tick = 1; now < 4000; now += 10) {
+    lossy = now < 2000;
+    inFlight.sort((a, b) => a.at - b.at);
+    while (inFlight.length > 0 && inFlight[0].at <= now) {
+      const message = inFlight.shift();
+      message.deliver(message.at);
+    }
+    if (now % MOVE_INTERVAL === 0) {
+      client = step(client);
+      send(now, receiveUpdate(tick++, playerState(client)));
+    }
+  }
+  assert.deepEqual(cellsOf(player.stateData), client);
+  assert.equal(player.resyncSentAt, null);
+});
@@ -0,0 +1,161 @@
+import { BOUNDARY_THICKNESS } from "./grid.js";
+import { Food, FoodKind, MapLayout, PlayerNetworkData, PlayerStateData, PositionCoords } from "./types.js";
+
+// Must match MAGNET_RADIUS in the client
+export const MAGNET_RADIUS = 2;
+// A player whose update was turned down is sent the state the server kept to
+// carry on from, and updates are turned down until the client says it took
+// it. Taking the ones already on their way would leave the correction behind
+// the snake by the time it arrives. It is sent again if nothing is heard back
+// for this long, in case it or the answer was lost.
+export const RESYNC_RETRY_MS = 1000;
+// Updates may arrive this many moves ahead of the snake's speed, bunched up
+// by jitter on the way
+export const MOVE_BURST = 3;
+
+function headOf(playerData: PlayerStateData): PositionCoords | undefined {
+  return playerData.body_segments[playerData.body_segments.length - 1];
+}
+
+// Steps between two cells, going the short way round the edges of the
+// grid like snakes do when they wrap
+function gridDistance(layout: MapLayout, a: PositionCoords, b: PositionCoords): number {
+  const width = layout.width + BOUNDARY_THICKNESS * 2;
+  const height = layout.height + BOUNDARY_THICKNESS * 2;
+  const dx = Math.abs(a.x - b.x);
+  const dy = Math.abs(a.y - b.y);
+  return Math.min(dx, width - dx) + Math.min(dy, height - dy);
+}
+
+// Segments gained since the last accepted update, a snake that has not sent
+// one yet counts as the head it spawns as
+export function growthOf(previous: PlayerStateData, next: PlayerStateData): number {
+  return next.body_segments.length - Math.max(previous.body_segments.length, 1);
+}
+
+// Returns why an update does not follow from the last accepted one, or null
+// if it does. Every update is one move: the head steps onto a neighbouring
+// cell, the old head becomes the neck and the rest of the body moves up
+// behind it, leaving the tail where it was only while there is growth left
+// from eaten food. Segments may be missing from the tail end, cut off by
+// poison, a shrink or boosting.
+export function validateMove(
+  layout: MapLayout,
+  previous: PlayerStateData,
+  next: PlayerStateData,
+  growthCredit: number
+): string | null {
+  const previousHead = headOf(previous);
+  const nextHead = headOf(next);
+  if (nextHead === undefined) {
+    return "no head";
+  }
+  const growth = growthOf(previous, next);
+  if (growth > Math.min(1, growthCredit)) {
+    return `body grew by ${growth} segments with ${growthCredit} left to grow`;
+  }
+  // The head can start anywhere right after joining a room
+  if (previousHead === undefined) {
+    return null;
+  }
+  const distance = gridDistance(layout, previousHead, nextHead);
+  if (distance !== 1) {
+    return `head moved ${distance} cells in one move`;
+  }
+  // body_segments runs from the neck to the tail and ends with the head
+  const followed = [previousHead, ...previous.body_segments.slice(0, -1)];
+  const body = next.body_segments.slice(0, -1);
+  const stray = body.findIndex(
+    (segment, index) => segment.x !== followed[index].x || segment.y !== followed[index].y
+  );
+  if (stray !== -1) {
+    return `body segment ${stray + 1} is not where the one ahead of it was`;
+  }
+  return null;
+}
+
+// Takes an update into the player's move bookkeeping if it follows from the
+// last accepted one and comes no quicker than the snake moves, a move every
+// moveInterval milliseconds give or take MOVE_BURST. Returns why it was
+// turned down otherwise. Saving the new state is left to the caller.
+export function takeMove(
+  layout: MapLayout,
+  playerNetworkData: PlayerNetworkData,
+  next: PlayerStateData,
+  tick: number,
+  moveInterval: number,
+  now: number
+): string | null {
+  if (playerNetworkData.resyncSentAt !== null) {
+    return "sent before the client took the correction";
+  }
+  const elapsed = now - playerNetworkData.moveBudgetAt;
+  playerNetworkData.moveBudget = Math.min(playerNetworkData.moveBudget + elapsed / moveInterval, MOVE_BURST);
+  playerNetworkData.moveBudgetAt = now;
+  if (playerNetworkData.moveBudget < 1) {
+    return `moved faster than every ${moveInterval}ms`;
+  }
+  const previous = playerNetworkData.stateData;
+  const violation = validateMove(layout, previous, next, playerNetworkData.growthCredit);
+  if (violation !== null) {
+    return violation;
+  }
+  playerNetworkData.lastInputTick = tick;
+  playerNetworkData.growthCredit -= Math.max(growthOf(previous, next), 0);
+  playerNetworkData.moveBudget -= 1;
+  return null;
+}
+
+// Whether a turned down player should be sent the state the server kept,
+// once until they take it or again if that is long overdue
+export function needsResync(playerNetworkData: PlayerNetworkData, now: number): boolean {
+  const sentAt = playerNetworkData.resyncSentAt;
+  return sentAt === null || now - sentAt >= RESYNC_RETRY_MS;
+}
+
+// Forget the moves of a snake's last life, the next update can start anywhere
+// and finds the move budget topped up
+export function resetMoves(playerNetworkData: PlayerNetworkData) {
+  playerNetworkData.lastInputTick = 0;
+  playerNetworkData.growthCredit = 0;
+  playerNetworkData.resyncSentAt = null;
+  playerNetworkData.moveBudget = 0;
+  playerNetworkData.moveBudgetAt = 0;
+}
+
+// Returns why a snake could not have eaten the food, or null if it could.
+// Food is eaten from under the head, a magnet pulls in all but poison
+// from around it.
+export function validateFoodClaim(
+  playerData: PlayerStateData,
+  food: Food,
+  magnet: boolean
+): string | null {
+  const head = headOf(playerData);
+  if (head === undefined) {
+    return "no head";
+  }
+  const distance = Math.max(Math.abs(food.x - head.x), Math.abs(food.y - head.y));
+  if (distance === 0) {
+    return null;
+  }
+  if (magnet && food.kind !== FoodKind.POISON && distance <= MAGNET_RADIUS) {
+    return null;
+  }
+  return `food is ${distance} cells from the head`;
+}
+
+// Returns why a boosting snake could not have shed a segment there, or null
+// if it could. Segments come off the tail end, which is the last one before
+// the head in body_segments.
+export function validateBoostDrop(playerData: PlayerStateData, position: PositionCoords): string | null {
+  const body = playerData.body_segments;
+  const tail = body[body.length - 2];
+  if (tail === undefined) {
+    return "no tail";
+  }
+  if (tail.x !== position.x || tail.y !== position.y) {
+    return "drop is not at the tail";
+  }
+  return null;
+}
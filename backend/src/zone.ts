@@ -0,0 +1,48 @@
+import { MapLayout, PositionCoords, Zone } from "./types.js";
+import { BOUNDARY_THICKNESS, foodCells, randomCell } from "./grid.js";
+
+// The zone stops shrinking once it is this many cells wide or high
+const MIN_ZONE_SIZE = 6;
+
+// The whole map inside the boundary
+export function fullZone(layout: MapLayout): Zone {
+  return {
+    left: BOUNDARY_THICKNESS,
+    top: BOUNDARY_THICKNESS,
+    right: layout.width + BOUNDARY_THICKNESS - 1,
+    bottom: layout.height + BOUNDARY_THICKNESS - 1,
+  };
+}
+
+// The zone one cell smaller on every side, or null if it is as small as it gets
+export function shrinkZone(zone: Zone): Zone | null {
+  if (
+    zone.right - zone.left + 1 - 2 < MIN_ZONE_SIZE ||
+    zone.bottom - zone.top + 1 - 2 < MIN_ZONE_SIZE
+  ) {
+    return null;
+  }
+  return {
+    left: zone.left + 1,
+    top: zone.top + 1,
+    right: zone.right - 1,
+    bottom: zone.bottom - 1,
+  };
+}
+
+export function isInsideZone(zone: Zone, x: number, y: number): boolean {
+  return x >= zone.left && x <= zone.right && y >= zone.top && y <= zone.bottom;
+}
+
+// A food cell inside the zone, or any cell in it once the zone has closed
+// in past the map's food zones
+export function getRandomZonePosition(layout: MapLayout, zone: Zone): PositionCoords {
+  const cells = foodCells(layout).filter((cell) => isInsideZone(zone, cell.x, cell.y));
+  if (cells.length > 0) {
+    return randomCell(cells);
+  }
+  return {
+    x: zone.left + Math.floor(Math.random() * (zone.right - zone.left + 1)),
+    y: zone.top + Math.floor(Math.random() * (zone.bottom - zone.top + 1)),
+  };
+}
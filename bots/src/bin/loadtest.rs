@@ -0,0 +1,484 @@
+//! Connect lots of bot players to a running server and measure how it copes.
+//!
+//! ```text
+//! cargo run --release --bin loadtest -- [--url URL] [--clients N] [--duration S] [--ramp-up S] [--room ID] [--strategy NAME]
+//! ```
+//!
+//! Each client is a thread speaking the same protocol as the browser client:
+//! it joins, enters a room, moves its snake with a strategy and respawns
+//! after dying. Latency is the round trip of a `ping`, and
+//! `player_states` broadcasts that never arrive are counted as dropped.
+
+use std::io::ErrorKind;
+use std::net::TcpStream;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::Serialize;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+use craby_snake_bots::protocol::{JoinRoomRequest, PlayerProfile, Pong, ServerMessage};
+use craby_snake_bots::state::{BodySegment, Direction, HNum, MapState, PlayerStateData, NORMAL_TICKS_PER_MOVE};
+use craby_snake_bots::strategies::{builtin, BUILTIN_STRATEGIES};
+use craby_snake_bots::strategy::{GameView, Strategy};
+
+// Must match TICK_TIME in the client, snakes move every NORMAL_TICKS_PER_MOVE ticks
+const TICK_TIME: Duration = Duration::from_millis(25);
+// Must match NETWORK_TICK_TIME on the server, player_states arrive this often
+const NETWORK_TICK_TIME: Duration = Duration::from_millis(50);
+// Must match RESPAWN_DELAY_MS on the server, with a little slack
+const RESPAWN_DELAY: Duration = Duration::from_millis(3100);
+const PROBE_INTERVAL: Duration = Duration::from_secs(1);
+// How long a client waits for a message before checking its timers
+const READ_TIMEOUT: Duration = Duration::from_millis(5);
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+const BOT_COLOURS: [&str; 8] = [
+    "#24b058", "#f19f4e", "#3f99ff", "#e05fd0", "#f5d547", "#4fd8e8", "#b48cff", "#f95c8a",
+];
+
+struct Options {
+    url: String,
+    clients: u64,
+    duration: Duration,
+    ramp_up: Duration,
+    room_id: u64,
+    strategy: String,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        url: "ws://localhost:8080".to_string(),
+        clients: 50,
+        duration: Duration::from_secs(30),
+        ramp_up: Duration::from_secs(5),
+        room_id: 1,
+        strategy: "greedy".to_string(),
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| format!("{} needs a value", arg))?;
+        let number = || value.parse::<u64>().map_err(|_| format!("{} needs a number", arg));
+        match arg.as_str() {
+            "--url" => options.url = value.clone(),
+            "--clients" => options.clients = number()?,
+            "--duration" => options.duration = Duration::from_secs(number()?),
+            "--ramp-up" => options.ramp_up = Duration::from_secs(number()?),
+            "--room" => options.room_id = number()?,
+            "--strategy" if builtin(&value, 0).is_some() => options.strategy = value.clone(),
+            "--strategy" => {
+                return Err(format!("Unknown strategy `{}`, the strategies are {}", value, BUILTIN_STRATEGIES.join(", ")));
+            }
+            other => return Err(format!("Unknown option `{}`", other)),
+        }
+    }
+    Ok(options)
+}
+
+/// Counters shared by every client
+#[derive(Default)]
+struct Stats {
+    connected: AtomicU64,
+    failed: AtomicU64,
+    disconnected: AtomicU64,
+    sent: AtomicU64,
+    received: AtomicU64,
+    bad_messages: AtomicU64,
+    player_states: AtomicU64,
+    player_states_expected: AtomicU64,
+    probes_lost: AtomicU64,
+    deaths: AtomicU64,
+    latencies: Mutex<Vec<Duration>>,
+}
+
+impl Stats {
+    fn count(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+struct LoadClient {
+    index: u64,
+    socket: WebSocket<MaybeTlsStream<TcpStream>>,
+    stats: Arc<Stats>,
+    strategy: Box<dyn Strategy>,
+    rng: StdRng,
+    room_id: u64,
+    player_id: Option<u64>,
+    in_room_since: Option<Instant>,
+    /// None while dead
+    me: Option<PlayerStateData>,
+    died_at: Option<Instant>,
+    others: Vec<PlayerStateData>,
+    map: MapState,
+    pending_growth: u64,
+    tick: u64,
+    started_at: Instant,
+    /// When the unanswered ping was sent, in milliseconds since `started_at`
+    probe_sent_at: Option<f64>,
+}
+
+impl LoadClient {
+    fn send<T: Serialize>(&mut self, message_type: &str, payload: &T) -> Result<(), String> {
+        let player_id = self.player_id.unwrap_or(0);
+        let message = ServerMessage::new(message_type, player_id, payload).map_err(|e| e.to_string())?;
+        let text = serde_json::to_string(&message).map_err(|e| e.to_string())?;
+        self.socket.send(Message::text(text)).map_err(|e| e.to_string())?;
+        Stats::count(&self.stats.sent);
+        Ok(())
+    }
+
+    fn handle_message(&mut self, text: &str) -> Result<(), String> {
+        Stats::count(&self.stats.received);
+        let Ok(server_message) = serde_json::from_str::<ServerMessage>(text) else {
+            Stats::count(&self.stats.bad_messages);
+            return Ok(());
+        };
+        match server_message.message_type.as_str() {
+            "assign_id" => {
+                self.player_id = Some(server_message.player_id);
+                let profile = PlayerProfile {
+                    name: format!("Load {}", self.index),
+                    colour: BOT_COLOURS[self.index as usize % BOT_COLOURS.len()].to_string(),
+                };
+                self.send("join", &profile)?;
+            }
+            "join_accepted" => {
+                let request = JoinRoomRequest {
+                    room_id: Some(self.room_id),
+                    code: None,
+                };
+                self.send("join_room", &request)?;
+            }
+            "join_rejected" | "room_error" => {
+                return Err(server_message.message);
+            }
+            "room_joined" => {
+                self.in_room_since = Some(Instant::now());
+                self.spawn();
+            }
+            "map_state" => match serde_json::from_str::<MapState>(&server_message.message) {
+                Ok(map_state) => self.map = map_state,
+                Err(_) => Stats::count(&self.stats.bad_messages),
+            },
+            "player_states" => match serde_json::from_str::<Vec<PlayerStateData>>(&server_message.message) {
+                Ok(player_states) => {
+                    Stats::count(&self.stats.player_states);
+                    self.others = player_states;
+                }
+                Err(_) => Stats::count(&self.stats.bad_messages),
+            },
+            // Carry on from the state the server kept, like the client does
+            "player_state_rejected" if self.me.is_some() => {
+                match serde_json::from_str::<PlayerStateData>(&server_message.message) {
+                    Ok(player_state) if player_state.head().is_some() => {
+                        self.me = Some(player_state);
+                        self.pending_growth = 0;
+                        self.send("player_state_resynced", &())?;
+                    }
+                    Ok(_) => {}
+                    Err(_) => Stats::count(&self.stats.bad_messages),
+                }
+            }
+            "player_died" if Some(server_message.player_id) == self.player_id && self.me.is_some() => {
+                self.die();
+            }
+            "pong" => match serde_json::from_str::<Pong>(&server_message.message) {
+                // A pong for a ping already counted as lost is not a sample
+                Ok(pong) if self.probe_sent_at == Some(pong.client_time) => {
+                    self.probe_sent_at = None;
+                    let round_trip = Duration::from_secs_f64((self.clock_ms() - pong.client_time).max(0.0) / 1000.0);
+                    self.stats.latencies.lock().expect("latencies lock").push(round_trip);
+                }
+                Ok(_) => {}
+                Err(_) => Stats::count(&self.stats.bad_messages),
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn die(&mut self) {
+        self.me = None;
+        self.died_at = Some(Instant::now());
+        Stats::count(&self.stats.deaths);
+    }
+
+    /// Put a fresh snake on a random free cell heading a random way. The
+    /// client looks for a cell with room around it instead, so these snakes
+    /// die young more often. The server moves them if the cell is in the
+    /// path of another snake.
+    fn spawn(&mut self) {
+        let Some(player_id) = self.player_id else {
+            return;
+        };
+        let occupied = |x: HNum, y: HNum| {
+            self.others.iter().flat_map(|other| other.body_segments.iter()).any(|segment| segment.x == x && segment.y == y)
+                || self.map.food_positions.iter().any(|food| food.x == x && food.y == y)
+        };
+        let cells: Vec<(HNum, HNum)> = self.map.layout.food_cells().into_iter()
+            .filter(|(x, y)| !occupied(*x, *y))
+            .collect();
+        let Some(&(x, y)) = cells.choose(&mut self.rng) else {
+            return;
+        };
+        let direction = *Direction::ALL.choose(&mut self.rng).expect("there are four directions");
+        self.me = Some(PlayerStateData {
+            player_id,
+            body_segments: vec![BodySegment { x, y, direction }],
+            invulnerable: true,
+            kills: 0,
+            score: 0,
+            name: String::new(),
+            colour: String::new(),
+            team: None,
+            effects: vec![],
+            ticks_per_move: NORMAL_TICKS_PER_MOVE,
+            boosting: false,
+        });
+        self.pending_growth = 0;
+        self.died_at = None;
+    }
+
+    /// One move of the snake, reported to the server like the client does
+    fn play_move(&mut self) -> Result<(), String> {
+        if self.in_room_since.is_none() {
+            return Ok(());
+        }
+        let Some(mut me) = self.me.take() else {
+            if self.died_at.is_some_and(|died_at| died_at.elapsed() >= RESPAWN_DELAY) {
+                self.spawn();
+                if let Some(me) = self.me.clone() {
+                    self.send("player_respawned", &me)?;
+                }
+            }
+            return Ok(());
+        };
+        let view = GameView {
+            tick: self.tick,
+            me: &me,
+            others: self.others.iter().collect(),
+            map: &self.map,
+        };
+        let direction = self.strategy.next_direction(&view);
+        let grow = self.pending_growth > 0;
+        if grow {
+            self.pending_growth -= 1;
+        }
+        me.advance(&self.map.layout, direction, grow);
+        self.tick += 1;
+
+        // Clients only report running into themselves, the server decides the rest
+        if me.hit_itself() {
+            self.send("player_died", &"")?;
+            self.die();
+            return Ok(());
+        }
+        // The server checks food claims against the head it was last sent,
+        // so the move goes out first like in the client
+        self.send("player_state", &me)?;
+        let head = *me.head().expect("a snake that just moved has a head");
+        if let Some(index) = self.map.food_positions.iter().position(|food| food.x == head.x && food.y == head.y) {
+            let food = self.map.food_positions.remove(index);
+            // Poison takes away the growth still to come, the server stops allowing it
+            self.pending_growth = if food.growth >= 0 { self.pending_growth + food.growth as u64 } else { 0 };
+            me.score = me.score.saturating_add_signed(food.score);
+            self.send("eat_food", &serde_json::json!({ "x": head.x, "y": head.y }))?;
+        }
+        self.me = Some(me);
+        Ok(())
+    }
+
+    /// Milliseconds since the client started, the clock pings are sent with
+    fn clock_ms(&self) -> f64 {
+        self.started_at.elapsed().as_secs_f64() * 1000.0
+    }
+
+    /// Ping the server and time how long the pong takes, like the client
+    /// does to keep its clock in step
+    fn probe(&mut self) -> Result<(), String> {
+        if self.player_id.is_none() {
+            return Ok(());
+        }
+        if self.probe_sent_at.is_some() {
+            Stats::count(&self.stats.probes_lost);
+        }
+        let sent_at = self.clock_ms();
+        self.probe_sent_at = Some(sent_at);
+        self.send("ping", &sent_at)
+    }
+
+    fn run(&mut self, deadline: Instant) -> Result<(), String> {
+        let move_interval = TICK_TIME * NORMAL_TICKS_PER_MOVE as u32;
+        let mut next_move = Instant::now() + move_interval;
+        let mut next_probe = Instant::now() + PROBE_INTERVAL;
+        while Instant::now() < deadline {
+            match self.socket.read() {
+                Ok(Message::Text(text)) => self.handle_message(text.as_str())?,
+                Ok(Message::Close(_)) => return Err("The server closed the connection".to_string()),
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(e)) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(e) => return Err(e.to_string()),
+            }
+            let now = Instant::now();
+            if now >= next_move {
+                self.play_move()?;
+                next_move += move_interval;
+            }
+            if now >= next_probe {
+                self.probe()?;
+                next_probe += PROBE_INTERVAL;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn run_client(index: u64, options: Arc<Options>, stats: Arc<Stats>, deadline: Instant) {
+    let socket = match tungstenite::connect(options.url.as_str()) {
+        Ok((socket, _)) => socket,
+        Err(e) => {
+            Stats::count(&stats.failed);
+            eprintln!("Client {} failed to connect: {}", index, e);
+            return;
+        }
+    };
+    if let MaybeTlsStream::Plain(stream) = socket.get_ref() {
+        if let Err(e) = stream.set_read_timeout(Some(READ_TIMEOUT)) {
+            eprintln!("Client {} can't set a read timeout: {}", index, e);
+        }
+    }
+    Stats::count(&stats.connected);
+
+    let mut client = LoadClient {
+        index,
+        socket,
+        stats: stats.clone(),
+        strategy: builtin(&options.strategy, index).expect("the strategy is checked when parsing"),
+        rng: StdRng::seed_from_u64(index),
+        room_id: options.room_id,
+        player_id: None,
+        in_room_since: None,
+        me: None,
+        died_at: None,
+        others: vec![],
+        map: MapState::default(),
+        pending_growth: 0,
+        tick: 0,
+        started_at: Instant::now(),
+        probe_sent_at: None,
+    };
+    if let Err(e) = client.run(deadline) {
+        Stats::count(&stats.disconnected);
+        eprintln!("Client {} disconnected: {}", index, e);
+    }
+    if let Some(in_room_since) = client.in_room_since {
+        let expected = in_room_since.elapsed().as_millis() / NETWORK_TICK_TIME.as_millis();
+        stats.player_states_expected.fetch_add(expected as u64, Ordering::Relaxed);
+    }
+    let _ = client.socket.close(None);
+    let _ = client.socket.flush();
+}
+
+/// The latency below which `fraction` of the samples fall
+fn percentile(sorted: &[Duration], fraction: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    sorted[((sorted.len() - 1) as f64 * fraction).round() as usize]
+}
+
+fn main() -> ExitCode {
+    let options = match parse_options() {
+        Ok(options) => Arc::new(options),
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let stats = Arc::new(Stats::default());
+    let start = Instant::now();
+    let deadline = start + options.ramp_up + options.duration;
+    println!(
+        "{} clients on {}, ramping up over {}s then running for {}s",
+        options.clients,
+        options.url,
+        options.ramp_up.as_secs(),
+        options.duration.as_secs()
+    );
+
+    let reporter_stats = stats.clone();
+    let reporter = thread::spawn(move || {
+        let (mut last_sent, mut last_received) = (0, 0);
+        while Instant::now() + REPORT_INTERVAL <= deadline {
+            thread::sleep(REPORT_INTERVAL);
+            let sent = reporter_stats.sent.load(Ordering::Relaxed);
+            let received = reporter_stats.received.load(Ordering::Relaxed);
+            let seconds = REPORT_INTERVAL.as_secs_f64();
+            println!(
+                "{:>4}s  {:>5} connected  {:>8.0} sent/s  {:>8.0} received/s",
+                start.elapsed().as_secs(),
+                reporter_stats.connected.load(Ordering::Relaxed) - reporter_stats.disconnected.load(Ordering::Relaxed),
+                (sent - last_sent) as f64 / seconds,
+                (received - last_received) as f64 / seconds,
+            );
+            (last_sent, last_received) = (sent, received);
+        }
+    });
+
+    let launch_gap = options.ramp_up.checked_div(options.clients as u32).unwrap_or_default();
+    let clients: Vec<_> = (0..options.clients)
+        .map(|index| {
+            let (options, stats) = (options.clone(), stats.clone());
+            let client = thread::spawn(move || run_client(index, options, stats, deadline));
+            thread::sleep(launch_gap);
+            client
+        })
+        .collect();
+    for client in clients {
+        let _ = client.join();
+    }
+    let _ = reporter.join();
+
+    let elapsed = start.elapsed().as_secs_f64();
+    let mut latencies = stats.latencies.lock().expect("latencies lock").clone();
+    latencies.sort();
+    let player_states = stats.player_states.load(Ordering::Relaxed);
+    let expected = stats.player_states_expected.load(Ordering::Relaxed);
+    let dropped = expected.saturating_sub(player_states);
+    println!();
+    println!(
+        "Connections:     {} made, {} failed, {} dropped early",
+        stats.connected.load(Ordering::Relaxed),
+        stats.failed.load(Ordering::Relaxed),
+        stats.disconnected.load(Ordering::Relaxed)
+    );
+    println!(
+        "Messages:        {:.0} sent/s, {:.0} received/s, {} unreadable",
+        stats.sent.load(Ordering::Relaxed) as f64 / elapsed,
+        stats.received.load(Ordering::Relaxed) as f64 / elapsed,
+        stats.bad_messages.load(Ordering::Relaxed)
+    );
+    println!(
+        "Player states:   {} of {} expected, {} dropped ({:.1}%)",
+        player_states,
+        expected,
+        dropped,
+        dropped as f64 / expected.max(1) as f64 * 100.0
+    );
+    println!(
+        "Latency:         p50 {:?}, p90 {:?}, p99 {:?}, max {:?} over {} probes, {} unanswered",
+        percentile(&latencies, 0.5),
+        percentile(&latencies, 0.9),
+        percentile(&latencies, 0.99),
+        latencies.last().copied().unwrap_or_default(),
+        latencies.len(),
+        stats.probes_lost.load(Ordering::Relaxed)
+    );
+    println!("Deaths:          {}", stats.deaths.load(Ordering::Relaxed));
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,115 @@
+//! Pit strategies against each other in seeded headless games and print how
+//! often each one wins.
+//!
+//! ```text
+//! cargo run --release --bin tournament -- [--games N] [--seed S] [--map FILE] [STRATEGY...]
+//! ```
+//!
+//! Every game seats each strategy once, in an order shuffled by the game's
+//! seed. Without any strategies named all the built-in ones play.
+
+use std::process::ExitCode;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use craby_snake_bots::game::Game;
+use craby_snake_bots::map_file::{parse_map, MapLayout};
+use craby_snake_bots::strategies::{builtin, BUILTIN_STRATEGIES};
+
+const DEFAULT_GAMES: u64 = 100;
+
+struct Options {
+    games: u64,
+    seed: u64,
+    layout: MapLayout,
+    strategies: Vec<String>,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        games: DEFAULT_GAMES,
+        seed: 0,
+        layout: MapLayout::default(),
+        strategies: vec![],
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+        match arg.as_str() {
+            "--games" => {
+                options.games = value("--games")?.parse().map_err(|_| "--games needs a number".to_string())?;
+            }
+            "--seed" => {
+                options.seed = value("--seed")?.parse().map_err(|_| "--seed needs a number".to_string())?;
+            }
+            "--map" => {
+                let path = value("--map")?;
+                let text = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path, e))?;
+                let map_file = parse_map(&text).map_err(|e| match e.line {
+                    0 => format!("{}: {}", path, e.message),
+                    line => format!("{}:{}:{}: {}", path, line, e.column, e.message),
+                })?;
+                options.layout = map_file.layout;
+            }
+            name if builtin(name, 0).is_some() => options.strategies.push(name.to_string()),
+            other => {
+                return Err(format!("Unknown strategy or option `{}`, the strategies are {}", other, BUILTIN_STRATEGIES.join(", ")));
+            }
+        }
+    }
+    if options.strategies.is_empty() {
+        options.strategies = BUILTIN_STRATEGIES.iter().map(|name| name.to_string()).collect();
+    }
+    Ok(options)
+}
+
+fn main() -> ExitCode {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let entrants = options.strategies.len();
+    let mut wins = vec![0u64; entrants];
+    let mut total_scores = vec![0u64; entrants];
+    let mut draws = 0;
+    let mut total_ticks = 0;
+    for game_number in 0..options.games {
+        let seed = options.seed.wrapping_add(game_number);
+        let mut seating: Vec<usize> = (0..entrants).collect();
+        seating.shuffle(&mut StdRng::seed_from_u64(seed));
+        let mut strategies: Vec<_> = seating.iter()
+            .enumerate()
+            .map(|(seat, entrant)| builtin(&options.strategies[*entrant], seed.wrapping_mul(31).wrapping_add(seat as u64)).expect("strategies are checked when parsing"))
+            .collect();
+
+        let result = Game::new(options.layout.clone(), entrants, seed).play(&mut strategies);
+        total_ticks += result.ticks;
+        for (seat, score) in result.scores.iter().enumerate() {
+            total_scores[seating[seat]] += score;
+        }
+        match result.winner {
+            Some(seat) => wins[seating[seat]] += 1,
+            None => draws += 1,
+        }
+    }
+
+    let games = options.games.max(1) as f64;
+    println!("{} games on {}, seeds {} to {}", options.games, options.layout.name, options.seed, options.seed.wrapping_add(options.games.saturating_sub(1)));
+    println!("{:<12} {:>6} {:>9} {:>10}", "Strategy", "Wins", "Win rate", "Avg score");
+    for (entrant, name) in options.strategies.iter().enumerate() {
+        println!(
+            "{:<12} {:>6} {:>8.1}% {:>10.1}",
+            name,
+            wins[entrant],
+            wins[entrant] as f64 / games * 100.0,
+            total_scores[entrant] as f64 / games,
+        );
+    }
+    println!("{:<12} {:>6} {:>8.1}%", "Draws", draws, draws as f64 / games * 100.0);
+    println!("Average game length: {:.0} moves", total_ticks as f64 / games);
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,342 @@
+//! A game played entirely in memory with the rules of a room on the server:
+//! every snake moves at once, walls and bodies are deadly and two heads that
+//! meet both die. Games with the same seed and strategies play out the same.
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use crate::map_file::MapLayout;
+use crate::state::{BodySegment, Direction, Food, HNum, MapState, PlayerStateData, BOUNDARY_THICKNESS, NORMAL_TICKS_PER_MOVE};
+use crate::strategy::{GameView, Strategy};
+
+/// Games still going after this many moves are won by the longest snake
+pub const MAX_TICKS: u64 = 5000;
+// Food kept on the grid, like foodSpawn on the server
+const FOOD_COUNT: usize = 3;
+// Random spawns keep this many cells clear of walls and each other
+const SPAWN_MARGIN: HNum = 3;
+const SPAWN_ATTEMPTS: usize = 100;
+
+pub struct GameResult {
+    /// Seat of the last snake standing, or of the longest one when time ran
+    /// out. None for a draw.
+    pub winner: Option<usize>,
+    pub ticks: u64,
+    pub scores: Vec<u64>,
+}
+
+pub struct Game {
+    map: MapState,
+    players: Vec<PlayerStateData>,
+    alive: Vec<bool>,
+    pending_growth: Vec<u64>,
+    rng: StdRng,
+    tick: u64,
+}
+
+impl Game {
+    /// A game on `layout` for `player_count` snakes, seated in the order the
+    /// strategies will be passed to [`Game::step`]. Maps read from files
+    /// should have passed [`crate::map_file::parse_map`] first.
+    pub fn new(layout: MapLayout, player_count: usize, seed: u64) -> Self {
+        let mut game = Self {
+            map: MapState {
+                layout,
+                ..MapState::default()
+            },
+            players: vec![],
+            alive: vec![true; player_count],
+            pending_growth: vec![0; player_count],
+            rng: StdRng::seed_from_u64(seed),
+            tick: 0,
+        };
+        let spawns = game.spawn_positions(player_count);
+        game.players = spawns.into_iter().enumerate()
+            .map(|(seat, (x, y, direction))| PlayerStateData {
+                player_id: seat as u64,
+                body_segments: vec![BodySegment { x, y, direction }],
+                invulnerable: false,
+                kills: 0,
+                score: 0,
+                name: String::new(),
+                colour: String::new(),
+                team: None,
+                effects: vec![],
+                ticks_per_move: NORMAL_TICKS_PER_MOVE,
+                boosting: false,
+            })
+            .collect();
+        game.add_food();
+        game
+    }
+
+    /// The map's spawn points first, then random cells away from walls and
+    /// the other spawns, facing the middle of the map. A map without any
+    /// floor leaves nowhere but its corner, where the snakes die on their
+    /// first move.
+    fn spawn_positions(&mut self, count: usize) -> Vec<(HNum, HNum, Direction)> {
+        let layout = &self.map.layout;
+        let mut spawns: Vec<(HNum, HNum, Direction)> = layout.spawn_points.iter()
+            .map(|spawn| (spawn.x + BOUNDARY_THICKNESS, spawn.y + BOUNDARY_THICKNESS, spawn.direction))
+            .collect();
+        spawns.shuffle(&mut self.rng);
+        spawns.truncate(count);
+
+        let mut cells = layout.floor_cells();
+        if cells.is_empty() {
+            cells.push((BOUNDARY_THICKNESS, BOUNDARY_THICKNESS));
+        }
+        let near_wall = |x: HNum, y: HNum| {
+            (-SPAWN_MARGIN..=SPAWN_MARGIN).any(|dy| (-SPAWN_MARGIN..=SPAWN_MARGIN).any(|dx| layout.is_deadly(x + dx, y + dy)))
+        };
+        while spawns.len() < count {
+            let mut cell = cells[self.rng.gen_range(0..cells.len())];
+            for _ in 0..SPAWN_ATTEMPTS {
+                let crowded = spawns.iter()
+                    .any(|(x, y, _)| (x - cell.0).abs() + (y - cell.1).abs() < SPAWN_MARGIN * 2);
+                if !crowded && !near_wall(cell.0, cell.1) {
+                    break;
+                }
+                cell = cells[self.rng.gen_range(0..cells.len())];
+            }
+            let (x, y) = cell;
+            let centre_x = layout.grid_width() / 2;
+            let centre_y = layout.grid_height() / 2;
+            let direction = if (centre_x - x).abs() > (centre_y - y).abs() {
+                if x < centre_x { Direction::RIGHT } else { Direction::LEFT }
+            } else if y < centre_y {
+                Direction::DOWN
+            } else {
+                Direction::UP
+            };
+            spawns.push((x, y, direction));
+        }
+        spawns
+    }
+
+    fn is_occupied(&self, x: HNum, y: HNum) -> bool {
+        self.players.iter().any(|player| player.body_segments.iter().any(|segment| segment.x == x && segment.y == y))
+            || self.map.food_positions.iter().any(|food| food.x == x && food.y == y)
+    }
+
+    fn add_food(&mut self) {
+        let missing = FOOD_COUNT.saturating_sub(self.map.food_positions.len());
+        let mut cells: Vec<(HNum, HNum)> = self.map.layout.food_cells().into_iter()
+            .filter(|(x, y)| !self.is_occupied(*x, *y))
+            .collect();
+        cells.shuffle(&mut self.rng);
+        self.map.food_positions.extend(cells.into_iter().take(missing).map(|(x, y)| Food::normal(x, y)));
+    }
+
+    pub fn is_over(&self) -> bool {
+        let alive = self.alive.iter().filter(|alive| **alive).count();
+        alive == 0 || (self.players.len() > 1 && alive == 1) || self.tick >= MAX_TICKS
+    }
+
+    /// Ask every snake still alive for its move and play them all at once
+    pub fn step(&mut self, strategies: &mut [Box<dyn Strategy>]) {
+        let directions: Vec<Option<Direction>> = (0..self.players.len())
+            .map(|seat| {
+                if !self.alive[seat] {
+                    return None;
+                }
+                let view = GameView {
+                    tick: self.tick,
+                    me: &self.players[seat],
+                    others: (0..self.players.len())
+                        .filter(|other| *other != seat && self.alive[*other])
+                        .map(|other| &self.players[other])
+                        .collect(),
+                    map: &self.map,
+                };
+                Some(strategies[seat].next_direction(&view))
+            })
+            .collect();
+
+        let previous_heads: Vec<Option<(HNum, HNum)>> = self.players.iter()
+            .map(|player| player.head().map(|head| (head.x, head.y)))
+            .collect();
+        for (seat, direction) in directions.into_iter().enumerate() {
+            let Some(direction) = direction else {
+                continue;
+            };
+            let grow = self.pending_growth[seat] > 0;
+            if grow {
+                self.pending_growth[seat] -= 1;
+            }
+            self.players[seat].advance(&self.map.layout, direction, grow);
+        }
+
+        let dead: Vec<usize> = (0..self.players.len())
+            .filter(|seat| self.alive[*seat])
+            .filter(|seat| {
+                let head = self.players[*seat].head().expect("living snakes have a head");
+                let hit_snake = self.players.iter().enumerate().any(|(other, player)| {
+                    let segments = &player.body_segments;
+                    // A snake's own head is the one cell it may share with itself
+                    let body = if other == *seat { &segments[..segments.len() - 1] } else { &segments[..] };
+                    body.iter().any(|segment| segment.x == head.x && segment.y == head.y)
+                });
+                // Two snakes swapping cells pass through each other's heads,
+                // which counts as heads meeting like on the server
+                let swapped = (0..self.players.len()).any(|other| {
+                    other != *seat
+                        && self.alive[other]
+                        && previous_heads[other] == Some((head.x, head.y))
+                        && previous_heads[*seat] == self.players[other].head().map(|head| (head.x, head.y))
+                });
+                hit_snake || swapped || self.map.layout.is_deadly(head.x, head.y)
+            })
+            .collect();
+        for seat in dead {
+            self.alive[seat] = false;
+            self.players[seat].body_segments.clear();
+        }
+
+        for seat in 0..self.players.len() {
+            let Some(head) = self.players[seat].head().copied() else {
+                continue;
+            };
+            if let Some(index) = self.map.food_positions.iter().position(|food| food.x == head.x && food.y == head.y) {
+                let food = self.map.food_positions.remove(index);
+                self.pending_growth[seat] += food.growth.max(0) as u64;
+                self.players[seat].score = self.players[seat].score.saturating_add_signed(food.score);
+            }
+        }
+        self.add_food();
+        self.tick += 1;
+    }
+
+    /// Play until one snake is left or time runs out
+    pub fn play(mut self, strategies: &mut [Box<dyn Strategy>]) -> GameResult {
+        assert_eq!(strategies.len(), self.players.len(), "one strategy per seat");
+        while !self.is_over() {
+            self.step(strategies);
+        }
+        let survivors: Vec<usize> = (0..self.players.len()).filter(|seat| self.alive[*seat]).collect();
+        let longest = survivors.iter().map(|seat| self.players[*seat].body_segments.len()).max().unwrap_or(0);
+        let longest_survivors: Vec<usize> = survivors.into_iter()
+            .filter(|seat| self.players[*seat].body_segments.len() == longest)
+            .collect();
+        GameResult {
+            winner: if longest_survivors.len() == 1 { Some(longest_survivors[0]) } else { None },
+            ticks: self.tick,
+            scores: self.players.iter().map(|player| player.score).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_file::{EdgeBehaviour, Rect};
+    use crate::strategies::builtin;
+
+    /// Always asks for the same direction
+    struct Steer(Direction);
+
+    impl Strategy for Steer {
+        fn name(&self) -> &str {
+            "steer"
+        }
+        fn next_direction(&mut self, _view: &GameView) -> Direction {
+            self.0
+        }
+    }
+
+    /// A game on the classic map with the snakes put exactly where the test
+    /// wants them, given neck first and head last like on the wire
+    fn game_with(snakes: &[(&[(HNum, HNum)], Direction)]) -> Game {
+        let mut game = Game::new(MapLayout::default(), snakes.len(), 0);
+        for (player, (cells, direction)) in game.players.iter_mut().zip(snakes) {
+            player.body_segments = cells.iter().map(|(x, y)| BodySegment { x: *x, y: *y, direction: *direction }).collect();
+        }
+        game.map.food_positions.clear();
+        game
+    }
+
+    fn steer(directions: &[Direction]) -> Vec<Box<dyn Strategy>> {
+        directions.iter().map(|direction| Box::new(Steer(*direction)) as Box<dyn Strategy>).collect()
+    }
+
+    #[test]
+    fn heads_that_meet_both_die() {
+        let mut game = game_with(&[(&[(5, 5)], Direction::RIGHT), (&[(7, 5)], Direction::LEFT)]);
+        game.step(&mut steer(&[Direction::RIGHT, Direction::LEFT]));
+        assert_eq!(game.alive, vec![false, false]);
+        assert!(game.is_over());
+    }
+
+    #[test]
+    fn heads_that_swap_cells_both_die() {
+        let mut game = game_with(&[(&[(5, 5)], Direction::RIGHT), (&[(6, 5)], Direction::LEFT)]);
+        game.step(&mut steer(&[Direction::RIGHT, Direction::LEFT]));
+        assert_eq!(game.alive, vec![false, false]);
+    }
+
+    #[test]
+    fn running_into_a_body_kills_only_the_runner() {
+        let upright = [(6, 4), (6, 5), (6, 6), (6, 3)];
+        let mut game = game_with(&[(&[(5, 5)], Direction::RIGHT), (&upright, Direction::UP)]);
+        game.step(&mut steer(&[Direction::RIGHT, Direction::UP]));
+        assert_eq!(game.alive, vec![false, true]);
+        assert!(game.players[0].body_segments.is_empty());
+        assert_eq!(game.players[1].head().map(|head| (head.x, head.y)), Some((6, 2)));
+    }
+
+    #[test]
+    fn snakes_may_follow_their_own_tail_but_not_cross_their_body() {
+        // A square of four, the head moves onto the cell the tail leaves
+        let square = [(5, 6), (6, 6), (6, 5), (5, 5)];
+        let mut game = game_with(&[(&square, Direction::UP)]);
+        game.step(&mut steer(&[Direction::RIGHT]));
+        assert_eq!(game.alive, vec![true]);
+
+        // One longer and the cell is still taken when the head gets there
+        let hook = [(5, 6), (6, 6), (6, 5), (6, 4), (5, 5)];
+        let mut game = game_with(&[(&hook, Direction::UP)]);
+        game.step(&mut steer(&[Direction::RIGHT]));
+        assert_eq!(game.alive, vec![false]);
+    }
+
+    #[test]
+    fn walls_and_solid_edges_are_deadly() {
+        let layout = MapLayout {
+            edge: EdgeBehaviour::Solid,
+            walls: vec![Rect { x: 5, y: 0, width: 1, height: 1 }],
+            ..MapLayout::default()
+        };
+        let mut game = Game::new(layout, 2, 0);
+        game.players[0].body_segments = vec![BodySegment { x: 5, y: 1, direction: Direction::RIGHT }];
+        game.players[1].body_segments = vec![BodySegment { x: 10, y: 1, direction: Direction::UP }];
+        game.map.food_positions.clear();
+        game.step(&mut steer(&[Direction::RIGHT, Direction::UP]));
+        assert_eq!(game.alive, vec![false, false]);
+    }
+
+    /// Every snake and every piece of food after one move
+    type Snapshot = (Vec<Vec<BodySegment>>, Vec<(HNum, HNum)>);
+
+    fn replay(seed: u64) -> Vec<Snapshot> {
+        let mut strategies: Vec<_> = ["random", "greedy", "cautious"].iter()
+            .enumerate()
+            .map(|(seat, name)| builtin(name, seed + seat as u64).unwrap())
+            .collect();
+        let mut game = Game::new(MapLayout::default(), strategies.len(), seed);
+        let mut moves = vec![];
+        while !game.is_over() && game.tick < 500 {
+            game.step(&mut strategies);
+            moves.push((
+                game.players.iter().map(|player| player.body_segments.clone()).collect(),
+                game.map.food_positions.iter().map(|food| (food.x, food.y)).collect(),
+            ));
+        }
+        moves
+    }
+
+    #[test]
+    fn games_with_the_same_seed_play_out_the_same() {
+        assert_eq!(replay(7), replay(7));
+        assert_ne!(replay(7), replay(8));
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::VecDeque;
+use crate::map_file::{EdgeBehaviour, MapLayout};
+use crate::state::{BodySegment, Direction, HNum};
+use crate::strategy::GameView;
+
+/// Which cells of the grid a snake can't move onto, boundary included.
+/// Helpers for strategies that search the grid.
+pub struct Occupancy<'a> {
+    pub layout: &'a MapLayout,
+    width: usize,
+    blocked: Vec<bool>,
+}
+
+impl<'a> Occupancy<'a> {
+    /// Walls, solid edges, cells outside the zone and every snake body are
+    /// blocked. Tails are left open since they move on with the snake.
+    pub fn new(view: &GameView<'a>) -> Self {
+        let layout = &view.map.layout;
+        let width = layout.grid_width() as usize;
+        let height = layout.grid_height() as usize;
+        let mut occupancy = Self {
+            layout,
+            width,
+            blocked: vec![false; width * height],
+        };
+        for y in 0..layout.grid_height() {
+            for x in 0..layout.grid_width() {
+                let solid_edge = layout.edge == EdgeBehaviour::Solid && layout.is_boundary(x, y);
+                let outside_zone = view.map.zone.is_some_and(|zone| !zone.contains(x, y));
+                if solid_edge || outside_zone || layout.is_wall(x, y) {
+                    occupancy.block(x, y);
+                }
+            }
+        }
+        for player in std::iter::once(view.me).chain(view.others.iter().copied()) {
+            let segments = &player.body_segments;
+            // The tail is the last body segment, right before the head
+            let tail = segments.len().checked_sub(2);
+            for (i, segment) in segments.iter().enumerate() {
+                if Some(i) != tail {
+                    occupancy.block(segment.x, segment.y);
+                }
+            }
+        }
+        occupancy
+    }
+
+    fn index(&self, x: HNum, y: HNum) -> Option<usize> {
+        let inside = x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.blocked.len() / self.width;
+        inside.then(|| y as usize * self.width + x as usize)
+    }
+
+    fn block(&mut self, x: HNum, y: HNum) {
+        if let Some(index) = self.index(x, y) {
+            self.blocked[index] = true;
+        }
+    }
+
+    pub fn is_blocked(&self, x: HNum, y: HNum) -> bool {
+        self.index(x, y).is_none_or(|index| self.blocked[index])
+    }
+
+    /// Moves from the head onto an open cell, never back into the neck
+    pub fn open_moves(&self, body: &[BodySegment]) -> Vec<Direction> {
+        let Some(head) = body.last() else {
+            return vec![];
+        };
+        Direction::ALL.into_iter()
+            .filter(|direction| body.len() == 1 || *direction != head.direction.opposite())
+            .filter(|direction| {
+                let (x, y) = self.layout.step(head.x, head.y, *direction);
+                !self.is_blocked(x, y)
+            })
+            .collect()
+    }
+
+    /// Open cells reachable from a cell, counting stops once `limit` is reached
+    pub fn reachable_cells(&self, x: HNum, y: HNum, limit: usize) -> usize {
+        let mut seen = vec![false; self.blocked.len()];
+        let mut queue = VecDeque::from([(x, y)]);
+        if let Some(index) = self.index(x, y) {
+            seen[index] = true;
+        }
+        let mut count = 0;
+        while let Some((x, y)) = queue.pop_front() {
+            if count >= limit {
+                break;
+            }
+            count += 1;
+            for direction in Direction::ALL {
+                let (next_x, next_y) = self.layout.step(x, y, direction);
+                if let Some(index) = self.index(next_x, next_y) {
+                    if !seen[index] && !self.blocked[index] {
+                        seen[index] = true;
+                        queue.push_back((next_x, next_y));
+                    }
+                }
+            }
+        }
+        count
+    }
+
+    /// Breadth first search from `(x, y)` that only sets off in one of
+    /// `moves`. Returns the distance to every reachable cell, indexed like
+    /// the grid, along with the first move on the way there.
+    pub fn distances(&self, x: HNum, y: HNum, moves: &[Direction]) -> Vec<Option<(u32, Direction)>> {
+        let mut distances = vec![None; self.blocked.len()];
+        let mut queue = VecDeque::new();
+        for direction in moves {
+            let (next_x, next_y) = self.layout.step(x, y, *direction);
+            if let Some(index) = self.index(next_x, next_y) {
+                if distances[index].is_none() {
+                    distances[index] = Some((1, *direction));
+                    queue.push_back((next_x, next_y));
+                }
+            }
+        }
+        while let Some((x, y)) = queue.pop_front() {
+            let Some((distance, first_move)) = self.index(x, y).and_then(|index| distances[index]) else {
+                continue;
+            };
+            for direction in Direction::ALL {
+                let (next_x, next_y) = self.layout.step(x, y, direction);
+                if let Some(index) = self.index(next_x, next_y) {
+                    if distances[index].is_none() && !self.blocked[index] {
+                        distances[index] = Some((distance + 1, first_move));
+                        queue.push_back((next_x, next_y));
+                    }
+                }
+            }
+        }
+        distances
+    }
+
+    /// Look up a cell in the result of [`Occupancy::distances`]
+    pub fn distance_to(&self, distances: &[Option<(u32, Direction)>], x: HNum, y: HNum) -> Option<(u32, Direction)> {
+        self.index(x, y).and_then(|index| distances[index])
+    }
+}
@@ -0,0 +1,14 @@
+//! Bots that play without a browser: the [`strategy::Strategy`] trait custom
+//! bots implement, a headless [`game::Game`] to pit them against each other
+//! and a few built-in strategies to measure them against. The same
+//! strategies can also play on a real server through [`protocol`].
+//!
+//! The game state, messages and maps come from the crate the client uses
+//! too and are re-exported here.
+
+pub mod game;
+pub mod grid;
+pub mod strategies;
+pub mod strategy;
+
+pub use craby_snake_shared::{map_file, protocol, state};
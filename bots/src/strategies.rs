@@ -0,0 +1,100 @@
+//! Strategies that come with the crate, to test new ones against
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use crate::grid::Occupancy;
+use crate::state::Direction;
+use crate::strategy::{GameView, Strategy};
+
+/// Names accepted by [`builtin`]
+pub const BUILTIN_STRATEGIES: [&str; 3] = ["random", "greedy", "cautious"];
+
+/// A built-in strategy by name, seeded so that games can be replayed
+pub fn builtin(name: &str, seed: u64) -> Option<Box<dyn Strategy>> {
+    match name {
+        "random" => Some(Box::new(RandomStrategy::new(seed))),
+        "greedy" => Some(Box::new(GreedyStrategy { cautious: false })),
+        "cautious" => Some(Box::new(GreedyStrategy { cautious: true })),
+        _ => None,
+    }
+}
+
+/// Wanders into any open cell
+pub struct RandomStrategy {
+    rng: StdRng,
+}
+
+impl RandomStrategy {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Strategy for RandomStrategy {
+    fn name(&self) -> &str {
+        "random"
+    }
+    fn next_direction(&mut self, view: &GameView) -> Direction {
+        let current = view.me.head().map_or(Direction::UP, |head| head.direction);
+        let occupancy = Occupancy::new(view);
+        occupancy.open_moves(&view.me.body_segments).choose(&mut self.rng).copied().unwrap_or(current)
+    }
+}
+
+/// Heads for the closest food along the shortest path, as long as the move
+/// leaves room for the whole body. The cautious variant also keeps out of
+/// the cells other heads can reach next.
+pub struct GreedyStrategy {
+    cautious: bool,
+}
+
+impl Strategy for GreedyStrategy {
+    fn name(&self) -> &str {
+        if self.cautious { "cautious" } else { "greedy" }
+    }
+    fn next_direction(&mut self, view: &GameView) -> Direction {
+        let Some(head) = view.me.head() else {
+            return Direction::UP;
+        };
+        let occupancy = Occupancy::new(view);
+        let layout = &view.map.layout;
+        let moves = occupancy.open_moves(&view.me.body_segments);
+        let room_needed = view.me.body_segments.len() + 2;
+        let room = |direction: &Direction| {
+            let (x, y) = layout.step(head.x, head.y, *direction);
+            occupancy.reachable_cells(x, y, room_needed)
+        };
+        let near_other_head = |direction: &Direction| {
+            let next = layout.step(head.x, head.y, *direction);
+            view.others.iter()
+                .filter_map(|other| other.head())
+                .any(|other| Direction::ALL.iter().any(|reach| layout.step(other.x, other.y, *reach) == next))
+        };
+        let safe_moves: Vec<Direction> = moves.iter()
+            .filter(|direction| room(direction) >= room_needed)
+            .filter(|direction| !(self.cautious && near_other_head(direction)))
+            .copied()
+            .collect();
+
+        let distances = occupancy.distances(head.x, head.y, &safe_moves);
+        let towards_food = view.map.food_positions.iter()
+            .filter(|food| food.growth > 0)
+            .filter_map(|food| occupancy.distance_to(&distances, food.x, food.y))
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, direction)| direction);
+        if let Some(direction) = towards_food {
+            return direction;
+        }
+        // Nothing to eat safely, keep going straight if that is safe or
+        // squeeze into the roomiest gap when nothing is
+        if safe_moves.contains(&head.direction) {
+            return head.direction;
+        }
+        safe_moves.first().copied()
+            .or_else(|| moves.iter().max_by_key(|direction| room(direction)).copied())
+            .unwrap_or(head.direction)
+    }
+}
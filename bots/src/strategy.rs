@@ -0,0 +1,23 @@
+use crate::state::{Direction, MapState, PlayerStateData};
+
+/// What a strategy gets to see of the game each tick. Everything is
+/// borrowed from the game, so a strategy can look but not change anything.
+pub struct GameView<'a> {
+    /// Moves made so far this game
+    pub tick: u64,
+    /// The strategy's own snake, head last like on the wire
+    pub me: &'a PlayerStateData,
+    /// Every other snake still alive
+    pub others: Vec<&'a PlayerStateData>,
+    pub map: &'a MapState,
+}
+
+/// A bot's brain: look at the game and pick where to go next.
+///
+/// Turning back into the neck is not a move, a strategy asking for it keeps
+/// going the way it was heading instead.
+pub trait Strategy {
+    /// Shown in tournament results
+    fn name(&self) -> &str;
+    fn next_direction(&mut self, view: &GameView) -> Direction;
+}
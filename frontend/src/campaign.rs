@@ -0,0 +1,68 @@
+use serde::Deserialize;
+use craby_snake_shared::map_file::{parse_map, MapLayout};
+use crate::solo::local_storage;
+
+const UNLOCKED_LEVELS_KEY: &str = "craby-snake.campaign.unlocked";
+
+// The campaign in the order it is played
+const LEVEL_FILES: [&str; 4] = [
+    include_str!("../levels/level_01.json"),
+    include_str!("../levels/level_02.json"),
+    include_str!("../levels/level_03.json"),
+    include_str!("../levels/level_04.json"),
+];
+
+/// A handcrafted stage, cleared by eating `food_goal` food. Level files are
+/// JSON map files with the goals added
+#[derive(Clone, Debug)]
+pub struct Level {
+    pub layout: MapLayout,
+    pub food_goal: u64,
+    pub par_time_ms: u64,
+}
+
+#[derive(Deserialize)]
+struct LevelGoals {
+    food_goal: u64,
+    par_time_ms: u64,
+}
+
+pub fn level_count() -> usize {
+    LEVEL_FILES.len()
+}
+
+pub fn load_level(index: usize) -> Option<Level> {
+    let file = LEVEL_FILES.get(index)?;
+    let level = parse_map(file).map_err(|e| e.to_string()).and_then(|map| {
+        let goals = serde_json::from_str::<LevelGoals>(file).map_err(|e| e.to_string())?;
+        Ok(Level { layout: map.layout, food_goal: goals.food_goal, par_time_ms: goals.par_time_ms })
+    });
+    match level {
+        Ok(level) => Some(level),
+        Err(e) => {
+            log::error!("Failed to load level {}: {}", index + 1, e);
+            None
+        }
+    }
+}
+
+/// The first level is always unlocked
+pub fn unlocked_levels() -> usize {
+    local_storage()
+        .and_then(|storage| storage.get_item(UNLOCKED_LEVELS_KEY).ok().flatten())
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(1)
+        .clamp(1, level_count())
+}
+
+/// Unlock the levels up to and including `index`
+pub fn unlock_level(index: usize) {
+    if index < unlocked_levels() || index >= level_count() {
+        return;
+    }
+    if let Some(storage) = local_storage() {
+        if storage.set_item(UNLOCKED_LEVELS_KEY, &(index + 1).to_string()).is_err() {
+            log::error!("Failed to save campaign progress");
+        }
+    }
+}
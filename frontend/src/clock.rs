@@ -0,0 +1,145 @@
+//! The server's clock and tick as seen from the client, estimated NTP-style
+//! from ping round trips, and the client's own tick number paced to follow it.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use craby_snake_shared::protocol::Pong;
+use crate::conditioner::now_ms;
+use crate::game_grid::TICK_TIME;
+
+// The offset is taken from the quickest of this many recent round trips,
+// the one least skewed by messages queueing on the way
+const SAMPLE_COUNT: usize = 8;
+// Pings are sent this many ticks apart, and quicker until there are
+// SAMPLE_COUNT round trips to pick from
+pub const SYNC_TICKS: u64 = 40;
+pub const FIRST_SYNC_TICKS: u64 = 4;
+// Further behind than this the client jumps to the server's tick instead of
+// catching up, most likely after the tab was in the background. It never
+// jumps back, the server drops inputs older than the newest one it took.
+const MAX_TICK_DRIFT: u64 = 40;
+
+#[derive(Clone, Copy)]
+struct Sample {
+    rtt: f64,
+    offset: f64,
+}
+
+#[derive(Default)]
+struct Clock {
+    samples: VecDeque<Sample>,
+    // The last tick a pong told of and when it started, on the server's clock
+    anchor: Option<(u64, f64)>,
+    tick: u64,
+    // Ticks of the newest snapshots taken
+    player_states_tick: u64,
+    map_state_tick: u64,
+}
+
+thread_local! {
+    static CLOCK: RefCell<Clock> = RefCell::new(Clock::default());
+}
+
+impl Clock {
+    fn offset(&self) -> Option<f64> {
+        self.samples.iter()
+            .min_by(|a, b| a.rtt.total_cmp(&b.rtt))
+            .map(|sample| sample.offset)
+    }
+    fn server_tick(&self) -> Option<u64> {
+        let (tick, tick_time) = self.anchor?;
+        let elapsed = now_ms() + self.offset()? - tick_time;
+        Some(tick + (elapsed / TICK_TIME as f64).max(0.0) as u64)
+    }
+}
+
+/// Take in the server's answer to a ping, returns the round trip time
+pub fn pong_received(pong: &Pong) -> f64 {
+    let now = now_ms();
+    let rtt = now - pong.client_time;
+    // The server answers straight away, so it read its clock halfway through the round trip
+    let offset = pong.server_time - (pong.client_time + now) / 2.0;
+    CLOCK.with(|clock| {
+        let mut clock = clock.borrow_mut();
+        if clock.samples.len() == SAMPLE_COUNT {
+            clock.samples.pop_front();
+        }
+        clock.samples.push_back(Sample { rtt, offset });
+        clock.anchor = Some((pong.tick, pong.tick_time));
+    });
+    rtt
+}
+
+/// Ticks to wait before the next ping
+pub fn sync_interval() -> u64 {
+    if CLOCK.with(|clock| clock.borrow().samples.len()) < SAMPLE_COUNT {
+        FIRST_SYNC_TICKS
+    } else {
+        SYNC_TICKS
+    }
+}
+
+/// How far the server's clock is ahead of performance.now(), in milliseconds
+pub fn offset() -> Option<f64> {
+    CLOCK.with(|clock| clock.borrow().offset())
+}
+
+/// The tick the server is on right now, by the estimated offset
+pub fn server_tick() -> Option<u64> {
+    CLOCK.with(|clock| clock.borrow().server_tick())
+}
+
+pub fn client_tick() -> u64 {
+    CLOCK.with(|clock| clock.borrow().tick)
+}
+
+/// The tick inputs are stamped with, 0 until the clock has been synced
+pub fn input_tick() -> u64 {
+    CLOCK.with(|clock| {
+        let clock = clock.borrow();
+        if clock.anchor.is_some() { clock.tick } else { 0 }
+    })
+}
+
+/// How many ticks to run for one beat of the tick timer, moving the client
+/// tick on by as many: one normally, two when falling behind the server and
+/// none when getting ahead of it, however far. Without `follow_server`, like
+/// in solo runs, the client keeps its own pace.
+pub fn ticks_due(follow_server: bool) -> u64 {
+    CLOCK.with(|clock| {
+        let mut clock = clock.borrow_mut();
+        let server_tick = if follow_server { clock.server_tick() } else { None };
+        let due = match server_tick {
+            None => 1,
+            Some(server_tick) if server_tick > clock.tick + MAX_TICK_DRIFT => {
+                clock.tick = server_tick.saturating_sub(1);
+                1
+            }
+            Some(server_tick) if server_tick > clock.tick + 2 => 2,
+            Some(server_tick) if server_tick < clock.tick => 0,
+            Some(_) => 1,
+        };
+        clock.tick += due;
+        due
+    })
+}
+
+// Snapshots overtaken on the way by newer ones are dropped, tick 0 comes
+// from a server without tick numbers and is always taken
+fn accept_snapshot(newest: &mut u64, tick: u64) -> bool {
+    if tick != 0 && tick < *newest {
+        return false;
+    }
+    *newest = tick;
+    true
+}
+
+/// Whether a `player_states` snapshot sent on `tick` should be applied
+pub fn accept_player_states(tick: u64) -> bool {
+    CLOCK.with(|clock| accept_snapshot(&mut clock.borrow_mut().player_states_tick, tick))
+}
+
+/// Whether a `map_state` snapshot sent on `tick` should be applied
+pub fn accept_map_state(tick: u64) -> bool {
+    CLOCK.with(|clock| accept_snapshot(&mut clock.borrow_mut().map_state_tick, tick))
+}
@@ -0,0 +1,230 @@
+use std::cell::Cell;
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{HtmlInputElement, WebSocket};
+use yew::{html, Component, Context, Html, classes, InputEvent, TargetCast};
+use crate::solo::local_storage;
+
+const STORAGE_KEY: &str = "network_conditions";
+const MAX_DELAY_MS: u32 = 2000;
+const MAX_JITTER_MS: u32 = 1000;
+// Messages picked for reordering are held back this much longer than the
+// jitter could, so the ones sent after them overtake them
+const REORDER_HOLD_MS: f64 = 100.0;
+
+/// How bad the connection to the server is made to look, in each direction
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+pub struct NetworkConditions {
+    pub enabled: bool,
+    pub delay_ms: u32,
+    /// Up to this much more delay, picked at random for every message
+    pub jitter_ms: u32,
+    pub loss_percent: u32,
+    pub reorder_percent: u32,
+}
+
+#[derive(Clone, Copy)]
+enum Route {
+    Outgoing,
+    Incoming,
+}
+
+thread_local! {
+    static CONDITIONS: Cell<NetworkConditions> = Cell::new(load_conditions());
+    // When the last message kept in order is delivered, in performance.now() milliseconds
+    static LAST_OUTGOING: Cell<f64> = const { Cell::new(0.0) };
+    static LAST_INCOMING: Cell<f64> = const { Cell::new(0.0) };
+}
+
+fn load_conditions() -> NetworkConditions {
+    local_storage()
+        .and_then(|storage| storage.get_item(STORAGE_KEY).ok()?)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Release builds have no panel to turn the conditioner off again, so
+/// conditions saved by a debug build are ignored there
+pub fn conditions() -> NetworkConditions {
+    if !cfg!(debug_assertions) {
+        return NetworkConditions::default();
+    }
+    CONDITIONS.with(Cell::get)
+}
+
+/// Change the conditions, they are kept across reloads
+pub fn set_conditions(conditions: NetworkConditions) {
+    CONDITIONS.with(|cell| cell.set(conditions));
+    if let (Some(storage), Ok(json)) = (local_storage(), serde_json::to_string(&conditions)) {
+        let _ = storage.set_item(STORAGE_KEY, &json);
+    }
+}
+
+pub fn now_ms() -> f64 {
+    web_sys::window()
+        .and_then(|window| window.performance())
+        .map_or(0.0, |performance| performance.now())
+}
+
+/// Hand a message over now, later or never, depending on the conditions
+fn condition<F: FnOnce() + 'static>(route: Route, deliver: F) {
+    let conditions = conditions();
+    if !conditions.enabled {
+        deliver();
+        return;
+    }
+    let mut rng = rand::thread_rng();
+    if rng.gen_range(0..100) < conditions.loss_percent {
+        return;
+    }
+    let now = now_ms();
+    let mut delay = conditions.delay_ms as f64 + rng.gen_range(0..=conditions.jitter_ms) as f64;
+    if rng.gen_range(0..100) < conditions.reorder_percent {
+        delay += conditions.jitter_ms as f64 + REORDER_HOLD_MS;
+    } else {
+        // Like TCP, a message held up by jitter holds up the ones behind it
+        let last = match route {
+            Route::Outgoing => &LAST_OUTGOING,
+            Route::Incoming => &LAST_INCOMING,
+        };
+        let deliver_at = last.with(|last| {
+            let deliver_at = (now + delay).max(last.get());
+            last.set(deliver_at);
+            deliver_at
+        });
+        delay = deliver_at - now;
+    }
+    let callback = Closure::once_into_js(deliver);
+    let scheduled = web_sys::window().map(|window| {
+        window.set_timeout_with_callback_and_timeout_and_arguments_0(callback.unchecked_ref(), delay.round() as i32)
+    });
+    if !matches!(scheduled, Some(Ok(_))) {
+        log::error!("Failed to hold back a message");
+    }
+}
+
+/// Send a message to the server through the conditioner
+pub fn send(ws: &WebSocket, message: &str) -> Result<(), JsValue> {
+    if !conditions().enabled {
+        return ws.send_with_str(message);
+    }
+    let ws = ws.clone();
+    let message = message.to_string();
+    condition(Route::Outgoing, move || {
+        if let Err(e) = ws.send_with_str(&message) {
+            log::error!("WebSocket send error: {:?}", e);
+        }
+    });
+    Ok(())
+}
+
+/// Pass a message from the server on to `deliver` through the conditioner
+pub fn receive<F: FnOnce(String) + 'static>(message: String, deliver: F) {
+    condition(Route::Incoming, move || deliver(message));
+}
+
+/// Debug build panel to turn the conditioner on and tune it
+pub struct ConditionerPanel {
+    open: bool,
+    conditions: NetworkConditions,
+}
+
+pub enum ConditionerMsg {
+    Toggle(()),
+    ToggleEnabled(()),
+    UpdateDelay(String),
+    UpdateJitter(String),
+    UpdateLoss(String),
+    UpdateReorder(String),
+}
+
+fn input_value(e: InputEvent) -> String {
+    let input: HtmlInputElement = e.target_unchecked_into();
+    input.value()
+}
+
+impl ConditionerPanel {
+    fn view_setting(&self, ctx: &Context<Self>, label: &str, value: u32, max: u32, msg: fn(String) -> ConditionerMsg) -> Html {
+        let handle_input = ctx.link().callback(move |e: InputEvent| msg(input_value(e)));
+        html! {
+            <label class={classes!("debug-panel__setting")}>
+                <span>{ format!("{} {}", label, value) }</span>
+                <input type="range" min="0" max={max.to_string()} value={value.to_string()} oninput={handle_input}/>
+            </label>
+        }
+    }
+}
+
+impl Component for ConditionerPanel {
+    type Message = ConditionerMsg;
+    type Properties = ();
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            open: false,
+            conditions: conditions(),
+        }
+    }
+    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+        let parse = |value: String, max: u32| value.parse::<u32>().map(|value| value.min(max));
+        match msg {
+            ConditionerMsg::Toggle(_) => {
+                self.open = !self.open;
+                return true;
+            }
+            ConditionerMsg::ToggleEnabled(_) => {
+                self.conditions.enabled = !self.conditions.enabled;
+            }
+            ConditionerMsg::UpdateDelay(value) => {
+                if let Ok(delay_ms) = parse(value, MAX_DELAY_MS) {
+                    self.conditions.delay_ms = delay_ms;
+                }
+            }
+            ConditionerMsg::UpdateJitter(value) => {
+                if let Ok(jitter_ms) = parse(value, MAX_JITTER_MS) {
+                    self.conditions.jitter_ms = jitter_ms;
+                }
+            }
+            ConditionerMsg::UpdateLoss(value) => {
+                if let Ok(loss_percent) = parse(value, 100) {
+                    self.conditions.loss_percent = loss_percent;
+                }
+            }
+            ConditionerMsg::UpdateReorder(value) => {
+                if let Ok(reorder_percent) = parse(value, 100) {
+                    self.conditions.reorder_percent = reorder_percent;
+                }
+            }
+        }
+        set_conditions(self.conditions);
+        true
+    }
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let handle_toggle = ctx.link().callback(|_| ConditionerMsg::Toggle(()));
+        let handle_enabled = ctx.link().callback(|_| ConditionerMsg::ToggleEnabled(()));
+        let label = if self.conditions.enabled { "Network (conditioned)" } else { "Network" };
+        html! {
+            <div class={classes!("debug-panel", "debug-panel--conditioner")}>
+                <button class={classes!("debug-panel__toggle")} onclick={handle_toggle}>{ label }</button>
+                {if self.open {
+                    html! {
+                        <>
+                            <label class={classes!("debug-panel__setting")}>
+                                <input type="checkbox" checked={self.conditions.enabled} onclick={handle_enabled}/>
+                                { " Condition traffic" }
+                            </label>
+                            { self.view_setting(ctx, "Delay (ms)", self.conditions.delay_ms, MAX_DELAY_MS, ConditionerMsg::UpdateDelay) }
+                            { self.view_setting(ctx, "Jitter (ms)", self.conditions.jitter_ms, MAX_JITTER_MS, ConditionerMsg::UpdateJitter) }
+                            { self.view_setting(ctx, "Loss (%)", self.conditions.loss_percent, 100, ConditionerMsg::UpdateLoss) }
+                            { self.view_setting(ctx, "Reorder (%)", self.conditions.reorder_percent, 100, ConditionerMsg::UpdateReorder) }
+                        </>
+                    }
+                } else {
+                    html! {}
+                }}
+            </div>
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::cell::Cell;
+use yew::{html, Html, classes};
+use crate::clock;
+use crate::conditioner::now_ms;
+
+// The rates shown are averaged over this long
+const SAMPLE_MS: f64 = 1000.0;
+
+thread_local! {
+    // Counted where messages leave and arrive, before the conditioner holds them back
+    static BYTES_SENT: Cell<u64> = const { Cell::new(0) };
+    static BYTES_RECEIVED: Cell<u64> = const { Cell::new(0) };
+}
+
+pub fn count_sent(message: &str) {
+    BYTES_SENT.with(|bytes| bytes.set(bytes.get() + message.len() as u64));
+}
+
+pub fn count_received(message: &str) {
+    BYTES_RECEIVED.with(|bytes| bytes.set(bytes.get() + message.len() as u64));
+}
+
+/// Timing and traffic figures for the overlay, times are performance.now() milliseconds
+#[derive(Default)]
+pub struct DebugStats {
+    pub visible: bool,
+    sample_start: f64,
+    sample_ticks: u64,
+    sample_sent: u64,
+    sample_received: u64,
+    tick_rate: f64,
+    sent_per_second: f64,
+    received_per_second: f64,
+    last_player_states: Option<f64>,
+    last_map_state: Option<f64>,
+    rtt_ms: Option<f64>,
+}
+
+fn format_since(time: Option<f64>, now: f64) -> String {
+    time.map_or("-".to_string(), |time| format!("{:.0} ms ago", now - time))
+}
+
+fn format_bytes(bytes_per_second: f64) -> String {
+    if bytes_per_second >= 1024.0 {
+        format!("{:.1} KB/s", bytes_per_second / 1024.0)
+    } else {
+        format!("{:.0} B/s", bytes_per_second)
+    }
+}
+
+impl DebugStats {
+    /// Count a game tick, true when a sample has finished and the rates changed
+    pub fn tick(&mut self) -> bool {
+        self.sample_ticks += 1;
+        let now = now_ms();
+        let elapsed = now - self.sample_start;
+        if elapsed < SAMPLE_MS {
+            return false;
+        }
+        let sent = BYTES_SENT.with(Cell::get);
+        let received = BYTES_RECEIVED.with(Cell::get);
+        let per_second = 1000.0 / elapsed;
+        self.tick_rate = self.sample_ticks as f64 * per_second;
+        self.sent_per_second = (sent - self.sample_sent) as f64 * per_second;
+        self.received_per_second = (received - self.sample_received) as f64 * per_second;
+        self.sample_start = now;
+        self.sample_ticks = 0;
+        self.sample_sent = sent;
+        self.sample_received = received;
+        true
+    }
+    pub fn player_states_received(&mut self) {
+        self.last_player_states = Some(now_ms());
+    }
+    pub fn map_state_received(&mut self) {
+        self.last_map_state = Some(now_ms());
+    }
+    pub fn round_trip_measured(&mut self, rtt_ms: f64) {
+        self.rtt_ms = Some(rtt_ms);
+    }
+    pub fn view(&self, network_id: u64) -> Html {
+        if !self.visible {
+            return html! {};
+        }
+        let now = now_ms();
+        let rows = [
+            ("Network id", network_id.to_string()),
+            ("Tick rate", format!("{:.1}/s", self.tick_rate)),
+            ("player_states", format_since(self.last_player_states, now)),
+            ("map_state", format_since(self.last_map_state, now)),
+            ("RTT", self.rtt_ms.map_or("-".to_string(), |rtt| format!("{:.0} ms", rtt))),
+            ("Clock offset", clock::offset().map_or("-".to_string(), |offset| format!("{:.0} ms", offset))),
+            ("Client tick", clock::client_tick().to_string()),
+            ("Server tick", clock::server_tick().map_or("-".to_string(), |tick| tick.to_string())),
+            ("Sent", format_bytes(self.sent_per_second)),
+            ("Received", format_bytes(self.received_per_second)),
+        ];
+        html! {
+            <div class={classes!("debug-overlay")}>
+                { for rows.into_iter().map(|(label, value)| html! {
+                    <div class={classes!("debug-overlay__row")}>
+                        <span>{ label }</span>
+                        <span>{ value }</span>
+                    </div>
+                })}
+            </div>
+        }
+    }
+}
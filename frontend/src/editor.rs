@@ -0,0 +1,418 @@
+use yew::{html, Callback, Component, Context, Html, classes, Event, InputEvent, TouchEvent, TargetCast};
+use yew::Properties;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Blob, File, HtmlAnchorElement, HtmlInputElement, HtmlTextAreaElement, Url};
+use craby_snake_shared::map_file::{
+    ascii_text, parse_map, EdgeBehaviour, MapError, MapFile, MapLayout,
+    FLOOR_CELL, FOOD_ZONE_CELL, MAX_MAP_SIZE, MIN_MAP_SIZE, WALL_CELL,
+};
+
+const DEFAULT_MAP_SIZE: i8 = 30;
+// What each tool paints, as a cell of the ASCII map format
+const TOOLS: [(char, &str); 7] = [
+    (WALL_CELL, "Wall"),
+    (FOOD_ZONE_CELL, "Food zone"),
+    ('^', "Spawn ↑"),
+    ('v', "Spawn ↓"),
+    ('<', "Spawn ←"),
+    ('>', "Spawn →"),
+    (FLOOR_CELL, "Erase"),
+];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum FileFormat {
+    Json,
+    Ascii,
+}
+
+#[derive(Properties, PartialEq, Clone)]
+pub struct MapEditorProps {
+    /// The map to start from, a blank one if there is none
+    pub initial: Option<MapLayout>,
+    pub on_play: Callback<MapLayout>,
+    pub on_close: Callback<()>,
+}
+
+/// Paint a map cell by cell, the map is kept as rows of ASCII cells and goes
+/// through the map file parser whenever it is played or exported
+pub struct MapEditorComponent {
+    name: String,
+    edge: EdgeBehaviour,
+    rows: Vec<Vec<char>>,
+    tool: char,
+    painting: bool,
+    format: FileFormat,
+    file_text: String,
+    error: Option<String>,
+}
+
+pub enum EditorMsg {
+    UpdateName(String),
+    SelectEdge(EdgeBehaviour),
+    UpdateWidth(String),
+    UpdateHeight(String),
+    SelectTool(char),
+    StartPaint((usize, usize)),
+    Paint((usize, usize)),
+    TouchPaint(TouchEvent),
+    StopPaint(()),
+    Clear(()),
+    SelectFormat(FileFormat),
+    UpdateFileText(String),
+    Export(()),
+    Import(()),
+    OpenFile(File),
+    FileOpened(Result<String, String>),
+    Play(()),
+    Close(()),
+}
+
+fn input_value(e: InputEvent) -> String {
+    let input: HtmlInputElement = e.target_unchecked_into();
+    input.value()
+}
+
+fn blank_rows(width: i8, height: i8) -> Vec<Vec<char>> {
+    vec![vec![FLOOR_CELL; width as usize]; height as usize]
+}
+
+/// Hand `text` to the browser as a file to save
+fn download_file(file_name: &str, text: &str) -> Result<(), JsValue> {
+    let blob = Blob::new_with_str_sequence(&js_sys::Array::of1(&JsValue::from_str(text)))?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+    let document = web_sys::window().and_then(|window| window.document()).ok_or("no document")?;
+    let anchor: HtmlAnchorElement = document.create_element("a")?.unchecked_into();
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+    Url::revoke_object_url(&url)
+}
+
+/// The text of a file picked in a file input
+async fn read_file(file: File) -> Result<String, String> {
+    let text = JsFuture::from(file.text()).await.map_err(|e| format!("Could not read the file: {:?}", e))?;
+    text.as_string().ok_or_else(|| "The file is not text".to_string())
+}
+
+/// The cell under a touch, read back from the data attributes of the grid
+fn touched_cell(e: &TouchEvent) -> Option<(usize, usize)> {
+    let touch = e.touches().get(0)?;
+    let element = web_sys::window()?.document()?.element_from_point(touch.client_x() as f32, touch.client_y() as f32)?;
+    let x = element.get_attribute("data-x")?.parse().ok()?;
+    let y = element.get_attribute("data-y")?.parse().ok()?;
+    Some((x, y))
+}
+
+impl MapEditorComponent {
+    fn width(&self) -> i8 {
+        self.rows.first().map_or(0, |row| row.len() as i8)
+    }
+    fn height(&self) -> i8 {
+        self.rows.len() as i8
+    }
+    fn paint(&mut self, (x, y): (usize, usize)) {
+        if let Some(cell) = self.rows.get_mut(y).and_then(|row| row.get_mut(x)) {
+            *cell = self.tool;
+        }
+    }
+    /// Keep what fits of the map and fill the rest with floor
+    fn resize(&mut self, width: i8, height: i8) {
+        let width = width.clamp(MIN_MAP_SIZE, MAX_MAP_SIZE) as usize;
+        let height = height.clamp(MIN_MAP_SIZE, MAX_MAP_SIZE) as usize;
+        self.rows.resize(height, vec![FLOOR_CELL; width]);
+        self.rows.iter_mut().for_each(|row| row.resize(width, FLOOR_CELL));
+    }
+    fn load(&mut self, layout: &MapLayout) {
+        self.name = layout.name.clone();
+        self.edge = layout.edge;
+        self.rows = layout.ascii_rows();
+    }
+    fn parse(&self) -> Result<MapFile, MapError> {
+        parse_map(&ascii_text(&self.name, self.edge, &self.rows))
+    }
+    /// The map's name made safe to use as a file name, with the extension
+    /// the server loads maps of this format from
+    fn file_name(&self) -> String {
+        let stem: String = self.name.trim().chars()
+            .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+            .collect();
+        let extension = match self.format {
+            FileFormat::Json => "json",
+            FileFormat::Ascii => "txt",
+        };
+        format!("{}.{}", if stem.is_empty() { "map" } else { &stem }, extension)
+    }
+    /// Load the map in the file text, or say why it can't be loaded
+    fn import(&mut self) {
+        match parse_map(&self.file_text) {
+            Ok(map) => {
+                self.load(&map.layout);
+                self.error = None;
+            }
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+    fn view_option<T: Copy + PartialEq + 'static>(
+        &self,
+        ctx: &Context<Self>,
+        label: &str,
+        value: T,
+        current: T,
+        msg: fn(T) -> EditorMsg,
+    ) -> Html {
+        let handle_click = ctx.link().callback(move |_| msg(value));
+        html! {
+            <button
+                class={classes!("leaderboard__tab", if value == current { "leaderboard__tab--selected" } else { "" })}
+                onclick={handle_click}
+            >
+                { label }
+            </button>
+        }
+    }
+    fn view_cell(&self, ctx: &Context<Self>, x: usize, y: usize, cell: char) -> Html {
+        let handle_mouse_down = ctx.link().callback(move |_| EditorMsg::StartPaint((x, y)));
+        let handle_mouse_enter = ctx.link().callback(move |_| EditorMsg::Paint((x, y)));
+        let handle_touch_start = ctx.link().callback(move |e: TouchEvent| {
+            e.prevent_default();
+            EditorMsg::StartPaint((x, y))
+        });
+        let is_spawn = !matches!(cell, FLOOR_CELL | WALL_CELL | FOOD_ZONE_CELL);
+        html! {
+            <div
+                key={x}
+                data-x={x.to_string()}
+                data-y={y.to_string()}
+                class={classes!(
+                    "cell",
+                    if cell == WALL_CELL { "cell--boundary" } else { "" },
+                    if cell == FOOD_ZONE_CELL { "cell--food-zone" } else { "" },
+                    if is_spawn { "cell--spawn" } else { "" },
+                )}
+                onmousedown={handle_mouse_down}
+                onmouseenter={handle_mouse_enter}
+                ontouchstart={handle_touch_start}
+            >
+                {if is_spawn {
+                    html! { <span class={classes!("power-up-icon")}>{ cell }</span> }
+                } else {
+                    html! {}
+                }}
+            </div>
+        }
+    }
+}
+
+impl Component for MapEditorComponent {
+    type Message = EditorMsg;
+    type Properties = MapEditorProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let mut editor = Self {
+            name: "Untitled".to_string(),
+            edge: EdgeBehaviour::Wrap,
+            rows: blank_rows(DEFAULT_MAP_SIZE, DEFAULT_MAP_SIZE),
+            tool: WALL_CELL,
+            painting: false,
+            format: FileFormat::Json,
+            file_text: String::new(),
+            error: None,
+        };
+        if let Some(ref layout) = ctx.props().initial {
+            editor.load(layout);
+        }
+        editor
+    }
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            EditorMsg::UpdateName(name) => {
+                self.name = name;
+            }
+            EditorMsg::SelectEdge(edge) => {
+                self.edge = edge;
+            }
+            EditorMsg::UpdateWidth(width) => {
+                if let Ok(width) = width.parse() {
+                    self.resize(width, self.height());
+                }
+            }
+            EditorMsg::UpdateHeight(height) => {
+                if let Ok(height) = height.parse() {
+                    self.resize(self.width(), height);
+                }
+            }
+            EditorMsg::SelectTool(tool) => {
+                self.tool = tool;
+            }
+            EditorMsg::StartPaint(cell) => {
+                self.painting = true;
+                self.paint(cell);
+            }
+            EditorMsg::Paint(cell) => {
+                if !self.painting {
+                    return false;
+                }
+                self.paint(cell);
+            }
+            EditorMsg::TouchPaint(e) => {
+                e.prevent_default();
+                match touched_cell(&e) {
+                    Some(cell) if self.painting => self.paint(cell),
+                    _ => return false,
+                }
+            }
+            EditorMsg::StopPaint(_) => {
+                self.painting = false;
+                return false;
+            }
+            EditorMsg::Clear(_) => {
+                self.rows = blank_rows(self.width(), self.height());
+            }
+            EditorMsg::SelectFormat(format) => {
+                self.format = format;
+            }
+            EditorMsg::UpdateFileText(text) => {
+                self.file_text = text;
+                return false;
+            }
+            EditorMsg::Export(_) => match self.parse() {
+                Ok(map) => {
+                    self.file_text = match self.format {
+                        FileFormat::Json => map.to_json(),
+                        FileFormat::Ascii => map.to_ascii(),
+                    };
+                    self.error = download_file(&self.file_name(), &self.file_text)
+                        .err()
+                        .map(|e| format!("Could not download the map: {:?}", e));
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            },
+            EditorMsg::Import(_) => {
+                self.import();
+            }
+            EditorMsg::OpenFile(file) => {
+                ctx.link().send_future(async move { EditorMsg::FileOpened(read_file(file).await) });
+                return false;
+            }
+            EditorMsg::FileOpened(Ok(text)) => {
+                self.file_text = text;
+                self.import();
+            }
+            EditorMsg::FileOpened(Err(e)) => {
+                self.error = Some(e);
+            }
+            EditorMsg::Play(_) => match self.parse() {
+                Ok(map) => ctx.props().on_play.emit(map.layout),
+                Err(e) => self.error = Some(e.to_string()),
+            },
+            EditorMsg::Close(_) => {
+                ctx.props().on_close.emit(());
+            }
+        }
+        true
+    }
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let handle_name = ctx.link().callback(|e: InputEvent| EditorMsg::UpdateName(input_value(e)));
+        let handle_width = ctx.link().callback(|e: InputEvent| EditorMsg::UpdateWidth(input_value(e)));
+        let handle_height = ctx.link().callback(|e: InputEvent| EditorMsg::UpdateHeight(input_value(e)));
+        let handle_file_text = ctx.link().callback(|e: InputEvent| {
+            let textarea: HtmlTextAreaElement = e.target_unchecked_into();
+            EditorMsg::UpdateFileText(textarea.value())
+        });
+        let handle_open_file = ctx.link().batch_callback(|e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let file = input.files().and_then(|files| files.get(0));
+            // Picking the same file again should load it again
+            input.set_value("");
+            file.map(EditorMsg::OpenFile)
+        });
+        let handle_stop = ctx.link().callback(|_| EditorMsg::StopPaint(()));
+        let handle_touch_end = ctx.link().callback(|_: TouchEvent| EditorMsg::StopPaint(()));
+        let handle_touch_move = ctx.link().callback(EditorMsg::TouchPaint);
+
+        html! {
+            <div class={classes!("editor-ctn")}>
+                <div class={classes!("room-bar")}>
+                    <input
+                        class={classes!("join-input")}
+                        placeholder="Map name"
+                        value={self.name.clone()}
+                        oninput={handle_name}
+                    />
+                    <label>
+                        { "Width " }
+                        <input
+                            class={classes!("lobby-number")}
+                            type="number"
+                            min={MIN_MAP_SIZE.to_string()}
+                            max={MAX_MAP_SIZE.to_string()}
+                            value={self.width().to_string()}
+                            oninput={handle_width}
+                        />
+                    </label>
+                    <label>
+                        { "Height " }
+                        <input
+                            class={classes!("lobby-number")}
+                            type="number"
+                            min={MIN_MAP_SIZE.to_string()}
+                            max={MAX_MAP_SIZE.to_string()}
+                            value={self.height().to_string()}
+                            oninput={handle_height}
+                        />
+                    </label>
+                    <span>
+                        { "Edge: " }
+                        { self.view_option(ctx, "Wrap", EdgeBehaviour::Wrap, self.edge, EditorMsg::SelectEdge) }
+                        { self.view_option(ctx, "Solid", EdgeBehaviour::Solid, self.edge, EditorMsg::SelectEdge) }
+                    </span>
+                </div>
+                <div class={classes!("room-bar")}>
+                    <span>
+                        { for TOOLS.iter().map(|(tool, label)| self.view_option(ctx, label, *tool, self.tool, EditorMsg::SelectTool)) }
+                    </span>
+                    <button class={classes!("leaderboard__tab")} onclick={ctx.link().callback(|_| EditorMsg::Clear(()))}>{ "Clear" }</button>
+                </div>
+                <div
+                    class={classes!("editor-grid")}
+                    onmouseup={handle_stop.clone()}
+                    onmouseleave={handle_stop}
+                    ontouchmove={handle_touch_move}
+                    ontouchend={handle_touch_end}
+                >
+                    { for self.rows.iter().enumerate().map(|(y, row)| html! {
+                        <div class="row" key={y}>
+                            { for row.iter().enumerate().map(|(x, cell)| self.view_cell(ctx, x, y, *cell)) }
+                        </div>
+                    })}
+                </div>
+                {if let Some(ref error) = self.error {
+                    html! { <p class={classes!("join-error")}>{ error }</p> }
+                } else {
+                    html! {}
+                }}
+                <div class={classes!("room-bar")}>
+                    <button class={classes!("btn")} onclick={ctx.link().callback(|_| EditorMsg::Play(()))}>{ "Test play" }</button>
+                    <span>
+                        { self.view_option(ctx, "JSON", FileFormat::Json, self.format, EditorMsg::SelectFormat) }
+                        { self.view_option(ctx, "ASCII", FileFormat::Ascii, self.format, EditorMsg::SelectFormat) }
+                        <button class={classes!("leaderboard__tab")} onclick={ctx.link().callback(|_| EditorMsg::Export(()))}>{ "Export" }</button>
+                        <button class={classes!("leaderboard__tab")} onclick={ctx.link().callback(|_| EditorMsg::Import(()))}>{ "Import" }</button>
+                        <label class={classes!("leaderboard__tab")}>
+                            { "Open file" }
+                            <input class={classes!("editor-file-input")} type="file" accept=".json,.txt" onchange={handle_open_file} />
+                        </label>
+                    </span>
+                    <button class={classes!("leaderboard__tab")} onclick={ctx.link().callback(|_| EditorMsg::Close(()))}>{ "Back to lobby" }</button>
+                </div>
+                <textarea
+                    class={classes!("editor-file")}
+                    placeholder="Paste a map file here to import it, or open one"
+                    value={self.file_text.clone()}
+                    oninput={handle_file_text}
+                />
+            </div>
+        }
+    }
+}
@@ -2,6 +2,7 @@
 use futures::StreamExt;
 use yew::{html, Component, Context, Html, classes, KeyboardEvent};
 use rand::Rng;
+use rand::seq::SliceRandom;
 use std::time::Duration;
 use futures::Stream;
 use yew::platform::time::interval;
@@ -9,68 +10,158 @@ use yew::Properties;
 use std::sync::{Arc, Mutex};
 use web_sys::WebSocket;
 use serde::{Serialize, Deserialize};
-use wasm_bindgen::JsValue;
-use wasm_bindgen::JsCast;
-use wasm_bindgen::closure::Closure;
-use web_sys::MessageEvent;
+use craby_snake_shared::map_file::MapLayout;
+use craby_snake_shared::protocol::{JoinRoomRequest, PlayerProfile, Pong};
+use craby_snake_shared::state::{
+    BodySegment, Direction, Food, FoodKind, HNum, MapState, PlayerStateData, PowerUp, PowerUpKind, Zone,
+    BOUNDARY_THICKNESS, NORMAL_TICKS_PER_MOVE,
+};
+use crate::lobby::{LobbyComponent, RoomSettings, RoomInfo, MAX_TEAMS};
+use crate::campaign::Level;
+use crate::editor::MapEditorComponent;
+use crate::clock;
+use crate::conditioner::{self, ConditionerPanel};
+use crate::debug_overlay::DebugStats;
+use crate::solo::{SoloMode, SoloRun};
 
-type HNum = i8;
+mod match_room;
+mod messages;
+mod render;
+mod solo;
 
-const BOUNDARY_THICKNESS: HNum = 1;
-const GRID_HEIGHT: HNum = 30;
-const GRID_WIDTH: HNum = 30;
 const GRID_OFFSET: HNum = BOUNDARY_THICKNESS * 2;
-const TICK_TIME: u64 = 50;
+// Must match SIMULATION_TICK_TIME on the server
+pub const TICK_TIME: u64 = 25;
+// Snake speed as the number of ticks between two moves next to
+// NORMAL_TICKS_PER_MOVE, must match the values in speed.ts on the server
+const FAST_TICKS_PER_MOVE: u64 = 1;
+const SLOW_TICKS_PER_MOVE: u64 = 4;
+// Boosting needs at least this many body segments and sheds one of
+// them every few moves, must match BOOST_MIN_LENGTH on the server
+const BOOST_MIN_LENGTH: usize = 4;
+const BOOST_SHED_MOVES: u64 = 4;
+// Must match RESPAWN_DELAY_MS and SPAWN_PROTECTION_MS on the server
+const RESPAWN_TICKS: u64 = 120;
+const SPAWN_PROTECTION_TICKS: u64 = 80;
+// Minimum distance in cells between a spawn point and any wall or other snake
+const SPAWN_CLEARANCE: HNum = 3;
+const KILL_FEED_LENGTH: usize = 5;
+const KILL_FEED_TICKS: u64 = 200;
+// Must match MAX_NAME_LENGTH on the server
+const MAX_NAME_LENGTH: usize = 16;
+const PLAYER_COLOURS: [&str; 8] = [
+    "#24b058", "#f19f4e", "#3f99ff", "#e05fd0", "#f5d547", "#4fd8e8", "#b48cff", "#f95c8a",
+];
+// Snakes in team rooms are drawn in their team's colour
+const TEAM_COLOURS: [&str; MAX_TEAMS as usize] = ["#e5484d", "#3f99ff", "#24b058", "#f5d547"];
+const TEAM_NAMES: [&str; MAX_TEAMS as usize] = ["Red", "Blue", "Green", "Yellow"];
+// Food within this many cells of the head is pulled in by a magnet
+const MAGNET_RADIUS: HNum = 2;
+// Segments lost when another snake picks up a shrink,
+// must match SHRINK_SEGMENTS on the server
+const SHRINK_SEGMENTS: usize = 3;
+// Food kept on the grid in solo runs, where there is no server to spawn it
+const SOLO_FOOD_COUNT: usize = 3;
+// Random cells tried before giving up on placing solo food
+const SOLO_FOOD_ATTEMPTS: usize = 100;
 
 pub fn start_game_tick(ms: u64) -> impl Stream<Item = ()> {
     interval(Duration::from_millis(ms))
 }
 
-/// Generate a random position within the grid
+/// Generate a random position within the map
 /// The position will not be on the boundary
-fn get_random_position() -> PositionCoords {
+fn get_random_position(map: &MapLayout) -> PositionCoords {
     let mut rng = rand::thread_rng();
     PositionCoords::new(
-        rng.gen_range(BOUNDARY_THICKNESS..=GRID_WIDTH),
-        rng.gen_range(BOUNDARY_THICKNESS..=GRID_HEIGHT)
+        rng.gen_range(BOUNDARY_THICKNESS..map.width + BOUNDARY_THICKNESS),
+        rng.gen_range(BOUNDARY_THICKNESS..map.height + BOUNDARY_THICKNESS)
     )
 }
 
-fn get_random_direction() -> Direction {
+/// Generate a spawn position that keeps `SPAWN_CLEARANCE` cells of space
+/// to the boundary, the walls and every other snake, and that is not on food
+/// or in the path of another snake's head. The clearance is relaxed one step
+/// at a time if the map is too crowded.
+fn get_safe_spawn_position(map: &MapLayout, player_states: &[PlayerStateData], food_positions: &[Food]) -> PositionCoords {
     let mut rng = rand::thread_rng();
-    match rng.gen_range(0..4) {
-        0 => Direction::UP,
-        1 => Direction::DOWN,
-        2 => Direction::LEFT,
-        _ => Direction::RIGHT,
+    for clearance in (0..=SPAWN_CLEARANCE).rev() {
+        let candidates: Vec<PositionCoords> = (BOUNDARY_THICKNESS + clearance..map.height + BOUNDARY_THICKNESS - clearance)
+            .flat_map(|y| {
+                (BOUNDARY_THICKNESS + clearance..map.width + BOUNDARY_THICKNESS - clearance)
+                    .map(move |x| PositionCoords::new(x, y))
+            })
+            .filter(|pos| !is_near_wall(map, pos, clearance))
+            .filter(|pos| is_clear_spawn_position(pos, player_states, food_positions, clearance))
+            .collect();
+        if let Some(position) = candidates.choose(&mut rng) {
+            return position.clone();
+        }
     }
+    get_random_position(map)
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-enum Direction {
-    UP,
-    DOWN,
-    LEFT,
-    RIGHT,
+fn is_near_wall(map: &MapLayout, pos: &PositionCoords, clearance: HNum) -> bool {
+    (-clearance..=clearance).any(|dy| (-clearance..=clearance).any(|dx| map.is_wall(pos.x + dx, pos.y + dy)))
 }
 
-impl Clone for Direction {
-    fn clone(&self) -> Direction {
-        use Direction as D;
-        match self {
-            D::UP => D::UP,
-            D::DOWN => D::DOWN,
-            D::LEFT => D::LEFT,
-            D::RIGHT => D::RIGHT,
-        }
+/// Pick one of the map's own spawn points that is clear of other snakes
+fn get_map_spawn_point(map: &MapLayout, player_states: &[PlayerStateData], food_positions: &[Food]) -> Option<(PositionCoords, Direction)> {
+    let clear: Vec<(PositionCoords, Direction)> = map.spawn_points.iter()
+        .map(|spawn| (PositionCoords::new(spawn.x + BOUNDARY_THICKNESS, spawn.y + BOUNDARY_THICKNESS), spawn.direction))
+        .filter(|(pos, _)| is_clear_spawn_position(pos, player_states, food_positions, SPAWN_CLEARANCE))
+        .collect();
+    clear.choose(&mut rand::thread_rng()).cloned()
+}
+
+fn is_clear_spawn_position(
+    pos: &PositionCoords,
+    player_states: &[PlayerStateData],
+    food_positions: &[Food],
+    clearance: HNum,
+) -> bool {
+    if food_positions.iter().any(|food| food.x == pos.x && food.y == pos.y) {
+        return false;
     }
+    player_states.iter().all(|player_state| {
+        let near_body = player_state.body_segments.iter().any(|segment| {
+            (segment.x - pos.x).abs().max((segment.y - pos.y).abs()) <= clearance
+        });
+        // The head is the last segment, look a few cells ahead of it,
+        // further for faster snakes
+        let lookahead = SPAWN_CLEARANCE * 2 * (NORMAL_TICKS_PER_MOVE / player_state.ticks_per_move.max(1)).max(1) as HNum;
+        let in_head_path = player_state.body_segments.last().is_some_and(|head| {
+            let (dx, dy) = head.direction.delta();
+            (1..=lookahead).any(|step| head.x + dx * step == pos.x && head.y + dy * step == pos.y)
+        });
+        !near_body && !in_head_path
+    })
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-struct BodySegment {
-    x: HNum,
-    y: HNum,
-    direction: Direction,
+/// Point a freshly spawned snake toward the longest straight run of free cells
+fn get_open_direction(map: &MapLayout, pos: &PositionCoords, player_states: &[PlayerStateData]) -> Direction {
+    let directions = [Direction::UP, Direction::DOWN, Direction::LEFT, Direction::RIGHT];
+    let runs: Vec<HNum> = directions.iter().map(|dir| free_run_length(map, pos, dir, player_states)).collect();
+    let longest = runs.iter().copied().max().unwrap_or(0);
+    let open: Vec<&Direction> = directions.iter().zip(runs).filter(|(_, run)| *run == longest).map(|(dir, _)| dir).collect();
+    open.choose(&mut rand::thread_rng()).map(|dir| **dir).unwrap_or(Direction::RIGHT)
+}
+
+fn free_run_length(map: &MapLayout, pos: &PositionCoords, dir: &Direction, player_states: &[PlayerStateData]) -> HNum {
+    let (dx, dy) = dir.delta();
+    let (mut x, mut y) = (pos.x + dx, pos.y + dy);
+    let mut run = 0;
+    while !map.is_boundary(x, y)
+        && !map.is_wall(x, y)
+        && !player_states.iter().any(|player_state| {
+            player_state.body_segments.iter().any(|segment| segment.x == x && segment.y == y)
+        })
+    {
+        run += 1;
+        x += dx;
+        y += dy;
+    }
+    run
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -104,52 +195,122 @@ impl PartialEq for WebSocketWrapper {
     }
 }
 
+/// A death decided by the server, `attacker_id` equals `victim_id`
+/// for self-inflicted deaths
 #[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct PlayerStateData {
+pub struct Kill {
+    attacker_id: u64,
+    victim_id: u64,
+    #[serde(default)]
+    attacker_name: String,
+    #[serde(default)]
+    victim_name: String,
+}
+
+/// `player_id` picked up a power-up
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PowerUpPickup {
     player_id: u64,
-    body_segments: Vec<BodySegment>,
+    kind: PowerUpKind,
 }
 
+/// An effect starting on or running out for `player_id`
 #[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct MapState {
-    food_positions: Vec<PositionCoords>,
+pub struct EffectChange {
+    player_id: u64,
+    kind: PowerUpKind,
+    duration_ms: u64,
 }
 
-fn send_server_message(ws: &WebSocket, message: &str) -> Result<(), JsValue> {
-    ws.send_with_str(message)
-        .map_err(|e| JsValue::from_str(&format!("WebSocket send error: {:?}", e)))?;
-    Ok(())
+struct ActiveEffect {
+    kind: PowerUpKind,
+    ticks_left: u64,
 }
 
-fn send_eat_food_message(ws: &WebSocket, player_id: u64, food_position: PositionCoords) -> Result<(), JsValue> {
-    let food_position_json: String = serde_json::to_string(&food_position).map_err(|e| JsValue::from_str(&e.to_string()))?;
-    let server_message = ServerMessage {
-        message_type: "eat_food".to_string(),
-        player_id,
-        message: food_position_json,
-    };
-    
-    let message = serde_json::to_string(&server_message).map_err(|e| JsValue::from_str(&e.to_string()))?;
-    send_server_message(ws, &message)
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaderboardPeriod {
+    Daily,
+    AllTime,
 }
-fn send_player_data(ws: &WebSocket, player_id: u64, player_data: &PlayerStateData) -> Result<(), JsValue> {
-    // Serialize the PlayerStateData struct into a JSON string
-    let player_state_json = serde_json::to_string(player_data).map_err(|e| JsValue::from_str(&e.to_string()))?;
-    let server_message = ServerMessage {
-        message_type: "player_state".to_string(),
-        player_id,
-        message: player_state_json,
-    };
-
-    let message = serde_json::to_string(&server_message).map_err(|e| JsValue::from_str(&e.to_string()))?;
-    send_server_message(ws, &message)
+
+/// The score a snake had when it died, kept by the server
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct LeaderboardEntry {
+    name: String,
+    score: u64,
+    kills: u64,
+    length: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Leaderboard {
+    period: LeaderboardPeriod,
+    entries: Vec<LeaderboardEntry>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct ServerMessage {
-    message_type: String, // "player_message" or other types
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchPhase {
+    Waiting,
+    Countdown,
+    Playing,
+    Finished,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct MatchResult {
     player_id: u64,
-    message: String, // The JSON string containing `PlayerStateData`
+    name: String,
+    #[serde(default)]
+    team: Option<u64>,
+    placement: u64,
+    kills: u64,
+    survived: bool,
+}
+
+/// A team's combined result, teams are ranked by survivors and then kills
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct TeamResult {
+    team: u64,
+    placement: u64,
+    kills: u64,
+    survivors: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct MatchPlayer {
+    player_id: u64,
+    name: String,
+    team: Option<u64>,
+}
+
+/// The state of the round in a match mode room
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MatchInfo {
+    phase: MatchPhase,
+    seconds_left: u64,
+    ready_ids: Vec<u64>,
+    player_count: u64,
+    #[serde(default)]
+    players: Vec<MatchPlayer>,
+    results: Vec<MatchResult>,
+    #[serde(default)]
+    team_results: Vec<TeamResult>,
+}
+
+/// Where a snake starts a match round
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MatchSpawn {
+    player_id: u64,
+    x: HNum,
+    y: HNum,
+    direction: Direction,
+}
+
+struct KillFeedEntry {
+    text: String,
+    ticks_left: u64,
 }
 
 #[derive(Properties, PartialEq, Clone)]
@@ -161,76 +322,114 @@ pub struct GameGridComponent{
     x: HNum,
     y: HNum,
     current_direction: Direction,
-    pending_body_segment: bool,
+    // Segments still to be added to the tail, one per move
+    pending_growth: u64,
     score: u64,
+    kills: u64,
+    kill_feed: Vec<KillFeedEntry>,
     paused: bool,
     dead: bool,
-    food_positions: Vec<PositionCoords>,
+    respawn_ticks: u64,
+    spawn_protection_ticks: u64,
+    food_positions: Vec<Food>,
+    power_ups: Vec<PowerUp>,
+    effects: Vec<ActiveEffect>,
+    // Ticks since the snake last moved
+    move_ticks: u64,
+    // Whether the boost key is held down
+    boost_held: bool,
+    // Moves made while boosting, a segment is shed every few of them
+    boost_moves: u64,
+    // The playable area and what it closes in to next, if it is shrinking
+    zone: Option<Zone>,
+    next_zone: Option<Zone>,
     body_segments: Vec<BodySegment>,
     ws: Option<WebSocketWrapper>, // Store WebSocket in the component state
     network_id: u64,
     player_states: Vec<PlayerStateData>,
+    joined: bool,
+    name: String,
+    colour: String,
+    join_error: Option<String>,
+    leaderboard_period: LeaderboardPeriod,
+    leaderboard: Option<Leaderboard>,
+    rooms: Vec<RoomInfo>,
+    // Maps the server can create rooms with
+    maps: Vec<String>,
+    room: Option<RoomInfo>,
+    lobby_error: Option<String>,
+    match_info: Option<MatchInfo>,
+    // Assigned by the server in team rooms
+    team: Option<u64>,
+    // Sitting out a match round, either waiting for it or knocked out of it
+    spectating: bool,
+    // An offline practice run, played instead of a room
+    solo: Option<SoloRun>,
+    // The map being played on, and the campaign level it comes from
+    level: Option<Level>,
+    map: MapLayout,
+    // The map editor is open, and the map last played from it
+    editing: bool,
+    editor_map: Option<MapLayout>,
+    // Shown over the grid, toggled with the backtick key
+    debug_stats: DebugStats,
+    // Timer beats since the clock was last synced with the server
+    ticks_since_sync: u64,
 }
 pub enum Msg {
     GameTicked(()),
     HandleKeyboardEvent(KeyboardEvent),
+    HandleKeyUp(KeyboardEvent),
     RestartGame(()),
     HandlePause(()),
     UpdateNetworkId(u64),
     UpdatePlayerStates(Vec<PlayerStateData>),
     UpdateMapState(MapState),
+    PlayerDied(Kill),
+    PlayerRespawned(PlayerStateData),
+    PlayerStateRejected(PlayerStateData),
+    PlayerDespawned(u64),
+    UpdateName(String),
+    SelectColour(String),
+    Join(()),
+    JoinAccepted(PlayerProfile),
+    JoinRejected(String),
+    ShowLeaderboard(LeaderboardPeriod),
+    UpdateLeaderboard(Leaderboard),
+    ListRooms(()),
+    UpdateRoomList(Vec<RoomInfo>),
+    UpdateMapList(Vec<String>),
+    CreateRoom(RoomSettings),
+    JoinRoom(JoinRoomRequest),
+    RoomJoined(RoomInfo),
+    RoomError(String),
+    LeaveRoom(()),
+    UpdateMatchState(MatchInfo),
+    MatchStarted(Vec<MatchSpawn>),
+    SetReady(bool),
+    StartSolo(SoloMode),
+    LeaveSolo(()),
+    OpenEditor(()),
+    CloseEditor(()),
+    TestPlayMap(MapLayout),
+    TeamAssigned(Option<u64>),
+    SetTeam(u64),
+    PowerUpPicked(PowerUpPickup),
+    EffectStarted(EffectChange),
+    EffectExpired(EffectChange),
+    Pong(Pong),
 }
 
 impl GameGridComponent {
-    fn connect_to_server(&mut self, ctx: &Context<Self>) {
-        if let Some(ref ws_wrapper) = self.ws {
-            let ws_clone = ws_wrapper.clone();
-            let link = ctx.link().clone();
-    
-            let on_message: Closure<dyn FnMut(MessageEvent)> = Closure::wrap(Box::new(move |event: web_sys::MessageEvent| {
-                if let Some(message) = event.data().as_string() {
-                    match serde_json::from_str::<ServerMessage>(&message) {
-                        Ok(server_message) => {
-                            match server_message.message_type.as_str() {
-                                "assign_id" => {
-                                    // Send message to update component's network_id
-                                    link.send_message(Msg::UpdateNetworkId(server_message.player_id));
-                                },
-                                "player_states" => {
-                                    if let Ok(player_states) = serde_json::from_str::<Vec<PlayerStateData>>(&server_message.message) {
-                                        // Send message to update component's player states
-                                        link.send_message(Msg::UpdatePlayerStates(player_states));
-                                    }
-                                },
-                                "map_state" => {
-                                    if let Ok(map_state) = serde_json::from_str::<MapState>(&server_message.message) {
-                                        // Send message to update component's player states
-                                        link.send_message(Msg::UpdateMapState(map_state));
-                                    }
-                                },
-                                _ => {}
-                            }
-                        }
-                        Err(e) => {
-                            log::error!("Failed to deserialize ServerMessage: {}", e);
-                        }
-                    }
-                }
-            }) as Box<dyn FnMut(web_sys::MessageEvent)>);
-    
-            ws_clone.lock().unwrap().set_onmessage(Some(on_message.as_ref().unchecked_ref()));
-            on_message.forget();
-        }
-    }
     fn move_up(&mut self) {
         if self.y == 0 {
-            self.y = GRID_HEIGHT + GRID_OFFSET - 1;
+            self.y = self.map.height + GRID_OFFSET - 1;
         } else {
             self.y -= 1;
         }
     }
     fn move_down(&mut self) {
-        if self.y == GRID_HEIGHT + GRID_OFFSET - 1 {
+        if self.y == self.map.height + GRID_OFFSET - 1 {
             self.y = 0;
         } else {
             self.y += 1;
@@ -238,13 +437,13 @@ impl GameGridComponent {
     }
     fn move_left(&mut self) {
         if self.x == 0 {
-            self.x = GRID_WIDTH + GRID_OFFSET - 1;
+            self.x = self.map.width + GRID_OFFSET - 1;
         } else {
             self.x -= 1;
         }
     }
     fn move_right(&mut self) {
-        if self.x == GRID_WIDTH + GRID_OFFSET - 1 {
+        if self.x == self.map.width + GRID_OFFSET - 1 {
             self.x = 0;
         } else {
             self.x += 1;
@@ -262,10 +461,17 @@ impl GameGridComponent {
             "ArrowDown" => self.update_direction(Direction::DOWN),
             "ArrowLeft" => self.update_direction(Direction::LEFT),
             "ArrowRight" => self.update_direction(Direction::RIGHT),
+            "Shift" => self.boost_held = true,
+            "`" => self.debug_stats.visible = !self.debug_stats.visible,
             //" " => self.update_pause(!self.paused), // spacebar
             _ => {}
         }
     }
+    fn handle_keyup(&mut self, event: KeyboardEvent) {
+        if event.key() == "Shift" {
+            self.boost_held = false;
+        }
+    }
     fn handle_tick(&mut self) {
         let pos = PositionCoords::new(self.x, self.y);
         match self.current_direction {
@@ -275,14 +481,14 @@ impl GameGridComponent {
             Direction::RIGHT => self.move_right(),
         }
         
-        // Add pending body segment if needed
-        if self.pending_body_segment {
+        // Add a pending body segment if needed
+        if self.pending_growth > 0 {
             self.body_segments.push(BodySegment {
                 x: pos.x,
                 y: pos.y,
-                direction: self.current_direction.clone(),
+                direction: self.current_direction,
             });
-            self.pending_body_segment = false;
+            self.pending_growth -= 1;
         }
         
         // update body segments
@@ -290,101 +496,372 @@ impl GameGridComponent {
             if i == 0 {
                 self.body_segments[i].x = pos.x;
                 self.body_segments[i].y = pos.y;
-                self.body_segments[i].direction = self.current_direction.clone();
+                self.body_segments[i].direction = self.current_direction;
             } else {
                 self.body_segments[i].x = self.body_segments[i - 1].x;
                 self.body_segments[i].y = self.body_segments[i - 1].y;
-                self.body_segments[i].direction = self.body_segments[i - 1].direction.clone();
+                self.body_segments[i].direction = self.body_segments[i - 1].direction;
             }
         }
 
         // Handle the game tick, update positions, etc.
-        let player_data: PlayerStateData = PlayerStateData {
-            player_id: self.network_id,//"player1".to_string(), // Replace with actual player ID
+        let player_data = self.player_state_data();
+        self.send_to_server("player_state", &player_data);
+    }
+    fn player_state_data(&self) -> PlayerStateData {
+        PlayerStateData {
+            player_id: self.network_id,
             body_segments: {
                 let mut segments = self.body_segments.clone();
                 segments.push(BodySegment {
                     x: self.x,
                     y: self.y,
-                    direction: self.current_direction.clone(),
+                    direction: self.current_direction,
                 });
                 segments
             },
+            invulnerable: self.is_spawn_protected(),
+            kills: self.kills,
+            score: self.score,
+            name: self.name.clone(),
+            colour: self.colour.clone(),
+            team: self.team,
+            effects: self.effects.iter().map(|effect| effect.kind).collect(),
+            ticks_per_move: self.ticks_per_move(),
+            boosting: self.is_boosting(),
+        }
+    }
+    /// Collisions with other snakes are resolved by the server for all
+    /// snakes at once and arrive as `player_died` messages
+    fn is_game_over(&self) -> bool {
+        self.map.is_deadly(self.x, self.y) || self.is_body_segment(self.x, self.y)
+    }
+    fn is_spawn_protected(&self) -> bool {
+        self.spawn_protection_ticks > 0
+    }
+    fn add_score(&mut self, score: i64) {
+        self.score = self.score.saturating_add_signed(score);
+    }
+    /// Queue segments to grow by, or cut them off the tail right away
+    fn grow(&mut self, growth: i64) {
+        if growth >= 0 {
+            self.pending_growth += growth.unsigned_abs();
+            return;
+        }
+        let shrink = growth.unsigned_abs() as usize;
+        let keep = self.body_segments.len().saturating_sub(shrink);
+        self.body_segments.truncate(keep);
+        self.pending_growth = 0;
+    }
+    /// Carry on from the state the server kept after it turned down our
+    /// updates, growth it did not allow is dropped. The server takes our
+    /// updates again once it hears we did.
+    fn resync(&mut self, player_state: PlayerStateData) {
+        if self.dead {
+            return;
+        }
+        let mut segments = player_state.body_segments;
+        let Some(head) = segments.pop() else {
+            return;
         };
-
-        // Send the player data to the server if an id has been 
-        // assigned by the server
-        if self.network_id == 0 {
+        self.x = head.x;
+        self.y = head.y;
+        self.current_direction = head.direction;
+        self.body_segments = segments;
+        self.pending_growth = 0;
+        self.score = player_state.score;
+        self.send_to_server("player_state_resynced", &());
+    }
+    /// Stop the snake and start the respawn countdown
+    fn enter_dead_state(&mut self) {
+        self.update_pause(true);
+        self.effects.clear();
+        // Dying ends a solo run, time attack and campaign runs without a time
+        if let Some(ref mut run) = self.solo {
+            let result = match run.mode {
+                SoloMode::TimeAttack | SoloMode::Campaign(_) | SoloMode::TestPlay => None,
+                SoloMode::ScoreAttack => Some(self.score),
+            };
+            run.finish(result);
             return;
         }
-        if let Some(ref ws_wrapper) = &self.ws {
-            // Now safely lock the WebSocketWrapper
-            if let Ok(ws) = ws_wrapper.0.lock() {
-                // If lock succeeds, send player data
-                if send_player_data(&ws, self.network_id, &player_data).is_err() {
-                    log::error!("Failed to send player data through WebSocket");
-                }
-            } else {
-                log::error!("Failed to lock WebSocket");
+        // There is no respawning in the middle of a match round
+        if self.is_match_room() {
+            self.spectating = true;
+            return;
+        }
+        self.dead = true;
+        self.respawn_ticks = RESPAWN_TICKS;
+    }
+    fn die(&mut self) {
+        self.enter_dead_state();
+        self.send_to_server("player_died", &());
+    }
+    /// Run one game tick, true when it needs a re-render
+    fn game_tick(&mut self) -> bool {
+        // The kill feed and debug overlay change even when nothing moves
+        let changed = self.age_kill_feed() | self.sample_debug_stats();
+        // Nothing moves until a room has been joined or a solo run started
+        if (self.room.is_none() && self.solo.is_none()) || self.spectating {
+            return changed;
+        }
+        if self.dead {
+            if self.respawn_ticks == 0 {
+                return changed;
             }
-        } else {
-            log::error!("WebSocket is not initialized");
+            self.respawn_ticks -= 1;
+            return true; // re-render the countdown
         }
+        if self.paused {
+            return changed; // don't re-render if paused
+        }
+        if self.update_solo_run() {
+            return true;
+        }
+        self.age_effects();
+        self.spawn_protection_ticks = self.spawn_protection_ticks.saturating_sub(1);
+        self.move_ticks += 1;
+        if self.move_ticks < self.ticks_per_move() {
+            return changed;
+        }
+        self.move_ticks = 0;
+        if self.is_game_over() {
+            self.die();
+            return true;
+        }
+        self.handle_tick();
+        self.spend_boost();
+        self.pick_up_power_up();
+        if let Some(food) = self.eat_food() {
+            //self.respawn_food();
+            /*self.body_segments.push(BodySegment {
+                x: self.x,
+                y: self.y,
+                direction: self.current_direction,
+            });*/
+            self.grow(food.growth);
+            self.add_score(food.score);
+            if self.solo.is_some() {
+                self.add_solo_food();
+            }
+        }
+        true
     }
-    fn is_game_over(&self) -> bool {
-        // is_boundary(self.x, self.y) ||
-        self.is_body_segment(self.x, self.y) || self.is_other_player_segment(self.x, self.y)
+    /// Update the debug figures, true when the overlay needs redrawing
+    fn sample_debug_stats(&mut self) -> bool {
+        self.debug_stats.tick() && self.debug_stats.visible
+    }
+    /// Ping the server every so often to keep the clock estimate fresh
+    fn sync_clock(&mut self) {
+        self.ticks_since_sync += 1;
+        if self.ticks_since_sync < clock::sync_interval() {
+            return;
+        }
+        self.ticks_since_sync = 0;
+        let sent_at = conditioner::now_ms();
+        self.send_to_server("ping", &sent_at);
+    }
+    fn list_rooms(&self) {
+        self.send_to_server("list_rooms", &());
     }
-    fn increment_score(&mut self) {
-        self.score += 1;
+    /// Start over in a freshly joined room
+    fn enter_room(&mut self, room: RoomInfo) {
+        self.room = Some(room);
+        self.lobby_error = None;
+        self.player_states.clear();
+        self.food_positions.clear();
+        self.power_ups.clear();
+        self.effects.clear();
+        self.zone = None;
+        self.next_zone = None;
+        self.kill_feed.clear();
+        self.respawn_ticks = 0;
+        self.match_info = None;
+        self.spectating = self.is_match_room();
+        if !self.spectating {
+            self.spawn();
+        }
+    }
+    fn leave_room(&mut self) {
+        self.send_to_server("leave_room", &());
+        self.room = None;
+        self.dead = false;
+        self.spectating = false;
+        self.match_info = None;
+        self.team = None;
+        self.player_states.clear();
+        self.map = MapLayout::default();
+        self.list_rooms();
+    }
+    fn fetch_leaderboard(&mut self, period: LeaderboardPeriod) {
+        self.leaderboard_period = period;
+        self.send_to_server("get_leaderboard", &period);
+    }
+    fn join(&mut self) {
+        let profile = PlayerProfile {
+            name: self.name.trim().to_string(),
+            colour: self.colour.clone(),
+        };
+        if profile.name.is_empty() {
+            self.join_error = Some("Pick a nickname".to_string());
+            return;
+        }
+        self.send_to_server("join", &profile);
     }
     fn restart(&mut self) {
-        let mut rng = rand::thread_rng();
-        self.x = rng.gen_range(BOUNDARY_THICKNESS..=GRID_WIDTH);
-        self.y = rng.gen_range(BOUNDARY_THICKNESS..=GRID_HEIGHT);
-        self.current_direction = get_random_direction();
+        self.spawn();
+
+        let player_data = self.player_state_data();
+        self.send_to_server("player_respawned", &player_data);
+    }
+    /// Place a fresh snake on the grid
+    fn spawn(&mut self) {
+        let (spawn_position, direction) = get_map_spawn_point(&self.map, &self.player_states, &self.food_positions)
+            .unwrap_or_else(|| {
+                let position = get_safe_spawn_position(&self.map, &self.player_states, &self.food_positions);
+                let direction = get_open_direction(&self.map, &position, &self.player_states);
+                (position, direction)
+            });
+        self.current_direction = direction;
+        self.x = spawn_position.x;
+        self.y = spawn_position.y;
         self.score = 0;
         self.paused = false;
         self.dead = false;
+        self.spawn_protection_ticks = SPAWN_PROTECTION_TICKS;
         self.body_segments.clear();
     }
     fn is_food_coordinate(&self, x: HNum, y: HNum) -> bool {
         self.food_positions.iter().any(|pos| pos.x == x && pos.y == y)
     }
-    fn is_food_eaten(&self) -> bool {
-        let is_food = self.is_food_coordinate(self.x, self.y);
-        
-        if is_food {
-            if let Some(ref ws_wrapper) = &self.ws {
-                // Now safely lock the WebSocketWrapper
-                if let Ok(ws) = ws_wrapper.0.lock() {
-                    // If lock succeeds, send player data
-                    let food_position = PositionCoords::new(self.x, self.y);
-                    if send_eat_food_message(&ws, self.network_id, food_position).is_err() {
-                        log::error!("Failed to send player data through WebSocket");
-                    }
-                } else {
-                    log::error!("Failed to lock WebSocket");
-                }
-            } else {
-                log::error!("WebSocket is not initialized");
-            }
+    fn food_at(&self, x: HNum, y: HNum) -> Option<&Food> {
+        self.food_positions.iter().find(|food| food.x == x && food.y == y)
+    }
+    fn eat_food(&mut self) -> Option<Food> {
+        let food = self.food_in_reach()?;
+        // Drop it right away so a magnet does not pull it in twice
+        self.food_positions.retain(|pos| pos.x != food.x || pos.y != food.y);
+        let food_position = PositionCoords::new(food.x, food.y);
+        self.send_to_server("eat_food", &food_position);
+        Some(food)
+    }
+    /// The food under the head, or with a magnet the closest food around it
+    fn food_in_reach(&self) -> Option<Food> {
+        if let Some(food) = self.food_at(self.x, self.y) {
+            return Some(food.clone());
         }
-        
-        is_food
+        if !self.has_effect(PowerUpKind::Magnet) {
+            return None;
+        }
+        let distance = |food: &Food| (food.x - self.x).abs().max((food.y - self.y).abs());
+        self.food_positions.iter()
+            // A magnet does not pull in poison
+            .filter(|food| food.kind != FoodKind::Poison && distance(food) <= MAGNET_RADIUS)
+            .min_by_key(|food| distance(food))
+            .cloned()
+    }
+    fn power_up_at(&self, x: HNum, y: HNum) -> Option<&PowerUp> {
+        self.power_ups.iter().find(|power_up| power_up.x == x && power_up.y == y)
+    }
+    /// The effect itself starts when the server confirms the pickup
+    fn pick_up_power_up(&mut self) {
+        if self.power_up_at(self.x, self.y).is_none() {
+            return;
+        }
+        let (x, y) = (self.x, self.y);
+        self.power_ups.retain(|power_up| power_up.x != x || power_up.y != y);
+        self.send_to_server("pick_up_power_up", &PositionCoords::new(x, y));
+    }
+    fn has_effect(&self, kind: PowerUpKind) -> bool {
+        self.effects.iter().any(|effect| effect.kind == kind)
+    }
+    fn start_effect(&mut self, kind: PowerUpKind, duration_ms: u64) {
+        if kind == PowerUpKind::ShrinkOthers {
+            let keep = self.body_segments.len().saturating_sub(SHRINK_SEGMENTS);
+            // The tail end is at the back of `body_segments`
+            self.body_segments.truncate(keep);
+            return;
+        }
+        self.effects.retain(|effect| effect.kind != kind);
+        self.effects.push(ActiveEffect { kind, ticks_left: duration_ms / TICK_TIME });
+    }
+    /// Count down the effects, the server sends `effect_expired` as well
+    fn age_effects(&mut self) {
+        self.effects.iter_mut().for_each(|effect| effect.ticks_left = effect.ticks_left.saturating_sub(1));
+        self.effects.retain(|effect| effect.ticks_left > 0);
+    }
+    /// Speed and slow cancel each other out, boosting doubles the speed
+    fn ticks_per_move(&self) -> u64 {
+        let ticks_per_move = match (self.has_effect(PowerUpKind::Speed), self.has_effect(PowerUpKind::Slow)) {
+            (true, false) => FAST_TICKS_PER_MOVE,
+            (false, true) => SLOW_TICKS_PER_MOVE,
+            _ => NORMAL_TICKS_PER_MOVE,
+        };
+        if self.is_boosting() {
+            (ticks_per_move / 2).max(1)
+        } else {
+            ticks_per_move
+        }
+    }
+    fn is_boosting(&self) -> bool {
+        self.boost_held && self.body_segments.len() >= BOOST_MIN_LENGTH
+    }
+    /// Boosting costs length, the tail is left behind as a pellet
+    fn spend_boost(&mut self) {
+        if !self.is_boosting() {
+            return;
+        }
+        self.boost_moves += 1;
+        if !self.boost_moves.is_multiple_of(BOOST_SHED_MOVES) {
+            return;
+        }
+        // The tail end is at the back of `body_segments`
+        let Some(tail) = self.body_segments.pop() else {
+            return;
+        };
+        let position = PositionCoords::new(tail.x, tail.y);
+        self.send_to_server("boost_drop", &position);
     }
     fn is_body_segment(&self, x: HNum, y: HNum) -> bool {
         self.body_segments.iter().any(|segment| segment.x == x && segment.y == y)
     }
-    fn is_other_player_segment(&self, x: HNum, y: HNum) -> bool {
-        self.player_states.iter().any(|player_state| {
-            player_state.body_segments.iter().any(|segment| segment.x == x && segment.y == y)
-        })
+    fn player_name(&self, id: u64, name: &str) -> String {
+        if id == self.network_id {
+            "You".to_string()
+        } else if name.is_empty() {
+            format!("Player {}", id)
+        } else {
+            name.to_string()
+        }
+    }
+    fn record_kill(&mut self, kill: &Kill) {
+        if kill.attacker_id == self.network_id && kill.victim_id != self.network_id {
+            self.kills += 1;
+        }
+        let victim = self.player_name(kill.victim_id, &kill.victim_name);
+        let text = if kill.attacker_id == kill.victim_id {
+            format!("{} crashed", victim)
+        } else {
+            format!("{} cut off {}", self.player_name(kill.attacker_id, &kill.attacker_name), victim)
+        };
+        self.push_feed(text);
+    }
+    fn push_feed(&mut self, text: String) {
+        self.kill_feed.push(KillFeedEntry { text, ticks_left: KILL_FEED_TICKS });
+        if self.kill_feed.len() > KILL_FEED_LENGTH {
+            self.kill_feed.remove(0);
+        }
+    }
+    /// Count down the kill feed entries, returns true if any entry expired
+    fn age_kill_feed(&mut self) -> bool {
+        let length = self.kill_feed.len();
+        self.kill_feed.iter_mut().for_each(|entry| entry.ticks_left = entry.ticks_left.saturating_sub(1));
+        self.kill_feed.retain(|entry| entry.ticks_left > 0);
+        self.kill_feed.len() != length
+    }
+    fn respawn_seconds_left(&self) -> u64 {
+        (self.respawn_ticks * TICK_TIME).div_ceil(1000)
     }
-}
-
-fn is_boundary(x: HNum, y: HNum) -> bool {
-    x < BOUNDARY_THICKNESS || x >= GRID_WIDTH + BOUNDARY_THICKNESS || y < BOUNDARY_THICKNESS || y >= GRID_HEIGHT + BOUNDARY_THICKNESS
 }
 
 impl Component for GameGridComponent {
@@ -394,21 +871,53 @@ impl Component for GameGridComponent {
     fn create(ctx: &Context<Self>) -> Self {
         let game_tick = start_game_tick(TICK_TIME);
         ctx.link().send_stream(game_tick.map(Msg::GameTicked));
-        let spawn_position = get_random_position();
+        let map = MapLayout::default();
+        let spawn_position = get_safe_spawn_position(&map, &[], &[]);
 
         let mut component = Self {
             x: spawn_position.x,
             y: spawn_position.y,
-            current_direction: Direction::RIGHT,
-            pending_body_segment: false,
+            current_direction: get_open_direction(&map, &spawn_position, &[]),
+            pending_growth: 0,
             score: 0,
+            kills: 0,
+            kill_feed: Vec::new(),
             paused: false,
             dead: false,
+            respawn_ticks: 0,
+            spawn_protection_ticks: SPAWN_PROTECTION_TICKS,
             food_positions: vec![],
+            power_ups: vec![],
+            effects: vec![],
+            move_ticks: 0,
+            boost_held: false,
+            boost_moves: 0,
+            zone: None,
+            next_zone: None,
             body_segments: vec![],
             ws: Some(ctx.props().ws.clone()), // Store the WebSocket from props
             network_id: 0,
             player_states: Vec::new(),
+            joined: false,
+            name: String::new(),
+            colour: PLAYER_COLOURS.choose(&mut rand::thread_rng()).unwrap_or(&PLAYER_COLOURS[0]).to_string(),
+            join_error: None,
+            leaderboard_period: LeaderboardPeriod::Daily,
+            leaderboard: None,
+            rooms: Vec::new(),
+            maps: Vec::new(),
+            room: None,
+            lobby_error: None,
+            match_info: None,
+            team: None,
+            spectating: false,
+            solo: None,
+            level: None,
+            map,
+            editing: false,
+            editor_map: None,
+            debug_stats: DebugStats::default(),
+            ticks_since_sync: 0,
         };
 
         component.connect_to_server(ctx);
@@ -417,30 +926,24 @@ impl Component for GameGridComponent {
     fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             Msg::GameTicked(_) => {
-                if self.paused {
-                    return false; // don't re-render if paused
-                }
-                if self.is_game_over() {
-                    self.update_pause(true);
-                    self.dead = true;
-                } else {
-                    self.handle_tick();
-                }
-                if self.is_food_eaten() {
-                    //self.respawn_food();
-                    /*self.body_segments.push(BodySegment {
-                        x: self.x,
-                        y: self.y,
-                        direction: self.current_direction.clone(),
-                    });*/
-                    self.pending_body_segment = true;
-                    self.increment_score();
+                self.sync_clock();
+                let mut changed = false;
+                for _ in 0..clock::ticks_due(self.solo.is_none()) {
+                    changed |= self.game_tick();
                 }
+                return changed;
             }
             Msg::HandleKeyboardEvent(event) => {
                 self.handle_keydown(event);
             }
+            Msg::HandleKeyUp(event) => {
+                self.handle_keyup(event);
+                return false;
+            }
             Msg::RestartGame(_) => {
+                if self.respawn_ticks > 0 {
+                    return false;
+                }
                 self.restart();
             }
             Msg::HandlePause(_) => {
@@ -450,10 +953,149 @@ impl Component for GameGridComponent {
                 self.network_id = id;
             }
             Msg::UpdatePlayerStates(states) => {
+                self.debug_stats.player_states_received();
                 self.player_states = states;
             }
             Msg::UpdateMapState(map_state) => {
+                self.debug_stats.map_state_received();
                 self.food_positions = map_state.food_positions;
+                self.power_ups = map_state.power_ups;
+                self.zone = map_state.zone;
+                self.next_zone = map_state.next_zone;
+                self.map = map_state.layout;
+            }
+            Msg::PlayerDied(kill) => {
+                self.record_kill(&kill);
+                let id = kill.victim_id;
+                if id == self.network_id {
+                    // The server can also declare us dead, e.g. after an early respawn
+                    if !self.dead {
+                        self.enter_dead_state();
+                    }
+                    // Our score has been recorded by now
+                    self.fetch_leaderboard(self.leaderboard_period);
+                } else {
+                    self.player_states.retain(|player_state| player_state.player_id != id);
+                }
+            }
+            Msg::PlayerRespawned(player_state) => {
+                if player_state.player_id != self.network_id {
+                    self.player_states.retain(|state| state.player_id != player_state.player_id);
+                    self.player_states.push(player_state);
+                }
+            }
+            Msg::PlayerStateRejected(player_state) => {
+                self.resync(player_state);
+            }
+            Msg::PlayerDespawned(id) => {
+                self.player_states.retain(|player_state| player_state.player_id != id);
+            }
+            Msg::UpdateName(name) => {
+                self.name = name;
+                self.join_error = None;
+            }
+            Msg::SelectColour(colour) => {
+                self.colour = colour;
+            }
+            Msg::Join(_) => {
+                self.join();
+            }
+            Msg::JoinAccepted(profile) => {
+                self.name = profile.name;
+                self.colour = profile.colour;
+                self.joined = true;
+                self.join_error = None;
+                self.list_rooms();
+            }
+            Msg::JoinRejected(reason) => {
+                self.join_error = Some(reason);
+            }
+            Msg::ShowLeaderboard(period) => {
+                self.fetch_leaderboard(period);
+            }
+            Msg::UpdateLeaderboard(leaderboard) => {
+                self.leaderboard = Some(leaderboard);
+            }
+            Msg::ListRooms(_) => {
+                self.list_rooms();
+            }
+            Msg::UpdateRoomList(rooms) => {
+                self.rooms = rooms;
+            }
+            Msg::UpdateMapList(maps) => {
+                self.maps = maps;
+            }
+            Msg::CreateRoom(settings) => {
+                self.send_to_server("create_room", &settings);
+            }
+            Msg::JoinRoom(request) => {
+                self.send_to_server("join_room", &request);
+            }
+            Msg::RoomJoined(room) => {
+                self.enter_room(room);
+            }
+            Msg::RoomError(error) => {
+                self.lobby_error = Some(error);
+            }
+            Msg::LeaveRoom(_) => {
+                self.leave_room();
+            }
+            Msg::UpdateMatchState(match_info) => {
+                // The round is over for everyone, survivors included
+                if match_info.phase == MatchPhase::Finished {
+                    self.spectating = true;
+                }
+                self.match_info = Some(match_info);
+            }
+            Msg::MatchStarted(spawns) => {
+                self.start_match(spawns);
+            }
+            Msg::SetReady(ready) => {
+                self.set_ready(ready);
+            }
+            Msg::StartSolo(mode) => {
+                self.start_solo(mode);
+            }
+            Msg::LeaveSolo(_) => {
+                self.leave_solo();
+            }
+            Msg::OpenEditor(_) => {
+                self.editing = true;
+            }
+            Msg::CloseEditor(_) => {
+                self.editing = false;
+            }
+            Msg::TestPlayMap(layout) => {
+                self.editing = false;
+                self.editor_map = Some(layout);
+                self.start_solo(SoloMode::TestPlay);
+            }
+            Msg::TeamAssigned(team) => {
+                self.team = team;
+            }
+            Msg::SetTeam(team) => {
+                self.set_team(team);
+            }
+            Msg::PowerUpPicked(pickup) => {
+                let name = self.player_states.iter()
+                    .find(|player_state| player_state.player_id == pickup.player_id)
+                    .map_or(String::new(), |player_state| player_state.name.clone());
+                let text = format!("{} picked up {}", self.player_name(pickup.player_id, &name), render::power_up_icon(pickup.kind));
+                self.push_feed(text);
+            }
+            Msg::EffectStarted(effect_change) => {
+                if effect_change.player_id == self.network_id && !self.dead && !self.spectating {
+                    self.start_effect(effect_change.kind, effect_change.duration_ms);
+                }
+            }
+            Msg::EffectExpired(effect_change) => {
+                if effect_change.player_id == self.network_id {
+                    self.effects.retain(|effect| effect.kind != effect_change.kind);
+                }
+            }
+            Msg::Pong(pong) => {
+                self.debug_stats.round_trip_measured(clock::pong_received(&pong));
+                return self.debug_stats.visible;
             }
         }
         true
@@ -462,55 +1104,79 @@ impl Component for GameGridComponent {
         let handle_keydown = ctx.link().callback(|e: KeyboardEvent| {
             Msg::HandleKeyboardEvent(e)
         });
+        let handle_keyup = ctx.link().callback(|e: KeyboardEvent| {
+            Msg::HandleKeyUp(e)
+        });
         let handle_restart = ctx.link().callback(|_| {
             Msg::RestartGame(())
         });
+        if self.editing {
+            return html! {
+                <div class={classes!("app-ctn")}>
+                    <MapEditorComponent
+                        initial={self.editor_map.clone()}
+                        on_play={ctx.link().callback(Msg::TestPlayMap)}
+                        on_close={ctx.link().callback(Msg::CloseEditor)}
+                    />
+                </div>
+            };
+        }
 
         html!(
-            <div tabIndex="0" onkeydown={handle_keydown} class={classes!("app-ctn")}>
-                { for (0..GRID_HEIGHT + GRID_OFFSET).map(|row| {
+            <div tabIndex="0" onkeydown={handle_keydown} onkeyup={handle_keyup} class={classes!("app-ctn")}>
+                { self.view_grid() }
+                { self.view_room_bar(ctx) }
+                { self.view_effects() }
+                {if self.room.is_some() {
+                    self.view_scoreboard()
+                } else {
+                    html! {}
+                }}
+                { self.debug_stats.view(self.network_id) }
+                <div class={classes!("kill-feed")}>
+                    { for self.kill_feed.iter().map(|entry| html! {
+                        <div class={classes!("kill-feed__entry")}>{ &entry.text }</div>
+                    })}
+                </div>
+                {if !self.joined {
+                    self.view_join_screen(ctx)
+                } else if let Some(ref run) = self.solo {
+                    if run.finished {
+                        self.view_solo_results(ctx, run)
+                    } else {
+                        html! {}
+                    }
+                } else if self.room.is_none() {
                     html! {
-                        <div class="row" key={row}>
-                            { for (0..GRID_WIDTH + GRID_OFFSET).map(|column| {
-                                html! {
-                                    <div key={column} class={classes!(
-                                        "cell",
-                                        if self.x == column && self.y == row {
-                                            "cell--active"
-                                        } else {
-                                            ""
-                                        },
-                                        if is_boundary(column, row) {
-                                            "cell--boundary"
-                                        } else {
-                                            ""
-                                        },
-                                        if self.is_food_coordinate(column, row) {
-                                            "cell--food"
-                                        } else {
-                                            ""
-                                        },
-                                        if self.is_body_segment(column, row) {
-                                            "cell--body"
-                                        } else {
-                                            ""
-                                        },
-                                        if self.is_other_player_segment(column, row) {
-                                            "cell--other--player"
-                                        } else {
-                                            ""
-                                        },
-                                    )}/>
-                                }
-                            })}
-                        </div>
+                        <LobbyComponent
+                            rooms={self.rooms.clone()}
+                            maps={self.maps.clone()}
+                            error={self.lobby_error.clone()}
+                            on_refresh={ctx.link().callback(Msg::ListRooms)}
+                            on_create={ctx.link().callback(Msg::CreateRoom)}
+                            on_join={ctx.link().callback(Msg::JoinRoom)}
+                            on_solo={ctx.link().callback(Msg::StartSolo)}
+                            on_editor={ctx.link().callback(Msg::OpenEditor)}
+                        />
                     }
-                })}
-                {if self.dead {
+                } else if self.spectating {
+                    self.view_match_overlay(ctx)
+                } else if self.dead {
                     html! {
                         <div class={classes!("game-over-ctn")}>
                             <h1 class={classes!("game-over")}>{ "Game Over" }</h1>
-                            <button class={classes!("btn")} onclick={handle_restart}>{ "Restart" }</button>
+                            {if self.respawn_ticks > 0 {
+                                html! {
+                                    <button class={classes!("btn")} disabled=true>
+                                        { format!("Respawn in {}", self.respawn_seconds_left()) }
+                                    </button>
+                                }
+                            } else {
+                                html! {
+                                    <button class={classes!("btn")} onclick={handle_restart}>{ "Respawn" }</button>
+                                }
+                            }}
+                            { self.view_leaderboard(ctx) }
                         </div>
                     }
                 } else {html!{<div></div>}}}
@@ -529,7 +1195,9 @@ impl Component for GameGridComponent {
                             </div>
                         </a>
                     </div>
-                
+                if cfg!(debug_assertions) {
+                    <ConditionerPanel />
+                }
          
             </div>
         )
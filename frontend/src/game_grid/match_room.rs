@@ -0,0 +1,175 @@
+//! Match rooms: teams, ready checks, rounds and the shrinking zone.
+
+use yew::{html, Context, Html, classes};
+use crate::lobby::RoomMode;
+use super::{GameGridComponent, Msg, HNum, PlayerStateData, MatchInfo, MatchPhase, MatchSpawn, TEAM_COLOURS, TEAM_NAMES};
+
+impl GameGridComponent {
+    pub(super) fn is_match_room(&self) -> bool {
+        self.room.as_ref().is_some_and(|room| room.settings.mode != RoomMode::Endless)
+    }
+    /// Cells outside the zone are as deadly as the boundary
+    pub(super) fn is_outside_zone(&self, x: HNum, y: HNum) -> bool {
+        self.zone.is_some_and(|zone| !zone.contains(x, y))
+    }
+    /// The ring of cells the zone closes over next
+    pub(super) fn is_zone_warning(&self, x: HNum, y: HNum) -> bool {
+        !self.is_outside_zone(x, y) && self.next_zone.is_some_and(|next_zone| !next_zone.contains(x, y))
+    }
+    pub(super) fn team_count(&self) -> u64 {
+        self.room.as_ref().map_or(0, |room| room.settings.team_count)
+    }
+    pub(super) fn is_teammate(&self, player_state: &PlayerStateData) -> bool {
+        self.team.is_some() && player_state.team == self.team
+    }
+    pub(super) fn set_team(&self, team: u64) {
+        self.send_to_server("set_team", &team);
+    }
+    /// Teams can only be switched between match rounds
+    fn can_switch_team(&self) -> bool {
+        self.team_count() > 0
+            && self.match_info.as_ref().is_none_or(|match_info| {
+                matches!(match_info.phase, MatchPhase::Waiting | MatchPhase::Finished)
+            })
+    }
+    fn is_ready(&self) -> bool {
+        self.match_info.as_ref().is_some_and(|match_info| match_info.ready_ids.contains(&self.network_id))
+    }
+    pub(super) fn set_ready(&self, ready: bool) {
+        self.send_to_server("set_ready", &ready);
+    }
+    /// Every snake in the room starts the round at the same time
+    pub(super) fn start_match(&mut self, spawns: Vec<MatchSpawn>) {
+        let Some(spawn) = spawns.into_iter().find(|spawn| spawn.player_id == self.network_id) else {
+            return;
+        };
+        self.x = spawn.x;
+        self.y = spawn.y;
+        self.current_direction = spawn.direction;
+        self.score = 0;
+        self.paused = false;
+        self.dead = false;
+        self.spectating = false;
+        self.pending_growth = 0;
+        self.spawn_protection_ticks = 0;
+        self.effects.clear();
+        self.body_segments.clear();
+    }
+    pub(super) fn view_team_picker(&self, ctx: &Context<Self>) -> Html {
+        if !self.can_switch_team() {
+            return match self.team {
+                Some(team) => html! { <span>{ format!("Team {}", team_name(team)) }</span> },
+                None => html! {},
+            };
+        }
+        html! {
+            <span>
+                { for (0..self.team_count()).map(|team| {
+                    let handle_click = ctx.link().callback(move |_| Msg::SetTeam(team));
+                    html! {
+                        <button
+                            class={classes!("leaderboard__tab", if self.team == Some(team) { "leaderboard__tab--selected" } else { "" })}
+                            style={format!("color: {}", TEAM_COLOURS[team as usize])}
+                            onclick={handle_click}
+                        >
+                            { team_name(team) }
+                        </button>
+                    }
+                })}
+            </span>
+        }
+    }
+    /// Who is on which team while waiting for a round
+    fn view_team_roster(&self, match_info: &MatchInfo) -> Html {
+        if self.team_count() == 0 {
+            return html! {};
+        }
+        html! {
+            <ul class={classes!("leaderboard__entries", "match-text")}>
+                { for (0..self.team_count()).map(|team| {
+                    let names: Vec<&str> = match_info.players.iter()
+                        .filter(|player| player.team == Some(team))
+                        .map(|player| player.name.as_str())
+                        .collect();
+                    html! {
+                        <li style={format!("color: {}", TEAM_COLOURS[team as usize])}>
+                            { format!("{}: {}", team_name(team), names.join(", ")) }
+                        </li>
+                    }
+                })}
+            </ul>
+        }
+    }
+    pub(super) fn view_match_overlay(&self, ctx: &Context<Self>) -> Html {
+        let Some(ref match_info) = self.match_info else {
+            return html! {};
+        };
+        let ready = self.is_ready();
+        let handle_ready = ctx.link().callback(move |_| Msg::SetReady(!ready));
+        let ready_button = html! {
+            <button class={classes!("btn")} onclick={handle_ready}>
+                { if ready { "Not ready" } else { "Ready" } }
+            </button>
+        };
+        let ready_count = format!("{}/{} ready", match_info.ready_ids.len(), match_info.player_count);
+
+        match match_info.phase {
+            MatchPhase::Waiting => html! {
+                <div class={classes!("game-over-ctn")}>
+                    <h1 class={classes!("match-title")}>{ "Waiting for players" }</h1>
+                    { self.view_team_roster(match_info) }
+                    <p class={classes!("match-text")}>{ ready_count }</p>
+                    { ready_button }
+                </div>
+            },
+            MatchPhase::Countdown => html! {
+                <div class={classes!("game-over-ctn")}>
+                    <h1 class={classes!("match-countdown")}>{ match_info.seconds_left }</h1>
+                </div>
+            },
+            MatchPhase::Playing => html! {
+                <div class={classes!("game-over-ctn")}>
+                    <h1 class={classes!("match-title")}>{ "Spectating" }</h1>
+                    <p class={classes!("match-text")}>{ "You will play in the next round" }</p>
+                </div>
+            },
+            MatchPhase::Finished => html! {
+                <div class={classes!("game-over-ctn")}>
+                    <h1 class={classes!("match-title")}>{ "Results" }</h1>
+                    <ol class={classes!("leaderboard__entries", "match-text")}>
+                        { for match_info.team_results.iter().map(|team_result| html! {
+                            <li class={classes!(if Some(team_result.team) == self.team { "match-result--own" } else { "" })}>
+                                { format!(
+                                    "{} - {} survived, {} kills",
+                                    team_name(team_result.team),
+                                    team_result.survivors,
+                                    team_result.kills,
+                                ) }
+                            </li>
+                        })}
+                    </ol>
+                    <ol class={classes!("leaderboard__entries", "match-text")}>
+                        { for match_info.results.iter().map(|result| html! {
+                            <li class={classes!(if result.player_id == self.network_id { "match-result--own" } else { "" })}>
+                                { format!(
+                                    "{}{} - {} kills",
+                                    result.name,
+                                    if result.survived { " (survived)" } else { "" },
+                                    result.kills,
+                                ) }
+                            </li>
+                        })}
+                    </ol>
+                    <p class={classes!("match-text")}>{ ready_count }</p>
+                    <button class={classes!("btn")} onclick={ctx.link().callback(|_| Msg::SetReady(true))} disabled={ready}>
+                        { if ready { "Waiting for rematch" } else { "Rematch" } }
+                    </button>
+                </div>
+            },
+        }
+    }
+}
+
+pub(super) fn team_name(team: u64) -> &'static str {
+    TEAM_NAMES.get(team as usize).copied().unwrap_or("?")
+}
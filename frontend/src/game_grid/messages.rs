@@ -0,0 +1,198 @@
+//! The messages exchanged with the server: sending inputs and passing what
+//! the server sends on to the component.
+
+use serde::Serialize;
+use wasm_bindgen::JsValue;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::closure::Closure;
+use web_sys::{MessageEvent, WebSocket};
+use yew::Context;
+use yew::html::Scope;
+use craby_snake_shared::protocol::{Pong, ServerMessage};
+use crate::lobby::RoomInfo;
+use crate::clock;
+use crate::conditioner;
+use crate::debug_overlay;
+use super::{
+    GameGridComponent, Msg, PlayerStateData, MapState, Kill, PlayerProfile, MatchInfo, MatchSpawn,
+    PowerUpPickup, EffectChange, Leaderboard,
+};
+
+/// Send `payload` as the JSON message of a `message_type` message, stamped
+/// with the tick the input was made on
+fn send_message<T: Serialize + ?Sized>(ws: &WebSocket, message_type: &str, player_id: u64, payload: &T) -> Result<(), JsValue> {
+    let server_message = ServerMessage {
+        tick: clock::input_tick(),
+        ..ServerMessage::new(message_type, player_id, payload).map_err(|e| JsValue::from_str(&e.to_string()))?
+    };
+
+    let message = serde_json::to_string(&server_message).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    debug_overlay::count_sent(&message);
+    conditioner::send(ws, &message)
+        .map_err(|e| JsValue::from_str(&format!("WebSocket send error: {:?}", e)))?;
+    Ok(())
+}
+
+/// Pass a message from the server on to the component as a `Msg`
+fn handle_server_message(link: &Scope<GameGridComponent>, message: &str) {
+    match serde_json::from_str::<ServerMessage>(message) {
+        Ok(server_message) => {
+            match server_message.message_type.as_str() {
+                "assign_id" => {
+                    // Send message to update component's network_id
+                    link.send_message(Msg::UpdateNetworkId(server_message.player_id));
+                },
+                "player_states" => {
+                    if !clock::accept_player_states(server_message.tick) {
+                        return;
+                    }
+                    if let Ok(player_states) = serde_json::from_str::<Vec<PlayerStateData>>(&server_message.message) {
+                        // Send message to update component's player states
+                        link.send_message(Msg::UpdatePlayerStates(player_states));
+                    }
+                },
+                "map_state" => {
+                    if !clock::accept_map_state(server_message.tick) {
+                        return;
+                    }
+                    if let Ok(map_state) = serde_json::from_str::<MapState>(&server_message.message) {
+                        // Send message to update component's player states
+                        link.send_message(Msg::UpdateMapState(map_state));
+                    }
+                },
+                "player_died" => {
+                    let kill = serde_json::from_str::<Kill>(&server_message.message).unwrap_or(Kill {
+                        attacker_id: server_message.player_id,
+                        victim_id: server_message.player_id,
+                        attacker_name: String::new(),
+                        victim_name: String::new(),
+                    });
+                    link.send_message(Msg::PlayerDied(kill));
+                },
+                "player_respawned" => {
+                    if let Ok(player_state) = serde_json::from_str::<PlayerStateData>(&server_message.message) {
+                        link.send_message(Msg::PlayerRespawned(player_state));
+                    }
+                },
+                "player_state_rejected" => {
+                    if let Ok(player_state) = serde_json::from_str::<PlayerStateData>(&server_message.message) {
+                        link.send_message(Msg::PlayerStateRejected(player_state));
+                    }
+                },
+                "player_despawned" => {
+                    link.send_message(Msg::PlayerDespawned(server_message.player_id));
+                },
+                "join_accepted" => {
+                    if let Ok(profile) = serde_json::from_str::<PlayerProfile>(&server_message.message) {
+                        link.send_message(Msg::JoinAccepted(profile));
+                    }
+                },
+                "join_rejected" => {
+                    link.send_message(Msg::JoinRejected(server_message.message));
+                },
+                "room_list" => {
+                    if let Ok(rooms) = serde_json::from_str::<Vec<RoomInfo>>(&server_message.message) {
+                        link.send_message(Msg::UpdateRoomList(rooms));
+                    }
+                },
+                "map_list" => {
+                    if let Ok(maps) = serde_json::from_str::<Vec<String>>(&server_message.message) {
+                        link.send_message(Msg::UpdateMapList(maps));
+                    }
+                },
+                "room_joined" => {
+                    if let Ok(room) = serde_json::from_str::<RoomInfo>(&server_message.message) {
+                        link.send_message(Msg::RoomJoined(room));
+                    }
+                },
+                "room_error" => {
+                    link.send_message(Msg::RoomError(server_message.message));
+                },
+                "match_state" => {
+                    if let Ok(match_info) = serde_json::from_str::<MatchInfo>(&server_message.message) {
+                        link.send_message(Msg::UpdateMatchState(match_info));
+                    }
+                },
+                "power_up_picked" => {
+                    if let Ok(pickup) = serde_json::from_str::<PowerUpPickup>(&server_message.message) {
+                        link.send_message(Msg::PowerUpPicked(pickup));
+                    }
+                },
+                "effect_started" => {
+                    if let Ok(effect_change) = serde_json::from_str::<EffectChange>(&server_message.message) {
+                        link.send_message(Msg::EffectStarted(effect_change));
+                    }
+                },
+                "effect_expired" => {
+                    if let Ok(effect_change) = serde_json::from_str::<EffectChange>(&server_message.message) {
+                        link.send_message(Msg::EffectExpired(effect_change));
+                    }
+                },
+                "team_assigned" => {
+                    if let Ok(team) = serde_json::from_str::<Option<u64>>(&server_message.message) {
+                        link.send_message(Msg::TeamAssigned(team));
+                    }
+                },
+                "match_started" => {
+                    if let Ok(spawns) = serde_json::from_str::<Vec<MatchSpawn>>(&server_message.message) {
+                        link.send_message(Msg::MatchStarted(spawns));
+                    }
+                },
+                "pong" => {
+                    if let Ok(pong) = serde_json::from_str::<Pong>(&server_message.message) {
+                        link.send_message(Msg::Pong(pong));
+                    }
+                },
+                "leaderboard" => {
+                    if let Ok(leaderboard) = serde_json::from_str::<Leaderboard>(&server_message.message) {
+                        link.send_message(Msg::UpdateLeaderboard(leaderboard));
+                    }
+                },
+                _ => {}
+            }
+        }
+        Err(e) => {
+            log::error!("Failed to deserialize ServerMessage: {}", e);
+        }
+    }
+}
+
+impl GameGridComponent {
+    pub(super) fn connect_to_server(&mut self, ctx: &Context<Self>) {
+        if let Some(ref ws_wrapper) = self.ws {
+            let ws_clone = ws_wrapper.clone();
+            let link = ctx.link().clone();
+    
+            let on_message: Closure<dyn FnMut(MessageEvent)> = Closure::wrap(Box::new(move |event: web_sys::MessageEvent| {
+                if let Some(message) = event.data().as_string() {
+                    debug_overlay::count_received(&message);
+                    let link = link.clone();
+                    conditioner::receive(message, move |message| handle_server_message(&link, &message));
+                }
+            }) as Box<dyn FnMut(web_sys::MessageEvent)>);
+    
+            ws_clone.lock().unwrap().set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+            on_message.forget();
+        }
+    }
+    /// Send a message through the WebSocket if an id has been
+    /// assigned by the server
+    pub(super) fn send_to_server<T: Serialize + ?Sized>(&self, message_type: &str, payload: &T) {
+        // Solo runs are played offline
+        if self.network_id == 0 || self.solo.is_some() {
+            return;
+        }
+        if let Some(ref ws_wrapper) = &self.ws {
+            // Now safely lock the WebSocketWrapper
+            if let Ok(ws) = ws_wrapper.0.lock() {
+                if send_message(&ws, message_type, self.network_id, payload).is_err() {
+                    log::error!("Failed to send message through WebSocket");
+                }
+            } else {
+                log::error!("Failed to lock WebSocket");
+            }
+        } else {
+            log::error!("WebSocket is not initialized");
+        }
+    }
+}
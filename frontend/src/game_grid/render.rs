@@ -0,0 +1,353 @@
+//! Drawing the grid and the panels around it.
+
+use yew::{html, Context, Html, classes, InputEvent, TargetCast};
+use web_sys::HtmlInputElement;
+use super::{
+    GameGridComponent, Msg, HNum, PlayerStateData, PowerUpKind, FoodKind, LeaderboardPeriod, MatchPhase,
+    GRID_OFFSET, TICK_TIME, MAX_NAME_LENGTH, PLAYER_COLOURS, TEAM_COLOURS,
+};
+use super::match_room::team_name;
+
+impl GameGridComponent {
+    /// Teammates' bodies are left out when friendly fire is off, as
+    /// they can be passed through
+    fn is_other_player_segment(&self, x: HNum, y: HNum) -> bool {
+        self.other_player_at(x, y).is_some_and(|player_state| !self.is_passable(player_state))
+    }
+    /// Boosting snakes leave a glowing trail
+    fn is_boosting_segment(&self, x: HNum, y: HNum) -> bool {
+        if (self.x == x && self.y == y) || self.is_body_segment(x, y) {
+            return self.is_boosting();
+        }
+        self.other_player_at(x, y).is_some_and(|player_state| player_state.boosting)
+    }
+    fn is_passable_segment(&self, x: HNum, y: HNum) -> bool {
+        self.other_player_at(x, y).is_some_and(|player_state| self.is_passable(player_state))
+    }
+    fn is_passable(&self, player_state: &PlayerStateData) -> bool {
+        let teammate = self.is_teammate(player_state)
+            && !self.room.as_ref().is_some_and(|room| room.settings.friendly_fire);
+        teammate || self.has_effect(PowerUpKind::Ghost) || player_state.effects.contains(&PowerUpKind::Ghost)
+    }
+    fn other_player_at(&self, x: HNum, y: HNum) -> Option<&PlayerStateData> {
+        self.player_states.iter().find(|player_state| {
+            player_state.body_segments.iter().any(|segment| segment.x == x && segment.y == y)
+        })
+    }
+    fn is_head(&self, x: HNum, y: HNum) -> bool {
+        (self.x == x && self.y == y)
+            || self.player_states.iter().any(|player_state| {
+                player_state.body_segments.last().is_some_and(|head| head.x == x && head.y == y)
+            })
+    }
+    fn is_protected_player_segment(&self, x: HNum, y: HNum) -> bool {
+        self.player_states.iter().filter(|player_state| player_state.invulnerable).any(|player_state| {
+            player_state.body_segments.iter().any(|segment| segment.x == x && segment.y == y)
+        })
+    }
+    /// Colour the cell in the colour of the snake occupying it, or in
+    /// the colour of its team
+    fn cell_style(&self, x: HNum, y: HNum) -> Option<String> {
+        let colour = if self.joined && ((self.x == x && self.y == y) || self.is_body_segment(x, y)) {
+            Some(snake_colour(&self.colour, self.team))
+        } else {
+            self.other_player_at(x, y).map(|player_state| snake_colour(&player_state.colour, player_state.team))
+        };
+        colour.filter(|colour| !colour.is_empty()).map(|colour| format!("background-color: {}", colour))
+    }
+    /// The snake's name over its head, followed by its effect icons
+    fn view_name_label(&self, x: HNum, y: HNum) -> Html {
+        let label = if self.joined && self.x == x && self.y == y {
+            Some((&self.name, self.effects.iter().map(|effect| effect.kind).collect::<Vec<_>>()))
+        } else {
+            self.player_states.iter()
+                .find(|player_state| player_state.body_segments.last().is_some_and(|head| head.x == x && head.y == y))
+                .map(|player_state| (&player_state.name, player_state.effects.clone()))
+        };
+        match label {
+            Some((name, effects)) if !name.is_empty() => html! {
+                <span class={classes!("name-label")}>
+                    { name }
+                    { for effects.iter().map(|kind| power_up_icon(*kind)) }
+                </span>
+            },
+            _ => html! {},
+        }
+    }
+    fn view_power_up(&self, x: HNum, y: HNum) -> Html {
+        match self.power_up_at(x, y) {
+            Some(power_up) => html! {
+                <span class={classes!("power-up-icon")}>{ power_up_icon(power_up.kind) }</span>
+            },
+            None => html! {},
+        }
+    }
+    /// Own effects with the seconds they have left
+    pub(super) fn view_effects(&self) -> Html {
+        if self.effects.is_empty() {
+            return html! {};
+        }
+        html! {
+            <div class={classes!("effect-bar")}>
+                { for self.effects.iter().map(|effect| html! {
+                    <span>{ format!("{} {}s", power_up_icon(effect.kind), (effect.ticks_left * TICK_TIME).div_ceil(1000)) }</span>
+                })}
+            </div>
+        }
+    }
+    pub(super) fn view_join_screen(&self, ctx: &Context<Self>) -> Html {
+        let handle_name_input = ctx.link().callback(|e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            Msg::UpdateName(input.value())
+        });
+        let handle_join = ctx.link().callback(|_| {
+            Msg::Join(())
+        });
+
+        html! {
+            <div class={classes!("join-ctn")}>
+                <h1 class={classes!("join-title")}>{ "Join game" }</h1>
+                <input
+                    class={classes!("join-input")}
+                    placeholder="Nickname"
+                    maxlength={MAX_NAME_LENGTH.to_string()}
+                    value={self.name.clone()}
+                    oninput={handle_name_input}
+                />
+                <div class={classes!("colour-picker")}>
+                    { for PLAYER_COLOURS.iter().map(|colour| {
+                        let selected = self.colour == *colour;
+                        let handle_select = ctx.link().callback(move |_| Msg::SelectColour(colour.to_string()));
+                        html! {
+                            <button
+                                class={classes!("colour-swatch", if selected { "colour-swatch--selected" } else { "" })}
+                                style={format!("background-color: {}", colour)}
+                                onclick={handle_select}
+                            />
+                        }
+                    })}
+                </div>
+                {if let Some(ref error) = self.join_error {
+                    html! { <p class={classes!("join-error")}>{ error }</p> }
+                } else {
+                    html! {}
+                }}
+                <button class={classes!("btn")} onclick={handle_join} disabled={self.network_id == 0}>{ "Play" }</button>
+            </div>
+        }
+    }
+    pub(super) fn view_room_bar(&self, ctx: &Context<Self>) -> Html {
+        if let Some(ref run) = self.solo {
+            return self.view_solo_bar(ctx, run);
+        }
+        let Some(ref room) = self.room else {
+            return html! {};
+        };
+        let handle_leave = ctx.link().callback(|_| {
+            Msg::LeaveRoom(())
+        });
+        html! {
+            <div class={classes!("room-bar")}>
+                <span>{ format!("{} - code {}", room.settings.name, room.code) }</span>
+                {match self.match_info {
+                    Some(ref match_info) if match_info.phase == MatchPhase::Playing => html! {
+                        <span>{ format!("{}:{:02}", match_info.seconds_left / 60, match_info.seconds_left % 60) }</span>
+                    },
+                    _ => html! {},
+                }}
+                { self.view_team_picker(ctx) }
+                <button class={classes!("leaderboard__tab")} onclick={handle_leave}>{ "Leave room" }</button>
+            </div>
+        }
+    }
+    pub(super) fn view_scoreboard(&self) -> Html {
+        // (name, team, length, score, kills)
+        let mut rows: Vec<(String, Option<u64>, usize, u64, u64)> = self.player_states.iter()
+            .map(|player_state| (
+                self.player_name(player_state.player_id, &player_state.name),
+                player_state.team,
+                player_state.body_segments.len(),
+                player_state.score,
+                player_state.kills,
+            ))
+            .collect();
+        if !self.dead {
+            rows.push((self.name.clone(), self.team, self.body_segments.len() + 1, self.score, self.kills));
+        }
+        rows.sort_by(|a, b| b.3.cmp(&a.3).then(b.2.cmp(&a.2)));
+
+        // Team totals of (length, score, kills)
+        let mut team_totals: Vec<(u64, usize, u64, u64)> = (0..self.team_count())
+            .map(|team| {
+                rows.iter()
+                    .filter(|row| row.1 == Some(team))
+                    .fold((team, 0, 0, 0), |total, row| (team, total.1 + row.2, total.2 + row.3, total.3 + row.4))
+            })
+            .collect();
+        team_totals.sort_by(|a, b| b.2.cmp(&a.2).then(b.3.cmp(&a.3)));
+
+        html! {
+            <table class={classes!("scoreboard")}>
+                <tr>
+                    <th>{ "Player" }</th>
+                    <th>{ "Length" }</th>
+                    <th>{ "Score" }</th>
+                    <th>{ "Kills" }</th>
+                </tr>
+                { for team_totals.into_iter().map(|(team, length, score, kills)| html! {
+                    <tr style={format!("color: {}", TEAM_COLOURS[team as usize])}>
+                        <td>{ format!("Team {}", team_name(team)) }</td>
+                        <td>{ length }</td>
+                        <td>{ score }</td>
+                        <td>{ kills }</td>
+                    </tr>
+                })}
+                { for rows.into_iter().map(|(name, team, length, score, kills)| html! {
+                    <tr style={team.map(|team| format!("color: {}", snake_colour("", Some(team))))}>
+                        <td>{ name }</td>
+                        <td>{ length }</td>
+                        <td>{ score }</td>
+                        <td>{ kills }</td>
+                    </tr>
+                })}
+            </table>
+        }
+    }
+    pub(super) fn view_leaderboard(&self, ctx: &Context<Self>) -> Html {
+        let tab = |period: LeaderboardPeriod, label: &str| {
+            let handle_click = ctx.link().callback(move |_| Msg::ShowLeaderboard(period));
+            let selected = self.leaderboard_period == period;
+            html! {
+                <button
+                    class={classes!("leaderboard__tab", if selected { "leaderboard__tab--selected" } else { "" })}
+                    onclick={handle_click}
+                >
+                    { label }
+                </button>
+            }
+        };
+        let entries = self.leaderboard.iter()
+            .filter(|leaderboard| leaderboard.period == self.leaderboard_period)
+            .flat_map(|leaderboard| leaderboard.entries.iter());
+
+        html! {
+            <div class={classes!("leaderboard")}>
+                <div>
+                    { tab(LeaderboardPeriod::Daily, "Today") }
+                    { tab(LeaderboardPeriod::AllTime, "All time") }
+                </div>
+                <ol class={classes!("leaderboard__entries")}>
+                    { for entries.map(|entry| html! {
+                        <li>{ format!("{} - {} ({} kills)", entry.name, entry.score, entry.kills) }</li>
+                    })}
+                </ol>
+            </div>
+        }
+    }
+    /// The cells of the map with everything on them
+    pub(super) fn view_grid(&self) -> Html {
+        html! {
+            <>
+                { for (0..self.map.height + GRID_OFFSET).map(|row| {
+                    html! {
+                        <div class="row" key={row}>
+                            { for (0..self.map.width + GRID_OFFSET).map(|column| {
+                                html! {
+                                    <div key={column} class={classes!(
+                                        "cell",
+                                        if self.x == column && self.y == row {
+                                            "cell--active"
+                                        } else {
+                                            ""
+                                        },
+                                        if self.is_head(column, row) {
+                                            "cell--head"
+                                        } else {
+                                            ""
+                                        },
+                                        if self.is_spawn_protected()
+                                            && ((self.x == column && self.y == row) || self.is_body_segment(column, row)) {
+                                            "cell--protected"
+                                        } else {
+                                            ""
+                                        },
+                                        if self.map.is_boundary(column, row) || self.is_outside_zone(column, row) || self.map.is_wall(column, row) {
+                                            "cell--boundary"
+                                        } else {
+                                            ""
+                                        },
+                                        if self.is_zone_warning(column, row) {
+                                            "cell--zone-warning"
+                                        } else {
+                                            ""
+                                        },
+                                        if self.is_food_coordinate(column, row) {
+                                            "cell--food"
+                                        } else {
+                                            ""
+                                        },
+                                        match self.food_at(column, row).map(|food| food.kind) {
+                                            Some(FoodKind::Golden) => "cell--food--golden",
+                                            Some(FoodKind::Poison) => "cell--food--poison",
+                                            Some(FoodKind::Decaying) => "cell--food--decaying",
+                                            Some(FoodKind::Pellet) => "cell--food--pellet",
+                                            _ => "",
+                                        },
+                                        if self.power_up_at(column, row).is_some() {
+                                            "cell--power-up"
+                                        } else {
+                                            ""
+                                        },
+                                        if self.is_body_segment(column, row) {
+                                            "cell--body"
+                                        } else {
+                                            ""
+                                        },
+                                        if self.is_other_player_segment(column, row) {
+                                            "cell--other--player"
+                                        } else {
+                                            ""
+                                        },
+                                        if self.is_boosting_segment(column, row) {
+                                            "cell--boosting"
+                                        } else {
+                                            ""
+                                        },
+                                        if self.is_passable_segment(column, row) {
+                                            "cell--passable"
+                                        } else {
+                                            ""
+                                        },
+                                        if self.is_protected_player_segment(column, row) {
+                                            "cell--protected"
+                                        } else {
+                                            ""
+                                        },
+                                    )} style={self.cell_style(column, row)}>
+                                        { self.view_power_up(column, row) }
+                                        { self.view_name_label(column, row) }
+                                    </div>
+                                }
+                            })}
+                        </div>
+                    }
+                })}
+            </>
+        }
+    }
+}
+
+fn snake_colour(colour: &str, team: Option<u64>) -> &str {
+    team.and_then(|team| TEAM_COLOURS.get(team as usize).copied()).unwrap_or(colour)
+}
+
+pub(super) fn power_up_icon(kind: PowerUpKind) -> &'static str {
+    match kind {
+        PowerUpKind::Speed => "⚡",
+        PowerUpKind::Slow => "🐢",
+        PowerUpKind::Ghost => "👻",
+        PowerUpKind::ShrinkOthers => "✂",
+        PowerUpKind::Magnet => "🧲",
+        PowerUpKind::Shield => "🛡",
+    }
+}
@@ -0,0 +1,172 @@
+//! Offline solo runs, played against the clock without a server.
+
+use yew::{html, Context, Html, classes};
+use crate::campaign;
+use craby_snake_shared::map_file::MapLayout;
+use crate::solo::{SoloMode, SoloRun, SCORE_ATTACK_MS, TIME_ATTACK_TARGET_LENGTH, format_duration};
+use super::{GameGridComponent, Msg, Food, TICK_TIME, SOLO_FOOD_ATTEMPTS, SOLO_FOOD_COUNT};
+
+impl GameGridComponent {
+    pub(super) fn start_solo(&mut self, mode: SoloMode) {
+        self.solo = Some(SoloRun::new(mode));
+        self.player_states.clear();
+        self.power_ups.clear();
+        self.effects.clear();
+        self.zone = None;
+        self.next_zone = None;
+        self.kill_feed.clear();
+        self.pending_growth = 0;
+        self.level = match mode {
+            SoloMode::Campaign(index) => campaign::load_level(index),
+            _ => None,
+        };
+        self.map = match (mode, &self.level) {
+            (SoloMode::Campaign(_), Some(level)) => level.layout.clone(),
+            (SoloMode::TestPlay, _) => self.editor_map.clone().unwrap_or_default(),
+            _ => MapLayout::default(),
+        };
+        self.food_positions.clear();
+        self.spawn();
+        for _ in 0..SOLO_FOOD_COUNT {
+            self.add_solo_food();
+        }
+    }
+    /// Back to the lobby, or to the editor after a test play
+    pub(super) fn leave_solo(&mut self) {
+        self.editing = self.solo.as_ref().is_some_and(|run| run.mode == SoloMode::TestPlay);
+        self.solo = None;
+        self.level = None;
+        self.map = MapLayout::default();
+        self.food_positions.clear();
+        self.update_pause(true);
+        self.list_rooms();
+    }
+    /// Advance the solo run clock, returns true if the run just ended
+    pub(super) fn update_solo_run(&mut self) -> bool {
+        let score = self.score;
+        let length = self.body_segments.len() + 1;
+        let Some(ref mut run) = self.solo else {
+            return false;
+        };
+        run.elapsed_ms += TICK_TIME;
+        let result = match run.mode {
+            SoloMode::TimeAttack if length >= TIME_ATTACK_TARGET_LENGTH => Some(run.elapsed_ms),
+            SoloMode::ScoreAttack if run.elapsed_ms >= SCORE_ATTACK_MS => Some(score),
+            SoloMode::Campaign(_) if self.level.as_ref().is_some_and(|level| score >= level.food_goal) => {
+                Some(run.elapsed_ms)
+            }
+            _ => return false,
+        };
+        run.finish(result);
+        self.update_pause(true);
+        true
+    }
+    /// Solo runs have no server, food is placed on a free cell of the
+    /// map's food zones locally
+    pub(super) fn add_solo_food(&mut self) {
+        for _ in 0..SOLO_FOOD_ATTEMPTS {
+            let Some((x, y)) = self.map.random_food_cell() else {
+                return;
+            };
+            if !self.is_food_coordinate(x, y) && !self.is_body_segment(x, y) && (x != self.x || y != self.y) {
+                self.food_positions.push(Food::normal(x, y));
+                return;
+            }
+        }
+    }
+    /// Timer, progress and personal best of a solo run
+    pub(super) fn view_solo_bar(&self, ctx: &Context<Self>, run: &SoloRun) -> Html {
+        let handle_leave = ctx.link().callback(|_| Msg::LeaveSolo(()));
+        let (timer, progress) = match run.mode {
+            SoloMode::TimeAttack => (
+                format_duration(run.elapsed_ms),
+                format!("Length {}/{}", self.body_segments.len() + 1, TIME_ATTACK_TARGET_LENGTH),
+            ),
+            SoloMode::ScoreAttack => (
+                format_duration(SCORE_ATTACK_MS.saturating_sub(run.elapsed_ms)),
+                format!("Score {}", self.score),
+            ),
+            SoloMode::Campaign(_) => (
+                format_duration(run.elapsed_ms),
+                format!("Food {}/{}", self.score, self.level.as_ref().map_or(0, |level| level.food_goal)),
+            ),
+            SoloMode::TestPlay => (format_duration(run.elapsed_ms), format!("Score {}", self.score)),
+        };
+        let leave_label = if run.mode == SoloMode::TestPlay { "Back to editor" } else { "Leave" };
+        html! {
+            <div class={classes!("room-bar")}>
+                <span>{ run.mode.title() }</span>
+                <span>{ timer }</span>
+                <span>{ progress }</span>
+                {if let Some(ref level) = self.level {
+                    html! { <span>{ format!("Par {}", format_duration(level.par_time_ms)) }</span> }
+                } else {
+                    html! {}
+                }}
+                <span>{ format!("Best {}", run.personal_best.map_or("-".to_string(), |best| run.mode.format_result(best))) }</span>
+                <button class={classes!("leaderboard__tab")} onclick={handle_leave}>{ leave_label }</button>
+            </div>
+        }
+    }
+    pub(super) fn view_solo_results(&self, ctx: &Context<Self>, run: &SoloRun) -> Html {
+        let mode = run.mode;
+        let handle_retry = ctx.link().callback(move |_| Msg::StartSolo(mode));
+        let handle_leave = ctx.link().callback(|_| Msg::LeaveSolo(()));
+        let next_level = match mode {
+            SoloMode::Campaign(index) if run.result.is_some() && index + 1 < campaign::unlocked_levels() => {
+                Some(index + 1)
+            }
+            _ => None,
+        };
+        html! {
+            <div class={classes!("game-over-ctn")}>
+                <h1 class={classes!("match-title")}>{ mode.title() }</h1>
+                {if let SoloMode::Campaign(_) = mode {
+                    html! {
+                        <p class={classes!("match-text")}>
+                            { if run.result.is_some() { "Level complete" } else { "Level failed" } }
+                        </p>
+                    }
+                } else {
+                    html! {}
+                }}
+                <p class={classes!("match-text")}>
+                    { match run.result {
+                        Some(result) => mode.format_result(result),
+                        None if mode == SoloMode::TestPlay => format!("Score {}", self.score),
+                        None => "Target not reached".to_string(),
+                    } }
+                </p>
+                {match (run.result, self.level.as_ref()) {
+                    (Some(result), Some(level)) => html! {
+                        <p class={classes!("match-text")}>
+                            { if result <= level.par_time_ms {
+                                format!("Under par ({})", format_duration(level.par_time_ms))
+                            } else {
+                                format!("Over par ({})", format_duration(level.par_time_ms))
+                            } }
+                        </p>
+                    },
+                    _ => html! {},
+                }}
+                {if run.new_best {
+                    html! { <p class={classes!("match-text", "match-result--own")}>{ "New personal best!" }</p> }
+                } else if let Some(best) = run.personal_best {
+                    html! { <p class={classes!("match-text")}>{ format!("Personal best {}", mode.format_result(best)) }</p> }
+                } else {
+                    html! {}
+                }}
+                {if let Some(next) = next_level {
+                    let handle_next = ctx.link().callback(move |_| Msg::StartSolo(SoloMode::Campaign(next)));
+                    html! { <button class={classes!("btn")} onclick={handle_next}>{ "Next level" }</button> }
+                } else {
+                    html! {}
+                }}
+                <button class={classes!("btn")} onclick={handle_retry}>{ "Play again" }</button>
+                <button class={classes!("leaderboard__tab")} onclick={handle_leave}>
+                    { if mode == SoloMode::TestPlay { "Back to editor" } else { "Back to lobby" } }
+                </button>
+            </div>
+        }
+    }
+}
@@ -0,0 +1,460 @@
+use yew::{html, Callback, Component, Context, Html, classes, InputEvent, TargetCast};
+use yew::Properties;
+use serde::{Serialize, Deserialize};
+use web_sys::HtmlInputElement;
+use craby_snake_shared::protocol::JoinRoomRequest;
+use crate::campaign;
+use crate::solo::SoloMode;
+
+// Must match MAX_ROOM_PLAYERS on the server
+const MAX_ROOM_PLAYERS: u64 = 16;
+const DEFAULT_ROOM_PLAYERS: u64 = 8;
+// Must match MIN_MATCH_TIME_LIMIT and MAX_MATCH_TIME_LIMIT on the server
+const MIN_MATCH_TIME_LIMIT: u64 = 30;
+const MAX_MATCH_TIME_LIMIT: u64 = 600;
+const DEFAULT_MATCH_TIME_LIMIT: u64 = 120;
+// Must match MAX_TEAMS on the server
+pub const MAX_TEAMS: u64 = 4;
+// Must match MAX_BOTS on the server
+const MAX_BOTS: u64 = 8;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum HeadOnRule {
+    BothDie,
+    LongerWins,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum CorpseBehaviour {
+    Vanish,
+    Food,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum RoomMode {
+    /// Drop in and respawn forever
+    Endless,
+    /// Rounds with a ready-check and a winner
+    Match,
+    /// Match rounds in a shrinking zone
+    BattleRoyale,
+}
+
+/// How well the server's bots play
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BotDifficulty {
+    /// Chases the closest food and walks into traps
+    Easy,
+    /// Checks a move leaves it room before taking it
+    #[default]
+    Normal,
+    /// Goes for the best food and keeps away from other heads
+    Hard,
+}
+
+/// Rules chosen when a room is created
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct RoomSettings {
+    pub name: String,
+    pub private: bool,
+    pub max_players: u64,
+    pub head_on_rule: HeadOnRule,
+    pub corpse_behaviour: CorpseBehaviour,
+    pub mode: RoomMode,
+    /// Seconds a match round lasts at most
+    pub match_time_limit: u64,
+    /// 0 for every snake for itself
+    #[serde(default)]
+    pub team_count: u64,
+    /// Whether teammates' bodies are solid
+    #[serde(default)]
+    pub friendly_fire: bool,
+    #[serde(default)]
+    pub power_ups: bool,
+    /// Name of a map loaded by the server, empty for the classic map
+    #[serde(default)]
+    pub map: String,
+    /// Bots the server adds when the room is created
+    #[serde(default)]
+    pub bot_count: u64,
+    #[serde(default)]
+    pub bot_difficulty: BotDifficulty,
+}
+
+/// What the lobby shows about a room
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct RoomInfo {
+    pub room_id: u64,
+    pub code: String,
+    pub settings: RoomSettings,
+    pub player_count: u64,
+}
+
+#[derive(Properties, PartialEq, Clone)]
+pub struct LobbyProps {
+    pub rooms: Vec<RoomInfo>,
+    /// Names of the maps rooms can be created with
+    pub maps: Vec<String>,
+    pub error: Option<String>,
+    pub on_refresh: Callback<()>,
+    pub on_create: Callback<RoomSettings>,
+    pub on_join: Callback<JoinRoomRequest>,
+    pub on_solo: Callback<SoloMode>,
+    pub on_editor: Callback<()>,
+}
+
+pub struct LobbyComponent {
+    settings: RoomSettings,
+    join_code: String,
+}
+
+pub enum LobbyMsg {
+    UpdateRoomName(String),
+    TogglePrivate(()),
+    UpdateMaxPlayers(String),
+    SelectHeadOnRule(HeadOnRule),
+    SelectCorpseBehaviour(CorpseBehaviour),
+    SelectMode(RoomMode),
+    UpdateMatchTimeLimit(String),
+    SelectTeamCount(u64),
+    ToggleFriendlyFire(()),
+    TogglePowerUps(()),
+    SelectMap(String),
+    UpdateBotCount(String),
+    SelectBotDifficulty(BotDifficulty),
+    UpdateJoinCode(String),
+    CreateRoom(()),
+    JoinByCode(()),
+}
+
+fn input_value(e: InputEvent) -> String {
+    let input: HtmlInputElement = e.target_unchecked_into();
+    input.value()
+}
+
+impl LobbyComponent {
+    fn view_option<T: Copy + PartialEq + 'static>(
+        &self,
+        ctx: &Context<Self>,
+        label: &str,
+        value: T,
+        current: T,
+        msg: fn(T) -> LobbyMsg,
+    ) -> Html {
+        let handle_click = ctx.link().callback(move |_| msg(value));
+        html! {
+            <button
+                class={classes!("leaderboard__tab", if value == current { "leaderboard__tab--selected" } else { "" })}
+                onclick={handle_click}
+            >
+                { label }
+            </button>
+        }
+    }
+}
+
+impl Component for LobbyComponent {
+    type Message = LobbyMsg;
+    type Properties = LobbyProps;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            settings: RoomSettings {
+                name: String::new(),
+                private: false,
+                max_players: DEFAULT_ROOM_PLAYERS,
+                head_on_rule: HeadOnRule::BothDie,
+                corpse_behaviour: CorpseBehaviour::Vanish,
+                mode: RoomMode::Endless,
+                match_time_limit: DEFAULT_MATCH_TIME_LIMIT,
+                team_count: 0,
+                friendly_fire: false,
+                power_ups: true,
+                map: String::new(),
+                bot_count: 0,
+                bot_difficulty: BotDifficulty::Normal,
+            },
+            join_code: String::new(),
+        }
+    }
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            LobbyMsg::UpdateRoomName(name) => {
+                self.settings.name = name;
+            }
+            LobbyMsg::TogglePrivate(_) => {
+                self.settings.private = !self.settings.private;
+            }
+            LobbyMsg::UpdateMaxPlayers(value) => {
+                if let Ok(max_players) = value.parse::<u64>() {
+                    self.settings.max_players = max_players.clamp(2, MAX_ROOM_PLAYERS);
+                    // Leave at least one place for a person
+                    self.settings.bot_count = self.settings.bot_count.min(self.settings.max_players - 1);
+                }
+            }
+            LobbyMsg::SelectHeadOnRule(rule) => {
+                self.settings.head_on_rule = rule;
+            }
+            LobbyMsg::SelectCorpseBehaviour(behaviour) => {
+                self.settings.corpse_behaviour = behaviour;
+            }
+            LobbyMsg::SelectMode(mode) => {
+                self.settings.mode = mode;
+            }
+            LobbyMsg::UpdateMatchTimeLimit(value) => {
+                if let Ok(seconds) = value.parse::<u64>() {
+                    self.settings.match_time_limit = seconds.clamp(MIN_MATCH_TIME_LIMIT, MAX_MATCH_TIME_LIMIT);
+                }
+            }
+            LobbyMsg::SelectTeamCount(team_count) => {
+                self.settings.team_count = team_count;
+            }
+            LobbyMsg::ToggleFriendlyFire(_) => {
+                self.settings.friendly_fire = !self.settings.friendly_fire;
+            }
+            LobbyMsg::TogglePowerUps(_) => {
+                self.settings.power_ups = !self.settings.power_ups;
+            }
+            LobbyMsg::SelectMap(map) => {
+                self.settings.map = map;
+            }
+            LobbyMsg::UpdateBotCount(value) => {
+                if let Ok(bot_count) = value.parse::<u64>() {
+                    self.settings.bot_count = bot_count.min(MAX_BOTS).min(self.settings.max_players - 1);
+                }
+            }
+            LobbyMsg::SelectBotDifficulty(difficulty) => {
+                self.settings.bot_difficulty = difficulty;
+            }
+            LobbyMsg::UpdateJoinCode(code) => {
+                self.join_code = code.to_uppercase();
+            }
+            LobbyMsg::CreateRoom(_) => {
+                ctx.props().on_create.emit(self.settings.clone());
+            }
+            LobbyMsg::JoinByCode(_) => {
+                ctx.props().on_join.emit(JoinRoomRequest {
+                    room_id: None,
+                    code: Some(self.join_code.clone()),
+                });
+            }
+        }
+        true
+    }
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let props = ctx.props();
+        let handle_refresh = props.on_refresh.reform(|_| ());
+        let unlocked = campaign::unlocked_levels();
+        let handle_room_name = ctx.link().callback(|e: InputEvent| LobbyMsg::UpdateRoomName(input_value(e)));
+        let handle_max_players = ctx.link().callback(|e: InputEvent| LobbyMsg::UpdateMaxPlayers(input_value(e)));
+        let handle_bot_count = ctx.link().callback(|e: InputEvent| LobbyMsg::UpdateBotCount(input_value(e)));
+        let handle_match_time_limit = ctx.link().callback(|e: InputEvent| LobbyMsg::UpdateMatchTimeLimit(input_value(e)));
+        let handle_join_code = ctx.link().callback(|e: InputEvent| LobbyMsg::UpdateJoinCode(input_value(e)));
+        let handle_private = ctx.link().callback(|_| LobbyMsg::TogglePrivate(()));
+        let handle_friendly_fire = ctx.link().callback(|_| LobbyMsg::ToggleFriendlyFire(()));
+        let handle_power_ups = ctx.link().callback(|_| LobbyMsg::TogglePowerUps(()));
+        let handle_create = ctx.link().callback(|_| LobbyMsg::CreateRoom(()));
+        let handle_join_code_submit = ctx.link().callback(|_| LobbyMsg::JoinByCode(()));
+
+        html! {
+            <div class={classes!("lobby-ctn")}>
+                <h1 class={classes!("join-title")}>{ "Rooms" }</h1>
+                <ul class={classes!("room-list")}>
+                    { for props.rooms.iter().map(|room| {
+                        let room_id = room.room_id;
+                        let handle_join = props.on_join.reform(move |_| JoinRoomRequest {
+                            room_id: Some(room_id),
+                            code: None,
+                        });
+                        html! {
+                            <li class={classes!("room-list__entry")}>
+                                <span>
+                                    { &room.settings.name }
+                                    {match room.settings.mode {
+                                        RoomMode::Endless => "",
+                                        RoomMode::Match => " (match)",
+                                        RoomMode::BattleRoyale => " (battle royale)",
+                                    }}
+                                    {if room.settings.team_count > 0 { format!(" ({} teams)", room.settings.team_count) } else { String::new() }}
+                                    {if room.settings.map.is_empty() { String::new() } else { format!(" on {}", room.settings.map) }}
+                                    {if room.settings.bot_count > 0 { format!(" with {} bots", room.settings.bot_count) } else { String::new() }}
+                                </span>
+                                <span>{ format!("{}/{}", room.player_count, room.settings.max_players) }</span>
+                                <button class={classes!("btn")} onclick={handle_join}>{ "Join" }</button>
+                            </li>
+                        }
+                    })}
+                </ul>
+                <button class={classes!("leaderboard__tab")} onclick={handle_refresh}>{ "Refresh" }</button>
+
+                <div class={classes!("lobby-section")}>
+                    <input
+                        class={classes!("join-input")}
+                        placeholder="Join code"
+                        value={self.join_code.clone()}
+                        oninput={handle_join_code}
+                    />
+                    <button class={classes!("btn")} onclick={handle_join_code_submit}>{ "Join by code" }</button>
+                </div>
+
+                <div class={classes!("lobby-section")}>
+                    <input
+                        class={classes!("join-input")}
+                        placeholder="Room name"
+                        value={self.settings.name.clone()}
+                        oninput={handle_room_name}
+                    />
+                    <label class={classes!("lobby-label")}>
+                        <input type="checkbox" checked={self.settings.private} onclick={handle_private}/>
+                        { " Private" }
+                    </label>
+                    <label class={classes!("lobby-label")}>
+                        <input type="checkbox" checked={self.settings.power_ups} onclick={handle_power_ups}/>
+                        { " Power-ups" }
+                    </label>
+                    <label class={classes!("lobby-label")}>
+                        { "Max players " }
+                        <input
+                            class={classes!("lobby-number")}
+                            type="number"
+                            min="2"
+                            max={MAX_ROOM_PLAYERS.to_string()}
+                            value={self.settings.max_players.to_string()}
+                            oninput={handle_max_players}
+                        />
+                    </label>
+                    <div class={classes!("lobby-label")}>
+                        { "Head-on: " }
+                        { self.view_option(ctx, "Both die", HeadOnRule::BothDie, self.settings.head_on_rule, LobbyMsg::SelectHeadOnRule) }
+                        { self.view_option(ctx, "Longer wins", HeadOnRule::LongerWins, self.settings.head_on_rule, LobbyMsg::SelectHeadOnRule) }
+                    </div>
+                    {if props.maps.is_empty() {
+                        html! {}
+                    } else {
+                        html! {
+                            <div class={classes!("lobby-label")}>
+                                { "Map: " }
+                                { for std::iter::once(String::new()).chain(props.maps.iter().cloned()).map(|map| {
+                                    let selected = map == self.settings.map;
+                                    let label = if map.is_empty() { "Classic".to_string() } else { map.clone() };
+                                    let handle_click = ctx.link().callback(move |_| LobbyMsg::SelectMap(map.clone()));
+                                    html! {
+                                        <button
+                                            class={classes!("leaderboard__tab", if selected { "leaderboard__tab--selected" } else { "" })}
+                                            onclick={handle_click}
+                                        >
+                                            { label }
+                                        </button>
+                                    }
+                                })}
+                            </div>
+                        }
+                    }}
+                    <div class={classes!("lobby-label")}>
+                        { "Mode: " }
+                        { self.view_option(ctx, "Endless", RoomMode::Endless, self.settings.mode, LobbyMsg::SelectMode) }
+                        { self.view_option(ctx, "Match", RoomMode::Match, self.settings.mode, LobbyMsg::SelectMode) }
+                        { self.view_option(ctx, "Battle royale", RoomMode::BattleRoyale, self.settings.mode, LobbyMsg::SelectMode) }
+                    </div>
+                    {if self.settings.mode != RoomMode::Endless {
+                        html! {
+                            <label class={classes!("lobby-label")}>
+                                { "Time limit (s) " }
+                                <input
+                                    class={classes!("lobby-number")}
+                                    type="number"
+                                    min={MIN_MATCH_TIME_LIMIT.to_string()}
+                                    max={MAX_MATCH_TIME_LIMIT.to_string()}
+                                    value={self.settings.match_time_limit.to_string()}
+                                    oninput={handle_match_time_limit}
+                                />
+                            </label>
+                        }
+                    } else {
+                        html! {}
+                    }}
+                    <div class={classes!("lobby-label")}>
+                        { "Teams: " }
+                        { self.view_option(ctx, "None", 0, self.settings.team_count, LobbyMsg::SelectTeamCount) }
+                        { for (2..=MAX_TEAMS).map(|team_count| {
+                            self.view_option(ctx, &team_count.to_string(), team_count, self.settings.team_count, LobbyMsg::SelectTeamCount)
+                        })}
+                    </div>
+                    {if self.settings.team_count > 0 {
+                        html! {
+                            <label class={classes!("lobby-label")}>
+                                <input type="checkbox" checked={self.settings.friendly_fire} onclick={handle_friendly_fire}/>
+                                { " Friendly fire" }
+                            </label>
+                        }
+                    } else {
+                        html! {}
+                    }}
+                    <label class={classes!("lobby-label")}>
+                        { "Bots " }
+                        <input
+                            class={classes!("lobby-number")}
+                            type="number"
+                            min="0"
+                            max={MAX_BOTS.min(self.settings.max_players - 1).to_string()}
+                            value={self.settings.bot_count.to_string()}
+                            oninput={handle_bot_count}
+                        />
+                    </label>
+                    {if self.settings.bot_count > 0 {
+                        html! {
+                            <div class={classes!("lobby-label")}>
+                                { "Bot skill: " }
+                                { self.view_option(ctx, "Easy", BotDifficulty::Easy, self.settings.bot_difficulty, LobbyMsg::SelectBotDifficulty) }
+                                { self.view_option(ctx, "Normal", BotDifficulty::Normal, self.settings.bot_difficulty, LobbyMsg::SelectBotDifficulty) }
+                                { self.view_option(ctx, "Hard", BotDifficulty::Hard, self.settings.bot_difficulty, LobbyMsg::SelectBotDifficulty) }
+                            </div>
+                        }
+                    } else {
+                        html! {}
+                    }}
+                    <div class={classes!("lobby-label")}>
+                        { "Corpses: " }
+                        { self.view_option(ctx, "Vanish", CorpseBehaviour::Vanish, self.settings.corpse_behaviour, LobbyMsg::SelectCorpseBehaviour) }
+                        { self.view_option(ctx, "Food", CorpseBehaviour::Food, self.settings.corpse_behaviour, LobbyMsg::SelectCorpseBehaviour) }
+                    </div>
+                    <button class={classes!("btn")} onclick={handle_create}>{ "Create room" }</button>
+                </div>
+
+                <div class={classes!("lobby-section")}>
+                    <span class={classes!("lobby-label")}>{ "Solo practice" }</span>
+                    { for [SoloMode::TimeAttack, SoloMode::ScoreAttack].into_iter().map(|mode| html! {
+                        <button class={classes!("btn")} onclick={props.on_solo.reform(move |_| mode)}>
+                            { mode.title() }
+                        </button>
+                    })}
+                    <button class={classes!("leaderboard__tab")} onclick={props.on_editor.reform(|_| ())}>
+                        { "Map editor" }
+                    </button>
+                </div>
+
+                <div class={classes!("lobby-section")}>
+                    <span class={classes!("lobby-label")}>{ "Campaign" }</span>
+                    { for (0..campaign::level_count()).filter_map(|index| campaign::load_level(index).map(|level| (index, level))).map(|(index, level)| {
+                        let locked = index >= unlocked;
+                        html! {
+                            <button class={classes!("btn")} disabled={locked} onclick={props.on_solo.reform(move |_| SoloMode::Campaign(index))}>
+                                { format!("{}. {}", index + 1, level.layout.name) }
+                            </button>
+                        }
+                    })}
+                </div>
+
+                {if let Some(ref error) = props.error {
+                    html! { <p class={classes!("join-error")}>{ error }</p> }
+                } else {
+                    html! {}
+                }}
+            </div>
+        }
+    }
+}
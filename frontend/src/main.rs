@@ -1,7 +1,14 @@
 use crate::network::start;
 
+mod campaign;
+mod clock;
+mod conditioner;
+mod debug_overlay;
+mod editor;
 mod game_grid;
+mod lobby;
 mod network;
+mod solo;
 
 fn main() {
     match start() {
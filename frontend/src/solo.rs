@@ -0,0 +1,114 @@
+use web_sys::Storage;
+use crate::campaign;
+
+/// Length a time attack run has to reach
+pub const TIME_ATTACK_TARGET_LENGTH: usize = 50;
+/// How long a score attack run lasts
+pub const SCORE_ATTACK_MS: u64 = 120_000;
+
+/// Offline practice modes, played without a room
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SoloMode {
+    /// Reach the target length as fast as possible
+    TimeAttack,
+    /// Score as much as possible before the time runs out
+    ScoreAttack,
+    /// Clear a campaign level, by its index
+    Campaign(usize),
+    /// Try out the map in the editor, there is no target
+    TestPlay,
+}
+
+impl SoloMode {
+    pub fn title(&self) -> String {
+        match self {
+            SoloMode::TimeAttack => "Time attack".to_string(),
+            SoloMode::ScoreAttack => "Score attack".to_string(),
+            SoloMode::Campaign(level) => format!("Level {}", level + 1),
+            SoloMode::TestPlay => "Test play".to_string(),
+        }
+    }
+    /// Test plays keep no personal best
+    fn storage_key(&self) -> Option<String> {
+        match self {
+            SoloMode::TimeAttack => Some("craby-snake.best.time_attack".to_string()),
+            SoloMode::ScoreAttack => Some("craby-snake.best.score_attack".to_string()),
+            SoloMode::Campaign(level) => Some(format!("craby-snake.best.level_{}", level + 1)),
+            SoloMode::TestPlay => None,
+        }
+    }
+    /// Lower times are better, except in score attack where higher scores are
+    fn is_better(&self, result: u64, best: u64) -> bool {
+        match self {
+            SoloMode::ScoreAttack => result > best,
+            _ => result < best,
+        }
+    }
+    /// Score attack results are scores, the others are times in milliseconds
+    pub fn format_result(&self, result: u64) -> String {
+        match self {
+            SoloMode::ScoreAttack => result.to_string(),
+            _ => format_duration(result),
+        }
+    }
+}
+
+pub struct SoloRun {
+    pub mode: SoloMode,
+    pub elapsed_ms: u64,
+    pub finished: bool,
+    /// None if the run ended without reaching the target
+    pub result: Option<u64>,
+    pub personal_best: Option<u64>,
+    pub new_best: bool,
+}
+
+impl SoloRun {
+    pub fn new(mode: SoloMode) -> Self {
+        Self {
+            mode,
+            elapsed_ms: 0,
+            finished: false,
+            result: None,
+            personal_best: load_personal_best(mode),
+            new_best: false,
+        }
+    }
+    /// End the run and keep the result if it beats the personal best
+    pub fn finish(&mut self, result: Option<u64>) {
+        self.finished = true;
+        self.result = result;
+        let Some(result) = result else {
+            return;
+        };
+        if let SoloMode::Campaign(level) = self.mode {
+            campaign::unlock_level(level + 1);
+        }
+        if self.personal_best.is_none_or(|best| self.mode.is_better(result, best)) {
+            self.personal_best = Some(result);
+            self.new_best = true;
+            save_personal_best(self.mode, result);
+        }
+    }
+}
+
+/// Format milliseconds as m:ss.t
+pub fn format_duration(ms: u64) -> String {
+    format!("{}:{:02}.{}", ms / 60_000, ms / 1000 % 60, ms / 100 % 10)
+}
+
+pub fn local_storage() -> Option<Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+fn load_personal_best(mode: SoloMode) -> Option<u64> {
+    local_storage()?.get_item(&mode.storage_key()?).ok()??.parse().ok()
+}
+
+fn save_personal_best(mode: SoloMode, result: u64) {
+    if let (Some(storage), Some(key)) = (local_storage(), mode.storage_key()) {
+        if storage.set_item(&key, &result.to_string()).is_err() {
+            log::error!("Failed to save personal best");
+        }
+    }
+}
@@ -0,0 +1,8 @@
+//! What the browser client and the native bots both need to talk to the
+//! server: the game state as it is sent in [`state`], the messages around it
+//! in [`protocol`] and the map file format in [`map_file`]. The server keeps
+//! its own copy of these in types.ts.
+
+pub mod map_file;
+pub mod protocol;
+pub mod state;
@@ -0,0 +1,466 @@
+//! Maps as they are stored in files, as JSON or as ASCII rows, and the
+//! checks every map has to pass. Must agree with mapfile.ts on the server.
+
+use std::fmt;
+use rand::seq::SliceRandom;
+use serde::{Serialize, Deserialize};
+use crate::state::{Direction, BOUNDARY_THICKNESS};
+
+/// Must match MAP_VERSION on the server
+pub const MAP_VERSION: u64 = 1;
+// Must match MIN_MAP_SIZE and MAX_MAP_SIZE on the server
+pub const MIN_MAP_SIZE: i8 = 10;
+pub const MAX_MAP_SIZE: i8 = 60;
+const MAX_MAP_NAME_LENGTH: usize = 24;
+const CLASSIC_SIZE: i8 = 30;
+// First line of the ASCII variant, followed by the version
+const ASCII_MAGIC: &str = "snake-map";
+// Cells of the ASCII variant, spawn points are drawn as the arrow they face
+pub const FLOOR_CELL: char = '.';
+pub const WALL_CELL: char = '#';
+pub const FOOD_ZONE_CELL: char = 'f';
+
+/// What happens to a snake running off the edge of the map
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeBehaviour {
+    /// Come back in on the other side
+    #[default]
+    Wrap,
+    /// The edge is a wall
+    Solid,
+}
+
+/// A block of cells, in map coordinates starting at 0 in the top left
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct Rect {
+    pub x: i8,
+    pub y: i8,
+    pub width: i8,
+    pub height: i8,
+}
+
+impl Rect {
+    pub fn contains(&self, x: i8, y: i8) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+    fn cells(&self) -> impl Iterator<Item = (i8, i8)> + '_ {
+        (self.y..self.y + self.height).flat_map(move |y| (self.x..self.x + self.width).map(move |x| (x, y)))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct SpawnPoint {
+    pub x: i8,
+    pub y: i8,
+    pub direction: Direction,
+}
+
+/// Everything about a map the game needs, sent by the server in `MapState`
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct MapLayout {
+    pub name: String,
+    pub width: i8,
+    pub height: i8,
+    #[serde(default)]
+    pub edge: EdgeBehaviour,
+    #[serde(default)]
+    pub walls: Vec<Rect>,
+    #[serde(default)]
+    pub spawn_points: Vec<SpawnPoint>,
+    // Food only spawns in these, anywhere if there are none
+    #[serde(default)]
+    pub food_zones: Vec<Rect>,
+}
+
+impl Default for MapLayout {
+    /// The open square the game was first played on
+    fn default() -> Self {
+        Self {
+            name: "Classic".to_string(),
+            width: CLASSIC_SIZE,
+            height: CLASSIC_SIZE,
+            edge: EdgeBehaviour::Wrap,
+            walls: vec![],
+            spawn_points: vec![],
+            food_zones: vec![],
+        }
+    }
+}
+
+// The methods below take grid coordinates, which include the boundary
+// drawn around the map
+impl MapLayout {
+    pub fn grid_width(&self) -> i8 {
+        self.width + BOUNDARY_THICKNESS * 2
+    }
+    pub fn grid_height(&self) -> i8 {
+        self.height + BOUNDARY_THICKNESS * 2
+    }
+    pub fn is_boundary(&self, x: i8, y: i8) -> bool {
+        x < BOUNDARY_THICKNESS
+            || x >= self.width + BOUNDARY_THICKNESS
+            || y < BOUNDARY_THICKNESS
+            || y >= self.height + BOUNDARY_THICKNESS
+    }
+    pub fn is_wall(&self, x: i8, y: i8) -> bool {
+        self.walls.iter().any(|wall| wall.contains(x - BOUNDARY_THICKNESS, y - BOUNDARY_THICKNESS))
+    }
+    /// Cells a snake dies on
+    pub fn is_deadly(&self, x: i8, y: i8) -> bool {
+        self.is_wall(x, y) || (self.edge == EdgeBehaviour::Solid && self.is_boundary(x, y))
+    }
+    /// One step on from a cell, snakes wrap around the whole grid
+    pub fn step(&self, x: i8, y: i8, direction: Direction) -> (i8, i8) {
+        let (dx, dy) = direction.delta();
+        (
+            (x + dx).rem_euclid(self.grid_width()),
+            (y + dy).rem_euclid(self.grid_height()),
+        )
+    }
+    /// Cells inside the map that are not walls
+    pub fn floor_cells(&self) -> Vec<(i8, i8)> {
+        Rect { x: 0, y: 0, width: self.width, height: self.height }.cells()
+            .map(|(x, y)| (x + BOUNDARY_THICKNESS, y + BOUNDARY_THICKNESS))
+            .filter(|(x, y)| !self.is_wall(*x, *y))
+            .collect()
+    }
+    /// Cells in the food zones, or anywhere if there are none, that are not walls
+    pub fn food_cells(&self) -> Vec<(i8, i8)> {
+        if self.food_zones.is_empty() {
+            return self.floor_cells();
+        }
+        self.food_zones.iter()
+            .flat_map(|zone| zone.cells())
+            .map(|(x, y)| (x + BOUNDARY_THICKNESS, y + BOUNDARY_THICKNESS))
+            .filter(|(x, y)| !self.is_wall(*x, *y))
+            .collect()
+    }
+    /// A random cell that food may spawn on, if there is one
+    pub fn random_food_cell(&self) -> Option<(i8, i8)> {
+        self.food_cells().choose(&mut rand::thread_rng()).copied()
+    }
+}
+
+impl MapLayout {
+    /// The map as rows of ASCII cells, spawn points are drawn over food
+    /// zones and walls over both
+    pub fn ascii_rows(&self) -> Vec<Vec<char>> {
+        let mut rows = vec![vec![FLOOR_CELL; self.width.max(0) as usize]; self.height.max(0) as usize];
+        let mut set = |x: i8, y: i8, cell: char| {
+            if let Some(row_cell) = rows.get_mut(y as usize).and_then(|row| row.get_mut(x as usize)) {
+                *row_cell = cell;
+            }
+        };
+        self.food_zones.iter().flat_map(|zone| zone.cells()).for_each(|(x, y)| set(x, y, FOOD_ZONE_CELL));
+        self.spawn_points.iter().for_each(|spawn| set(spawn.x, spawn.y, spawn_cell(&spawn.direction)));
+        self.walls.iter().flat_map(|wall| wall.cells()).for_each(|(x, y)| set(x, y, WALL_CELL));
+        rows
+    }
+}
+
+pub fn spawn_cell(direction: &Direction) -> char {
+    match direction {
+        Direction::UP => '^',
+        Direction::DOWN => 'v',
+        Direction::LEFT => '<',
+        Direction::RIGHT => '>',
+    }
+}
+
+/// A map as it is stored in a file
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MapFile {
+    pub version: u64,
+    #[serde(flatten)]
+    pub layout: MapLayout,
+}
+
+/// Why a map file could not be loaded, `line` and `column` start at 1 and
+/// are 0 when the problem is not at a single place in the text
+#[derive(Clone, PartialEq, Debug)]
+pub struct MapError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl MapFile {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+    pub fn to_ascii(&self) -> String {
+        ascii_text(&self.layout.name, self.layout.edge, &self.layout.ascii_rows())
+    }
+}
+
+/// Write out a map in the ASCII variant of the current version
+pub fn ascii_text(name: &str, edge: EdgeBehaviour, rows: &[Vec<char>]) -> String {
+    let edge = match edge {
+        EdgeBehaviour::Wrap => "wrap",
+        EdgeBehaviour::Solid => "solid",
+    };
+    let mut text = format!("{} {}\nname: {}\nedge: {}\n\n", ASCII_MAGIC, MAP_VERSION, name, edge);
+    for row in rows {
+        text.extend(row);
+        text.push('\n');
+    }
+    text
+}
+
+impl MapError {
+    fn at(line: usize, column: usize, message: impl Into<String>) -> Self {
+        Self { line, column, message: message.into() }
+    }
+    fn general(message: impl Into<String>) -> Self {
+        Self::at(0, 0, message)
+    }
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+        }
+    }
+}
+
+/// Parse a map in either the JSON or the ASCII format and check that it
+/// can be played
+pub fn parse_map(text: &str) -> Result<MapFile, MapError> {
+    let map = if text.trim_start().starts_with('{') {
+        parse_json_map(text)?
+    } else {
+        parse_ascii_map(text)?
+    };
+    validate_map(&map)?;
+    Ok(map)
+}
+
+fn parse_json_map(text: &str) -> Result<MapFile, MapError> {
+    serde_json::from_str::<MapFile>(text)
+        .map_err(|e| MapError::at(e.line(), char_column(text, e.line(), e.column()), strip_position(&e)))
+}
+
+// serde_json counts columns in bytes, errors are reported in characters
+// like everywhere else
+fn char_column(text: &str, line: usize, byte_column: usize) -> usize {
+    let Some(line_text) = line.checked_sub(1).and_then(|index| text.lines().nth(index)) else {
+        return byte_column;
+    };
+    line_text.char_indices().take_while(|(index, _)| *index + 1 < byte_column).count() + 1
+}
+
+// serde_json puts the position at the end of its messages, it is reported separately
+fn strip_position(error: &serde_json::Error) -> String {
+    let message = error.to_string();
+    match message.rfind(" at line ") {
+        Some(index) => message[..index].to_string(),
+        None => message,
+    }
+}
+
+/// The ASCII variant: a `snake-map <version>` line, `key: value` headers,
+/// a blank line and then one row of the map per line.
+///
+/// `.` is floor, `#` is wall, `f` is floor in a food zone and `^ v < >` are
+/// spawn points facing that way. Lines starting with `;` are comments.
+fn parse_ascii_map(text: &str) -> Result<MapFile, MapError> {
+    let mut lines = text.lines().enumerate()
+        .map(|(index, line)| (index + 1, line.trim_end()))
+        .filter(|(_, line)| !line.starts_with(';'));
+
+    let (magic_line, magic) = lines.next().ok_or_else(|| MapError::general("The map is empty"))?;
+    let version = magic.strip_prefix(ASCII_MAGIC)
+        .map(str::trim)
+        .ok_or_else(|| MapError::at(magic_line, 1, format!("Expected `{} <version>`", ASCII_MAGIC)))?
+        .parse::<u64>()
+        .map_err(|_| MapError::at(magic_line, ASCII_MAGIC.len() + 2, "Expected a version number"))?;
+
+    let mut layout = MapLayout { name: String::new(), width: 0, height: 0, ..MapLayout::default() };
+    for (line_number, line) in lines.by_ref() {
+        if line.is_empty() {
+            break;
+        }
+        let (key, value) = line.split_once(':')
+            .ok_or_else(|| MapError::at(line_number, 1, "Expected `key: value`"))?;
+        let value_column = key.chars().count() + 2 + (value.chars().count() - value.trim_start().chars().count());
+        match key.trim() {
+            "name" => layout.name = value.trim().to_string(),
+            "edge" => {
+                layout.edge = match value.trim() {
+                    "wrap" => EdgeBehaviour::Wrap,
+                    "solid" => EdgeBehaviour::Solid,
+                    other => return Err(MapError::at(line_number, value_column, format!("Unknown edge behaviour `{}`", other))),
+                }
+            }
+            other => return Err(MapError::at(line_number, 1, format!("Unknown header `{}`", other))),
+        }
+    }
+
+    let rows: Vec<(usize, &str)> = lines.skip_while(|(_, line)| line.is_empty()).collect();
+    let rows = match rows.iter().rposition(|(_, line)| !line.is_empty()) {
+        Some(last) => &rows[..=last],
+        None => return Err(MapError::general("The map has no rows")),
+    };
+    let width = rows[0].1.chars().count();
+    if width > MAX_MAP_SIZE as usize || rows.len() > MAX_MAP_SIZE as usize {
+        return Err(MapError::general(format!("Maps are at most {} by {} cells", MAX_MAP_SIZE, MAX_MAP_SIZE)));
+    }
+    for (y, (line_number, row)) in rows.iter().enumerate() {
+        let row_width = row.chars().count();
+        if row_width != width {
+            return Err(MapError::at(*line_number, row_width.min(width) + 1, format!("Expected {} cells in every row", width)));
+        }
+        let mut wall_start: Option<i8> = None;
+        let mut zone_start: Option<i8> = None;
+        // A trailing floor cell closes the runs still open at the end of the row
+        for (x, cell) in row.chars().chain(std::iter::once(FLOOR_CELL)).enumerate() {
+            let (x, y) = (x as i8, y as i8);
+            let direction = match cell {
+                '^' => Some(Direction::UP),
+                'v' => Some(Direction::DOWN),
+                '<' => Some(Direction::LEFT),
+                '>' => Some(Direction::RIGHT),
+                FLOOR_CELL | WALL_CELL | FOOD_ZONE_CELL => None,
+                other => return Err(MapError::at(*line_number, x as usize + 1, format!("Unknown cell `{}`", other))),
+            };
+            if let Some(direction) = direction {
+                layout.spawn_points.push(SpawnPoint { x, y, direction });
+            }
+            // Runs of walls and food zone cells become one rect each
+            match (cell == WALL_CELL, wall_start) {
+                (true, None) => wall_start = Some(x),
+                (false, Some(start)) => {
+                    layout.walls.push(Rect { x: start, y, width: x - start, height: 1 });
+                    wall_start = None;
+                }
+                _ => {}
+            }
+            match (cell == FOOD_ZONE_CELL, zone_start) {
+                (true, None) => zone_start = Some(x),
+                (false, Some(start)) => {
+                    layout.food_zones.push(Rect { x: start, y, width: x - start, height: 1 });
+                    zone_start = None;
+                }
+                _ => {}
+            }
+        }
+    }
+    layout.width = width as i8;
+    layout.height = rows.len() as i8;
+    Ok(MapFile { version, layout })
+}
+
+fn validate_map(map: &MapFile) -> Result<(), MapError> {
+    let layout = &map.layout;
+    if map.version != MAP_VERSION {
+        return Err(MapError::general(format!("Unsupported map version {}, expected {}", map.version, MAP_VERSION)));
+    }
+    let name_length = layout.name.trim().chars().count();
+    if name_length == 0 || name_length > MAX_MAP_NAME_LENGTH {
+        return Err(MapError::general(format!("Map names must be 1 to {} characters long", MAX_MAP_NAME_LENGTH)));
+    }
+    let size = MIN_MAP_SIZE..=MAX_MAP_SIZE;
+    if !size.contains(&layout.width) || !size.contains(&layout.height) {
+        return Err(MapError::general(format!("Maps are {} to {} cells wide and high", MIN_MAP_SIZE, MAX_MAP_SIZE)));
+    }
+    let inside = |rect: &Rect| {
+        rect.width > 0 && rect.height > 0 && rect.x >= 0 && rect.y >= 0
+            && rect.x as i16 + rect.width as i16 <= layout.width as i16
+            && rect.y as i16 + rect.height as i16 <= layout.height as i16
+    };
+    if let Some(index) = layout.walls.iter().position(|wall| !inside(wall)) {
+        return Err(MapError::general(format!("Wall {} is not inside the map", index + 1)));
+    }
+    if let Some(index) = layout.food_zones.iter().position(|zone| !inside(zone)) {
+        return Err(MapError::general(format!("Food zone {} is not inside the map", index + 1)));
+    }
+    for (index, spawn) in layout.spawn_points.iter().enumerate() {
+        let cell = Rect { x: spawn.x, y: spawn.y, width: 1, height: 1 };
+        if !inside(&cell) || layout.walls.iter().any(|wall| wall.contains(spawn.x, spawn.y)) {
+            return Err(MapError::general(format!("Spawn point {} is not on a free cell", index + 1)));
+        }
+    }
+    if layout.food_cells().is_empty() {
+        return Err(MapError::general("There is nowhere for food to spawn"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROW: &str = "..........";
+
+    // An ASCII map with the headers on lines 2 and 3, its rows start on line 5
+    fn ascii_map(rows: &[&str]) -> String {
+        format!("snake-map 1\nname: Test\nedge: wrap\n\n{}\n", rows.join("\n"))
+    }
+
+    fn error_at(text: &str) -> (usize, usize) {
+        let error = parse_map(text).unwrap_err();
+        (error.line, error.column)
+    }
+
+    #[test]
+    fn parses_ascii_rows_into_rects() {
+        let map = parse_map(&ascii_map(&["##..ff..>.", ROW, ROW, ROW, ROW, ROW, ROW, ROW, ROW, ROW])).unwrap();
+        assert_eq!(map.layout.width, 10);
+        assert_eq!(map.layout.height, 10);
+        assert_eq!(map.layout.walls, vec![Rect { x: 0, y: 0, width: 2, height: 1 }]);
+        assert_eq!(map.layout.food_zones, vec![Rect { x: 4, y: 0, width: 2, height: 1 }]);
+        assert_eq!(map.layout.spawn_points, vec![SpawnPoint { x: 8, y: 0, direction: Direction::RIGHT }]);
+    }
+
+    #[test]
+    fn reports_unknown_cells_in_characters() {
+        assert_eq!(error_at(&ascii_map(&[ROW, ROW, "....x....."])), (7, 5));
+        // Ten characters wide, but not ten bytes
+        assert_eq!(error_at(&ascii_map(&[ROW, "..🐍......."])), (6, 3));
+    }
+
+    #[test]
+    fn reports_ragged_rows_where_they_end() {
+        assert_eq!(error_at(&ascii_map(&[ROW, ROW, "........."])), (7, 10));
+        assert_eq!(error_at(&ascii_map(&[ROW, "..........."])), (6, 11));
+    }
+
+    #[test]
+    fn reports_unknown_headers_and_values() {
+        assert_eq!(error_at("snake-map 1\nname: Test\nsize: 10\n\n..........\n"), (3, 1));
+        assert_eq!(error_at("snake-map 1\nname: Tëst\nedge:  bouncy\n\n..........\n"), (3, 8));
+        assert_eq!(error_at("; a comment\nsnake-map one\n"), (2, 11));
+        assert_eq!(error_at("map 1\n"), (1, 1));
+    }
+
+    #[test]
+    fn reports_bad_json_in_characters() {
+        assert_eq!(error_at("{\n  \"name\": \"Test\",\n  \"width\": x\n}"), (3, 12));
+        assert_eq!(error_at("{\"name\": \"Schlänge 🐍\", \"width\": x}"), (1, 33));
+    }
+
+    #[test]
+    fn counts_name_length_in_characters() {
+        let rows = [ROW; 10].join("\n");
+        let name = "🐍".repeat(MAX_MAP_NAME_LENGTH);
+        assert!(parse_map(&format!("snake-map 1\nname: {}\n\n{}\n", name, rows)).is_ok());
+        let error = parse_map(&format!("snake-map 1\nname: {}🐍\n\n{}\n", name, rows)).unwrap_err();
+        assert_eq!((error.line, error.column), (0, 0));
+    }
+
+    #[test]
+    fn rejects_unplayable_maps() {
+        let walled_in = [["#ff#......"].as_slice(), &[ROW; 9]].concat();
+        assert_eq!(parse_map(&ascii_map(&walled_in)).unwrap().layout.food_cells(), vec![(2, 1), (3, 1)]);
+        let walled_over = r#"{"version": 1, "name": "Test", "width": 10, "height": 10,
+            "walls": [{"x": 0, "y": 0, "width": 10, "height": 2}],
+            "food_zones": [{"x": 0, "y": 0, "width": 10, "height": 1}]}"#;
+        assert_eq!(parse_map(walled_over).unwrap_err().message, "There is nowhere for food to spawn");
+        let future = ascii_map(&[ROW; 10]).replace("snake-map 1", "snake-map 2");
+        assert_eq!(parse_map(&future).unwrap_err().message, "Unsupported map version 2, expected 1");
+    }
+}
@@ -0,0 +1,53 @@
+//! Messages between clients and the server, see `handlePlayerMessage` in
+//! server.ts
+
+use serde::{Serialize, Deserialize};
+
+/// Every message either way is one of these, with the payload as a JSON string
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ServerMessage {
+    pub message_type: String,
+    pub player_id: u64,
+    pub message: String,
+    /// The server tick it was sent on, or for inputs the client tick they
+    /// were made on. Clients that keep no tick send 0.
+    #[serde(default)]
+    pub tick: u64,
+}
+
+impl ServerMessage {
+    pub fn new<T: Serialize + ?Sized>(message_type: &str, player_id: u64, payload: &T) -> serde_json::Result<Self> {
+        Ok(Self {
+            message_type: message_type.to_string(),
+            player_id,
+            message: serde_json::to_string(payload)?,
+            tick: 0,
+        })
+    }
+}
+
+/// The nickname and colour chosen on the join screen
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PlayerProfile {
+    pub name: String,
+    pub colour: String,
+}
+
+/// Rooms are joined by id from the room list or by their join code
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct JoinRoomRequest {
+    pub room_id: Option<u64>,
+    pub code: Option<String>,
+}
+
+/// The server's answer to a ping, times are milliseconds on each side's own clock
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct Pong {
+    /// When the ping was sent, as given in the ping
+    pub client_time: f64,
+    /// When the ping was answered, from Date.now() on the server
+    pub server_time: f64,
+    /// The server's tick at that point and when it started
+    pub tick: u64,
+    pub tick_time: f64,
+}
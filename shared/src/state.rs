@@ -0,0 +1,195 @@
+//! The game state as the server sends it, see `player_states` and `map_state`
+//! in types.ts. Field names must match the wire format.
+
+use serde::{Serialize, Deserialize};
+use crate::map_file::MapLayout;
+
+pub type HNum = i8;
+
+// Must match BOUNDARY_THICKNESS on the server
+pub const BOUNDARY_THICKNESS: HNum = 1;
+// Must match NORMAL_TICKS_PER_MOVE on the server
+pub const NORMAL_TICKS_PER_MOVE: u64 = 2;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Direction {
+    UP,
+    DOWN,
+    LEFT,
+    RIGHT,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [Direction::UP, Direction::DOWN, Direction::LEFT, Direction::RIGHT];
+
+    /// The change in coordinates of one step in this direction
+    pub fn delta(&self) -> (HNum, HNum) {
+        match self {
+            Direction::UP => (0, -1),
+            Direction::DOWN => (0, 1),
+            Direction::LEFT => (-1, 0),
+            Direction::RIGHT => (1, 0),
+        }
+    }
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::UP => Direction::DOWN,
+            Direction::DOWN => Direction::UP,
+            Direction::LEFT => Direction::RIGHT,
+            Direction::RIGHT => Direction::LEFT,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct BodySegment {
+    pub x: HNum,
+    pub y: HNum,
+    pub direction: Direction,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerUpKind {
+    Speed,
+    Slow,
+    /// Pass through other snakes
+    Ghost,
+    ShrinkOthers,
+    /// Eat food from a distance
+    Magnet,
+    /// Survive one hit
+    Shield,
+}
+
+/// One snake, `body_segments` runs from the neck to the tail and ends with the head
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PlayerStateData {
+    pub player_id: u64,
+    pub body_segments: Vec<BodySegment>,
+    #[serde(default)]
+    pub invulnerable: bool,
+    #[serde(default)]
+    pub kills: u64,
+    #[serde(default)]
+    pub score: u64,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub colour: String,
+    #[serde(default)]
+    pub team: Option<u64>,
+    #[serde(default)]
+    pub effects: Vec<PowerUpKind>,
+    #[serde(default = "default_ticks_per_move")]
+    pub ticks_per_move: u64,
+    #[serde(default)]
+    pub boosting: bool,
+}
+
+fn default_ticks_per_move() -> u64 {
+    NORMAL_TICKS_PER_MOVE
+}
+
+impl PlayerStateData {
+    pub fn head(&self) -> Option<&BodySegment> {
+        self.body_segments.last()
+    }
+    /// Move one cell the way the client does: the old head becomes the neck
+    /// and the rest of the body follows, leaving the tail where it was when
+    /// growing. Turning back into the neck keeps the snake going straight.
+    pub fn advance(&mut self, layout: &MapLayout, direction: Direction, grow: bool) {
+        let Some(head) = self.body_segments.pop() else {
+            return;
+        };
+        let direction = if !self.body_segments.is_empty() && direction == head.direction.opposite() {
+            head.direction
+        } else {
+            direction
+        };
+        let (x, y) = layout.step(head.x, head.y, direction);
+        self.body_segments.insert(0, BodySegment { direction, ..head });
+        if !grow {
+            self.body_segments.pop();
+        }
+        self.body_segments.push(BodySegment { x, y, direction });
+    }
+    /// Whether the head ran into the snake's own body
+    pub fn hit_itself(&self) -> bool {
+        let Some((head, body)) = self.body_segments.split_last() else {
+            return false;
+        };
+        body.iter().any(|segment| segment.x == head.x && segment.y == head.y)
+    }
+}
+
+/// Inclusive cell bounds of the playable area in battle royale rooms
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct Zone {
+    pub left: HNum,
+    pub top: HNum,
+    pub right: HNum,
+    pub bottom: HNum,
+}
+
+impl Zone {
+    pub fn contains(&self, x: HNum, y: HNum) -> bool {
+        x >= self.left && x <= self.right && y >= self.top && y <= self.bottom
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PowerUp {
+    pub kind: PowerUpKind,
+    pub x: HNum,
+    pub y: HNum,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum FoodKind {
+    Normal,
+    Golden,
+    /// Shrinks the snake that eats it
+    Poison,
+    /// Worth less the longer it lies around
+    Decaying,
+    /// Shed by boosting snakes
+    Pellet,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Food {
+    pub x: HNum,
+    pub y: HNum,
+    pub kind: FoodKind,
+    /// Segments gained, negative for segments lost
+    pub growth: i64,
+    pub score: i64,
+}
+
+impl Food {
+    pub fn normal(x: HNum, y: HNum) -> Self {
+        Self {
+            x,
+            y,
+            kind: FoodKind::Normal,
+            growth: 1,
+            score: 1,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct MapState {
+    pub food_positions: Vec<Food>,
+    #[serde(default)]
+    pub power_ups: Vec<PowerUp>,
+    #[serde(default)]
+    pub zone: Option<Zone>,
+    #[serde(default)]
+    pub next_zone: Option<Zone>,
+    // Missing from servers that only know the classic map
+    #[serde(default)]
+    pub layout: MapLayout,
+}